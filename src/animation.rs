@@ -0,0 +1,374 @@
+use std::ops::{Add, Mul};
+
+use crate::{
+    geometry::{orientation::Orientation, primitives::Vector},
+    lighting::{RadiantFlux, Spectra},
+    scene::scene::{EntityKey, Scene},
+};
+
+/// how a keyframe blends into the one after it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    /// holds the value until the next keyframe
+    Step,
+    Linear,
+    /// catmull-rom through the keyframes on either side, so motion carries through keyframes
+    Cubic,
+    /// starts and stops gently
+    EaseInOut,
+}
+
+#[derive(Clone, Debug)]
+pub struct Keyframe<T> {
+    /// in seconds
+    pub time: f32,
+    pub value: T,
+    /// applies from this keyframe up to the next
+    pub interpolation: Interpolation,
+}
+
+impl<T> Keyframe<T> {
+    pub fn new(time: f32, value: T, interpolation: Interpolation) -> Keyframe<T> {
+        Keyframe {
+            time,
+            value,
+            interpolation,
+        }
+    }
+}
+
+/// anything a track can blend between
+pub trait Animatable: Clone {
+    fn interpolate(a: &Self, b: &Self, t: f32) -> Self;
+    /// the curve between a and b, shaped by their neighbours
+    fn cubic(before: &Self, a: &Self, b: &Self, after: &Self, t: f32) -> Self;
+}
+
+/// the values of one property over time
+#[derive(Clone, Debug)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Animatable> Track<T> {
+    pub fn new(mut keyframes: Vec<Keyframe<T>>) -> Track<T> {
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Track { keyframes }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    /// the value at a time in seconds, holding the first and last keyframes beyond either end
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let keys = &self.keyframes;
+        let first = keys.first()?;
+        let last = keys.last()?;
+        if time <= first.time {
+            return Some(first.value.clone());
+        }
+        if time >= last.time {
+            return Some(last.value.clone());
+        }
+        // the segment that starts at or before the time
+        let i = keys.partition_point(|k| k.time <= time) - 1;
+        let (a, b) = (&keys[i], &keys[i + 1]);
+        let t = (time - a.time) / (b.time - a.time);
+        let value = match a.interpolation {
+            Interpolation::Step => a.value.clone(),
+            Interpolation::Linear => T::interpolate(&a.value, &b.value, t),
+            Interpolation::EaseInOut => T::interpolate(&a.value, &b.value, smoothstep(t)),
+            Interpolation::Cubic => {
+                let before = &keys[i.saturating_sub(1)].value;
+                let after = &keys[(i + 2).min(keys.len() - 1)].value;
+                T::cubic(before, &a.value, &b.value, after, t)
+            }
+        };
+        Some(value)
+    }
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3. - 2. * t)
+}
+
+fn lerp<T>(a: &T, b: &T, t: f32) -> T
+where
+    T: Clone + Add<T, Output = T>,
+    f32: Mul<T, Output = T>,
+{
+    let [wa, wb] = lerp_weights(t);
+    wa * a.clone() + wb * b.clone()
+}
+
+/// uniform catmull-rom, the curve passes through p1 at 0 and p2 at 1
+fn catmull_rom<T>(p0: &T, p1: &T, p2: &T, p3: &T, t: f32) -> T
+where
+    T: Clone + Add<T, Output = T>,
+    f32: Mul<T, Output = T>,
+{
+    let [w0, w1, w2, w3] = catmull_rom_weights(t);
+    w0 * p0.clone() + w1 * p1.clone() + w2 * p2.clone() + w3 * p3.clone()
+}
+
+// the scalar math lives outside the generic functions,
+// inside them the bound on f32 hides its ordinary arithmetic
+fn lerp_weights(t: f32) -> [f32; 2] {
+    [1. - t, t]
+}
+
+fn catmull_rom_weights(t: f32) -> [f32; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        0.5 * (-t3 + 2. * t2 - t),
+        0.5 * (3. * t3 - 5. * t2 + 2.),
+        0.5 * (-3. * t3 + 4. * t2 + t),
+        0.5 * (t3 - t2),
+    ]
+}
+
+impl Animatable for f32 {
+    fn interpolate(a: &f32, b: &f32, t: f32) -> f32 {
+        lerp(a, b, t)
+    }
+    fn cubic(before: &f32, a: &f32, b: &f32, after: &f32, t: f32) -> f32 {
+        catmull_rom(before, a, b, after, t)
+    }
+}
+
+impl Animatable for Vector {
+    fn interpolate(a: &Vector, b: &Vector, t: f32) -> Vector {
+        lerp(a, b, t)
+    }
+    fn cubic(before: &Vector, a: &Vector, b: &Vector, after: &Vector, t: f32) -> Vector {
+        catmull_rom(before, a, b, after, t)
+    }
+}
+
+impl Animatable for Spectra {
+    fn interpolate(a: &Spectra, b: &Spectra, t: f32) -> Spectra {
+        lerp(a, b, t)
+    }
+    fn cubic(before: &Spectra, a: &Spectra, b: &Spectra, after: &Spectra, t: f32) -> Spectra {
+        catmull_rom(before, a, b, after, t)
+    }
+}
+
+impl Animatable for RadiantFlux {
+    fn interpolate(a: &RadiantFlux, b: &RadiantFlux, t: f32) -> RadiantFlux {
+        Spectra::interpolate(&a.0, &b.0, t).into()
+    }
+    fn cubic(
+        before: &RadiantFlux,
+        a: &RadiantFlux,
+        b: &RadiantFlux,
+        after: &RadiantFlux,
+        t: f32,
+    ) -> RadiantFlux {
+        Spectra::cubic(&before.0, &a.0, &b.0, &after.0, t).into()
+    }
+}
+
+/// orientations always travel along the great arc, cubic keyframes only ease in and out of each one
+impl Animatable for Orientation {
+    fn interpolate(a: &Orientation, b: &Orientation, t: f32) -> Orientation {
+        a.slerp(*b, t)
+    }
+    fn cubic(
+        _before: &Orientation,
+        a: &Orientation,
+        b: &Orientation,
+        _after: &Orientation,
+        t: f32,
+    ) -> Orientation {
+        a.slerp(*b, smoothstep(t))
+    }
+}
+
+/// the properties that can be animated
+#[derive(Clone, Debug)]
+pub enum Channel {
+    Position(Track<Vector>),
+    Orientation(Track<Orientation>),
+    Scale(Track<Vector>),
+    /// lights only
+    RadiantFlux(Track<RadiantFlux>),
+    /// cameras only, in meters
+    FocalLength(Track<f32>),
+}
+
+/// a track attached to an entity
+#[derive(Clone, Debug)]
+pub struct Animation {
+    pub target: EntityKey,
+    pub channel: Channel,
+}
+
+impl Animation {
+    pub fn new(target: EntityKey, channel: Channel) -> Animation {
+        Animation { target, channel }
+    }
+}
+
+/// poses every animated entity as it is at a time in seconds
+/// animations whose target has been removed are skipped
+pub fn animate(scene: &mut Scene, time: f32) {
+    let animations = std::mem::take(&mut scene.animations);
+    for animation in &animations {
+        let Some(entity) = scene.try_get_mut(animation.target) else {
+            continue;
+        };
+        match &animation.channel {
+            Channel::Position(track) => {
+                if let Some(position) = track.sample(time) {
+                    entity.set_position(position);
+                }
+            }
+            Channel::Orientation(track) => {
+                if let Some(orientation) = track.sample(time) {
+                    entity.set_orientation(orientation);
+                }
+            }
+            Channel::Scale(track) => {
+                if let Some(scale) = track.sample(time) {
+                    entity.set_scale(scale);
+                }
+            }
+            Channel::RadiantFlux(track) => {
+                if let (Some(flux), Some(light)) = (track.sample(time), entity.as_light_mut()) {
+                    light.set_radiant_flux(flux);
+                }
+            }
+            Channel::FocalLength(track) => {
+                if let (Some(focal_length), Some(camera)) =
+                    (track.sample(time), entity.as_camera_mut())
+                {
+                    camera.lens.focal_length = focal_length;
+                }
+            }
+        }
+    }
+    scene.animations = animations;
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::{
+        application::application,
+        geometry::{
+            orientation::{Orientation, J},
+            primitives::Vector,
+        },
+        lighting::{const_spectra, PointLight},
+        object::Object,
+        scene::scene::{Removal, Scene},
+    };
+
+    use super::*;
+
+    fn track(interpolation: Interpolation) -> Track<f32> {
+        Track::new(vec![
+            Keyframe::new(2., 10., interpolation),
+            Keyframe::new(0., 0., interpolation),
+            Keyframe::new(1., 1., interpolation),
+            Keyframe::new(3., 12., interpolation),
+        ])
+    }
+
+    #[test]
+    fn curves() {
+        let linear = track(Interpolation::Linear);
+        assert_eq!(linear.sample(-1.), Some(0.));
+        assert_eq!(linear.sample(5.), Some(12.));
+        assert_abs_diff_eq!(linear.sample(1.5).unwrap(), 5.5);
+
+        let step = track(Interpolation::Step);
+        assert_eq!(step.sample(1.99), Some(1.));
+        assert_eq!(step.sample(2.), Some(10.));
+
+        let eased = track(Interpolation::EaseInOut);
+        assert_abs_diff_eq!(eased.sample(1.5).unwrap(), 5.5);
+        assert!(eased.sample(1.1).unwrap() < linear.sample(1.1).unwrap());
+
+        let cubic = track(Interpolation::Cubic);
+        for key in cubic.keyframes() {
+            assert_abs_diff_eq!(cubic.sample(key.time).unwrap(), key.value);
+        }
+        // overshoots toward where the motion is heading
+        assert!(cubic.sample(2.5).unwrap() > linear.sample(2.5).unwrap());
+
+        assert_eq!(Track::<f32>::new(Vec::new()).sample(0.), None);
+    }
+
+    #[test]
+    fn application_evaluates_the_tick() {
+        let mut scene = Scene::default();
+        scene.frame_rate = 10.;
+        let object = scene.push_object(Object::default());
+        let light = scene.push_simple_light(PointLight::default());
+        let camera = scene.active_camera_key();
+        let turned = Orientation::from_axis_angle(1., J);
+        scene.animations = vec![
+            Animation::new(
+                object,
+                Channel::Position(Track::new(vec![
+                    Keyframe::new(0., Vector::new(0., 0., 0.), Interpolation::Linear),
+                    Keyframe::new(2., Vector::new(4., 0., 0.), Interpolation::Linear),
+                ])),
+            ),
+            Animation::new(
+                object,
+                Channel::Orientation(Track::new(vec![
+                    Keyframe::new(0., Orientation::default(), Interpolation::Linear),
+                    Keyframe::new(2., turned, Interpolation::Linear),
+                ])),
+            ),
+            Animation::new(
+                light,
+                Channel::RadiantFlux(Track::new(vec![
+                    Keyframe::new(0., const_spectra(0.).into(), Interpolation::Linear),
+                    Keyframe::new(1., const_spectra(8.).into(), Interpolation::Linear),
+                ])),
+            ),
+            Animation::new(
+                camera,
+                Channel::FocalLength(Track::new(vec![
+                    Keyframe::new(0., 0.02, Interpolation::Step),
+                    Keyframe::new(1., 0.08, Interpolation::Step),
+                ])),
+            ),
+        ];
+
+        // frame 5 at 10 fps is half a second in
+        scene.tick = 5;
+        application(&mut scene);
+        assert_abs_diff_eq!(scene.get(object).get_position().x, 1.);
+        let expected = Orientation::from_axis_angle(0.25, J);
+        assert_abs_diff_eq!(
+            scene.get(object).get_orientation().y,
+            expected.y,
+            epsilon = 1e-5
+        );
+        let (_, light) = scene.simple_lights().next().unwrap();
+        assert_abs_diff_eq!(
+            light.radiant_intensity(Vector::ones()).0.spectra[0],
+            4. / (4. * std::f32::consts::PI),
+            epsilon = 1e-5
+        );
+        assert_abs_diff_eq!(scene.active_camera().lens.focal_length, 0.02);
+
+        // any time in seconds works too, not just whole frames
+        animate(&mut scene, 1.25);
+        assert_abs_diff_eq!(scene.get(object).get_position().x, 2.5);
+        assert_abs_diff_eq!(scene.active_camera().lens.focal_length, 0.08);
+
+        // removing an entity takes its animations with it
+        scene.remove(object, Removal::Recursive);
+        assert_eq!(scene.animations.len(), 2);
+        animate(&mut scene, 0.);
+    }
+}
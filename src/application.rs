@@ -1,43 +1,12 @@
+use crate::animation::animate;
 use crate::scene::scene::Scene;
 
 /// all of the stuff that should happen to run the scene,
 /// note: this is recomputed every frame. Not a major performance bottleneck
 pub fn application(scene: &mut Scene) -> &Scene {
-    // let tick = scene.tick as f32;
-    // let objects: &mut Vec<Object> = &mut scene.objects;
-
-    // let _lights = &mut scene.lights;
-    // // lights.index_mut(0).se();
-
-    // objects
-    //     .index_mut(0)
-    //     .meshes
-    //     .index_mut(0)
-    //     .add_transform(build_arbitrary_rotation_transform(
-    //         tick.to_radians(),
-    //         Vector::new(0., 0., -1.),
-    //     ));
-
-    // objects
-    //     .index_mut(0)
-    //     .meshes
-    //     .index_mut(0)
-    //     .add_transform(build_arbitrary_rotation_transform(
-    //         tick.to_radians(),
-    //         Vector::new(1., 1., 1.),
-    //     ));
-
-    // objects
-    //     .index_mut(1)
-    //     .meshes
-    //     .index_mut(0)
-    //     .add_transform(build_y_rotation_transform(tick.to_radians() * -3.));
-
-    // objects
-    //     .index_mut(2)
-    //     .meshes
-    //     .index_mut(0)
-    //     .add_transform(build_y_rotation_transform(tick.to_radians() * 2.));
+    // every tracked entity is posed for the current frame
+    let time = scene.tick as f32 / scene.frame_rate;
+    animate(scene, time);
 
     scene
 }
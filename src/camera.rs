@@ -1,6 +1,7 @@
 #![allow(nonstandard_style)]
 
-use rand::{rngs::ThreadRng, Rng};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 // use crate::coordinate_space::Orientation;
 use crate::{
@@ -9,6 +10,7 @@ use crate::{
         orientation::{Orientation, UP},
         primitives::{Ray, Vector, ORIGIN},
     },
+    random::Pcg32,
     scene::scene::EntityKey,
 };
 
@@ -28,6 +30,8 @@ pub struct Camera {
     pub scale: Vector,
     pub children: Vec<EntityKey>,
     pub parent: Option<EntityKey>,
+    pub name: Option<String>,
+    pub tags: Vec<String>,
 }
 
 impl Default for Camera {
@@ -43,11 +47,16 @@ impl Default for Camera {
             orientation: UP,
             scale: Vector::ones(),
             parent: None,
+            name: None,
+            tags: Vec::new(),
         }
     }
 }
 
 impl Entity for Camera {
+    fn clone_entity(&self) -> Box<dyn Entity> {
+        Box::new(self.clone())
+    }
     fn get_position(&self) -> Vector {
         self.position
     }
@@ -66,6 +75,33 @@ impl Entity for Camera {
     fn set_parent(&mut self, parent: EntityKey) {
         self.parent = Some(parent);
     }
+    fn get_parent(&self) -> Option<EntityKey> {
+        self.parent
+    }
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+    fn set_position(&mut self, position: Vector) {
+        self.position = position;
+    }
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+    fn set_scale(&mut self, scale: Vector) {
+        self.scale = scale;
+    }
+    fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_string());
+    }
+    fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+    fn get_mut_tags(&mut self) -> &mut Vec<String> {
+        &mut self.tags
+    }
     fn as_camera(&self) -> Option<&Camera> {
         Some(self)
     }
@@ -133,15 +169,15 @@ impl Camera {
             // z is negative, and if the ray placement is scaled to one from the sensor width,
             // the focal length needs to be proportional
         };
-        // let position = ORIGIN;
-        let position = camera.position;
+        // camera space, the scene moves it to wherever the camera ends up in the world
+        let position = ORIGIN;
         let ray = Ray::new(position, direction);
         ray.direction.unitized();
         ray
     }
 
     /// yeah, the math was hard for me too 2023-11-20
-    pub fn jittered_pixel_to_ray(camera: &Self, x: u32, y: u32, rng: &mut ThreadRng) -> Ray {
+    pub fn jittered_pixel_to_ray(camera: &Self, x: u32, y: u32, rng: &mut Pcg32) -> Ray {
         let x_jitter: f32 = rng.gen();
         let y_jitter: f32 = rng.gen();
         let x: f32 = (x as f32) + x_jitter; // centers the pixels
@@ -166,8 +202,8 @@ impl Camera {
             // z is negative, and if the ray placement is scaled to one from the sensor width,
             // the focal length needs to be proportional
         };
-        // let position = ORIGIN;
-        let position = camera.position;
+        // camera space, the scene moves it to wherever the camera ends up in the world
+        let position = ORIGIN;
         let ray = Ray::new(position, direction);
         ray.direction.unitized();
         ray
@@ -176,7 +212,8 @@ impl Camera {
 
 /// models a camera lens
 /// defines the field of view
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Lens {
     /// ƒ-stop is focal length / aperture pupil diameter <https://www.wikiwand.com/en/F-number>
     pub aperture: f32,
@@ -210,7 +247,8 @@ impl Default for Lens {
 /// models a camera sensor
 /// notably used to accurately model the frequency response
 /// I would like to implement a number of predefined lenses in addition to offering a generic
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Sensor {
     /// the width of the sensor in millimeters
     pub width: f32,
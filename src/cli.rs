@@ -0,0 +1,484 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use image::{ImageBuffer, ImageError, ImageFormat, RgbImage};
+
+use graphics_v3::color::draw_chromaticity_diagram::coloring_book;
+use graphics_v3::render_observer::{ProgressBar, ProgressiveFile};
+use graphics_v3::scene::scene::{Rendermode, ShaderMode};
+use graphics_v3::scene::scenes::{cornell_scene, simple_scene};
+use graphics_v3::{
+    load_gltf, load_scene_file, render_observed, Film, LoadError, RenderObserver, Scene,
+};
+
+/// the scenes that can be asked for by name instead of by file
+const BUILT_IN_SCENES: [&str; 2] = ["cornell", "simple"];
+
+/// None when there's no built-in scene by that name
+fn built_in_scene(name: &str) -> Option<Result<Scene, LoadError>> {
+    match name {
+        "cornell" => Some(cornell_scene()),
+        "simple" => Some(simple_scene()),
+        _ => None,
+    }
+}
+
+/// a spectral renderer
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// renders one frame of a scene
+    Render {
+        #[command(flatten)]
+        scene: SceneArgs,
+        #[command(flatten)]
+        progress: ProgressArgs,
+        /// the format is taken from the extension unless --format says otherwise
+        #[arg(short, long, default_value = "render.png")]
+        output: PathBuf,
+        #[arg(long)]
+        format: Option<OutputFormat>,
+        /// the frame to animate the scene to
+        #[arg(long, default_value_t = 0)]
+        frame: u32,
+    },
+    /// renders a range of frames of a scene into a directory, one numbered image per frame
+    Animate {
+        #[command(flatten)]
+        scene: SceneArgs,
+        #[command(flatten)]
+        progress: ProgressArgs,
+        #[arg(short, long, default_value = "animation")]
+        output: PathBuf,
+        #[arg(long, default_value = "png")]
+        format: OutputFormat,
+        #[arg(long, default_value_t = 0)]
+        start: u32,
+        /// the last frame, inclusive
+        #[arg(long)]
+        end: u32,
+    },
+    /// draws the CIE 1931 chromaticity diagram
+    Chromaticity {
+        #[arg(short, long, default_value = "color_gamut.png")]
+        output: PathBuf,
+        #[arg(long)]
+        format: Option<OutputFormat>,
+        /// the width and height of the image in pixels
+        #[arg(long, default_value_t = 1000)]
+        size: u32,
+    },
+}
+
+/// which scene to render and anything about it to override
+#[derive(Args, Debug)]
+pub struct SceneArgs {
+    /// a built-in scene (cornell, simple) or a scene file (.toml, .gltf, .glb)
+    pub scene: String,
+    /// width and height in pixels, as WIDTHxHEIGHT
+    #[arg(short, long, value_parser = parse_resolution)]
+    pub resolution: Option<(u32, u32)>,
+    /// rays per pixel
+    #[arg(short, long)]
+    pub samples: Option<u32>,
+    /// how many times a ray may bounce
+    #[arg(long)]
+    pub max_trace_depth: Option<u32>,
+    #[arg(long)]
+    pub mode: Option<Rendermode>,
+    #[arg(long)]
+    pub shader: Option<ShaderMode>,
+    /// worker threads for threaded ray tracing, 0 uses every core
+    #[arg(short, long)]
+    pub threads: Option<usize>,
+    /// the same seed gives the same image, however many threads render it
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+impl SceneArgs {
+    /// loads the scene and applies the overrides to it
+    pub fn scene(&self) -> Result<Scene, CliError> {
+        let mut scene = load_scene(&self.scene)?;
+        if let Some((horizontal_res, vertical_res)) = self.resolution {
+            let sensor = &mut scene.active_camera_mut().sensor;
+            sensor.horizontal_res = horizontal_res;
+            sensor.vertical_res = vertical_res;
+        }
+        if let Some(samples) = self.samples {
+            scene.samples = samples;
+        }
+        if let Some(max_trace_depth) = self.max_trace_depth {
+            scene.max_trace_depth = max_trace_depth;
+        }
+        if let Some(mode) = &self.mode {
+            scene.rendermode = mode.clone();
+        }
+        if let Some(shader) = &self.shader {
+            scene.shadermode = shader.clone();
+        }
+        if let Some(threads) = self.threads {
+            scene.threads = threads;
+        }
+        if let Some(seed) = self.seed {
+            scene.seed = seed;
+        }
+        Ok(scene)
+    }
+}
+
+/// what to show while a render is going
+#[derive(Args, Debug)]
+pub struct ProgressArgs {
+    /// no progress bar
+    #[arg(short, long)]
+    pub quiet: bool,
+    /// also writes the image so far here as tiles finish
+    #[arg(long)]
+    pub partial: Option<PathBuf>,
+    /// milliseconds between writes of the partial image
+    #[arg(long, default_value_t = 1000)]
+    pub partial_interval: u64,
+}
+
+impl ProgressArgs {
+    fn partial_file(&self) -> Result<Option<ProgressiveFile>, CliError> {
+        let Some(path) = &self.partial else {
+            return Ok(None);
+        };
+        let format = OutputFormat::choose(None, path)?;
+        Ok(Some(ProgressiveFile::new(
+            path,
+            format.image_format(),
+            Duration::from_millis(self.partial_interval),
+        )))
+    }
+}
+
+/// the image formats renders can be saved as
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Png,
+    Jpeg,
+    Bmp,
+    Tiff,
+    Tga,
+    Pnm,
+}
+
+impl OutputFormat {
+    pub fn from_path(path: &Path) -> Option<OutputFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(OutputFormat::Png),
+            "jpg" | "jpeg" => Some(OutputFormat::Jpeg),
+            "bmp" => Some(OutputFormat::Bmp),
+            "tif" | "tiff" => Some(OutputFormat::Tiff),
+            "tga" => Some(OutputFormat::Tga),
+            "pnm" | "ppm" => Some(OutputFormat::Pnm),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Bmp => "bmp",
+            OutputFormat::Tiff => "tiff",
+            OutputFormat::Tga => "tga",
+            OutputFormat::Pnm => "ppm",
+        }
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            OutputFormat::Png => ImageFormat::Png,
+            OutputFormat::Jpeg => ImageFormat::Jpeg,
+            OutputFormat::Bmp => ImageFormat::Bmp,
+            OutputFormat::Tiff => ImageFormat::Tiff,
+            OutputFormat::Tga => ImageFormat::Tga,
+            OutputFormat::Pnm => ImageFormat::Pnm,
+        }
+    }
+
+    /// --format if it was given, otherwise whatever the extension says
+    fn choose(format: Option<OutputFormat>, path: &Path) -> Result<OutputFormat, CliError> {
+        format
+            .or_else(|| OutputFormat::from_path(path))
+            .ok_or_else(|| CliError::UnknownFormat(path.to_path_buf()))
+    }
+}
+
+/// everything that stops the command line from finishing, each is reported and exits with a failure
+#[derive(Debug)]
+pub enum CliError {
+    /// the scene file couldn't be loaded
+    Load(LoadError),
+    /// neither a built-in scene nor a file type scenes are read from
+    UnknownScene(String),
+    /// no --format, and an extension that doesn't say
+    UnknownFormat(PathBuf),
+    /// an animation told to end before it starts
+    BackwardFrames {
+        start: u32,
+        end: u32,
+    },
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Image {
+        path: PathBuf,
+        error: ImageError,
+    },
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Load(error) => write!(f, "{error}"),
+            CliError::UnknownScene(scene) => write!(
+                f,
+                "`{scene}` isn't a built-in scene ({}) or a scene file (.toml, .gltf, .glb)",
+                BUILT_IN_SCENES.join(", ")
+            ),
+            CliError::UnknownFormat(path) => write!(
+                f,
+                "can't tell what format to save {} as, give it an extension or use --format",
+                path.display()
+            ),
+            CliError::BackwardFrames { start, end } => write!(
+                f,
+                "the animation ends at frame {end}, before it starts at frame {start}"
+            ),
+            CliError::Io { path, error } => write!(f, "{}: {error}", path.display()),
+            CliError::Image { path, error } => write!(f, "{}: {error}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<LoadError> for CliError {
+    fn from(error: LoadError) -> Self {
+        CliError::Load(error)
+    }
+}
+
+/// WIDTHxHEIGHT, both above zero
+fn parse_resolution(text: &str) -> Result<(u32, u32), String> {
+    let (width, height) = text
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("`{text}` should look like 640x480"))?;
+    let parse = |side: &str| match side.trim().parse::<u32>() {
+        Ok(0) | Err(_) => Err(format!("`{side}` isn't a number of pixels")),
+        Ok(pixels) => Ok(pixels),
+    };
+    Ok((parse(width)?, parse(height)?))
+}
+
+/// a built-in scene by name, otherwise a file by its extension
+pub fn load_scene(scene: &str) -> Result<Scene, CliError> {
+    if let Some(built_in) = built_in_scene(scene) {
+        return Ok(built_in?);
+    }
+    let extension = Path::new(scene)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("toml") => Ok(load_scene_file(scene.to_string())?),
+        Some("gltf" | "glb") => {
+            let (scene, warnings) = load_gltf(scene.to_string())?;
+            // the scene still renders, so these are only worth a mention
+            for warning in warnings {
+                eprintln!("warning: {warning}");
+            }
+            Ok(scene)
+        }
+        _ => Err(CliError::UnknownScene(scene.to_string())),
+    }
+}
+
+fn save(film: &Film, path: &Path, format: OutputFormat) -> Result<(), CliError> {
+    film.save(path, format.image_format())
+        .map_err(|error| CliError::Image {
+            path: path.to_path_buf(),
+            error,
+        })
+}
+
+/// renders the scene at the given frame
+fn render_frame(mut scene: Scene, frame: u32, progress: &ProgressArgs) -> Result<Film, CliError> {
+    scene.tick = frame;
+    let mut partial = progress.partial_file()?;
+    let mut bar = ProgressBar::new();
+    let mut observers: Vec<&mut dyn RenderObserver> = Vec::new();
+    if let Some(partial) = &mut partial {
+        observers.push(partial);
+    }
+    if !progress.quiet {
+        observers.push(&mut bar);
+    }
+    let film = render_observed(&scene, &mut observers);
+    // the render itself is fine, so this is only worth a mention
+    if let Some(ProgressiveFile {
+        path,
+        error: Some(error),
+        ..
+    }) = &partial
+    {
+        eprintln!("warning: {}: {error}", path.display());
+    }
+    Ok(film)
+}
+
+pub fn run(cli: Cli) -> Result<(), CliError> {
+    match cli.command {
+        Command::Render {
+            scene,
+            progress,
+            output,
+            format,
+            frame,
+        } => {
+            // checked first so a bad name doesn't cost a render
+            let format = OutputFormat::choose(format, &output)?;
+            let film = render_frame(scene.scene()?, frame, &progress)?;
+            save(&film, &output, format)
+        }
+        Command::Animate {
+            scene,
+            progress,
+            output,
+            format,
+            start,
+            end,
+        } => {
+            if start > end {
+                return Err(CliError::BackwardFrames { start, end });
+            }
+            // every frame starts from the scene as loaded, the tick poses it
+            let scene = scene.scene()?;
+            fs::create_dir_all(&output).map_err(|error| CliError::Io {
+                path: output.clone(),
+                error,
+            })?;
+            for frame in start..=end {
+                let film = render_frame(scene.clone(), frame, &progress)?;
+                let path = output.join(format!("{frame:04}.{}", format.extension()));
+                save(&film, &path, format)?;
+            }
+            Ok(())
+        }
+        Command::Chromaticity {
+            output,
+            format,
+            size,
+        } => {
+            let format = OutputFormat::choose(format, &output)?;
+            let mut canvas: RgbImage = ImageBuffer::new(size, size);
+            coloring_book(&mut canvas);
+            save(&Film::from(canvas), &output, format)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use clap::Parser;
+
+    use graphics_v3::scene::scene::Rendermode;
+
+    use super::{load_scene, parse_resolution, run, Cli, CliError, Command, OutputFormat};
+
+    #[test]
+    fn overrides_apply_to_the_scene() {
+        let cli = Cli::try_parse_from([
+            "graphics_v3",
+            "render",
+            "simple",
+            "--resolution",
+            "64x48",
+            "--samples",
+            "3",
+            "--mode",
+            "ray_trace",
+            "--threads",
+            "2",
+            "--seed",
+            "7",
+            "-o",
+            "out.jpg",
+        ])
+        .unwrap();
+        let Command::Render { scene, output, .. } = cli.command else {
+            panic!("expected a render");
+        };
+        assert_eq!(OutputFormat::from_path(&output), Some(OutputFormat::Jpeg));
+        let scene = scene.scene().unwrap();
+        assert_eq!(scene.active_camera().sensor.res(), (64, 48));
+        assert_eq!(scene.samples, 3);
+        assert_eq!(scene.rendermode, Rendermode::_RayTrace);
+        assert_eq!(scene.threads, 2);
+        assert_eq!(scene.seed, 7);
+        // anything not given is left as the scene had it
+        assert_eq!(scene.max_trace_depth, 5);
+    }
+
+    #[test]
+    fn mistakes_are_reported() {
+        assert!(parse_resolution("640x480").is_ok());
+        assert!(parse_resolution("640").is_err());
+        assert!(parse_resolution("0x480").is_err());
+        assert!(Cli::try_parse_from(["graphics_v3", "animate", "simple"]).is_err());
+        assert!(Cli::try_parse_from(["graphics_v3", "render", "simple", "--mode", "x"]).is_err());
+        let backward = Cli::try_parse_from([
+            "graphics_v3",
+            "animate",
+            "simple",
+            "--start",
+            "5",
+            "--end",
+            "2",
+        ])
+        .unwrap();
+        let error = run(backward).err().unwrap();
+        assert!(matches!(
+            error,
+            CliError::BackwardFrames { start: 5, end: 2 }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "the animation ends at frame 2, before it starts at frame 5"
+        );
+
+        let error = load_scene("teapot").err().unwrap();
+        assert!(matches!(error, CliError::UnknownScene(_)));
+        assert_eq!(
+            error.to_string(),
+            "`teapot` isn't a built-in scene (cornell, simple) or a scene file (.toml, .gltf, .glb)"
+        );
+        assert!(matches!(
+            load_scene("scenes/missing.toml"),
+            Err(CliError::Load(_))
+        ));
+        assert_eq!(OutputFormat::from_path(Path::new("render")), None);
+        assert_eq!(
+            OutputFormat::from_path(Path::new("a.PNG")),
+            Some(OutputFormat::Png)
+        );
+    }
+}
@@ -2,3 +2,4 @@ pub mod cie_color_matching_functions;
 pub mod colorspace_conversion;
 pub mod draw_chromaticity_diagram;
 pub mod luminous_efficiency;
+pub mod rgb_to_spectra;
@@ -0,0 +1,123 @@
+use crate::lighting::{black_spectra, Spectra};
+
+// Smits, "An RGB to Spectrum Conversion for Reflectances" (1999)
+// ten bins spread evenly from 380nm to 720nm
+const SMITS_FIRST_NM: f32 = 380.;
+const SMITS_LAST_NM: f32 = 720.;
+const SMITS_WHITE: [f32; 10] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [f32; 10] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [f32; 10] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [f32; 10] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [f32; 10] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [f32; 10] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [f32; 10] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+/// keeps the reflectance spectra a hair below one, the basis slightly overshoots it
+const REFLECTANCE_SCALE: f32 = 0.94;
+
+/// turns a linear RGB reflectance into a smooth spectrum with the same color
+/// as much as possible is built from white, then the secondary and primary that make up the rest
+/// values beyond 720nm hold the last bin
+pub fn rgb_to_spectra(rgb: (f32, f32, f32)) -> Spectra {
+    let (r, g, b) = rgb;
+    let mut bins = [0.; 10];
+    let mut add = |weight: f32, basis: &[f32; 10]| {
+        for (bin, value) in bins.iter_mut().zip(basis) {
+            *bin += weight * value;
+        }
+    };
+    if r <= g && r <= b {
+        add(r, &SMITS_WHITE);
+        if g <= b {
+            add(g - r, &SMITS_CYAN);
+            add(b - g, &SMITS_BLUE);
+        } else {
+            add(b - r, &SMITS_CYAN);
+            add(g - b, &SMITS_GREEN);
+        }
+    } else if g <= r && g <= b {
+        add(g, &SMITS_WHITE);
+        if r <= b {
+            add(r - g, &SMITS_MAGENTA);
+            add(b - r, &SMITS_BLUE);
+        } else {
+            add(b - g, &SMITS_MAGENTA);
+            add(r - b, &SMITS_RED);
+        }
+    } else {
+        add(b, &SMITS_WHITE);
+        if r <= g {
+            add(r - b, &SMITS_YELLOW);
+            add(g - r, &SMITS_GREEN);
+        } else {
+            add(g - b, &SMITS_YELLOW);
+            add(r - g, &SMITS_RED);
+        }
+    }
+
+    let mut spectra = black_spectra();
+    let bin_width = (SMITS_LAST_NM - SMITS_FIRST_NM) / 9.;
+    for (i, value) in spectra.spectra.iter_mut().enumerate() {
+        let λ = i as f32 * 10. + 380.;
+        let position = ((λ - SMITS_FIRST_NM) / bin_width).min(9.);
+        let below = position.floor() as usize;
+        let above = (below + 1).min(9);
+        let t = position - below as f32;
+        let smooth = (1. - t) * bins[below] + t * bins[above];
+        *value = (REFLECTANCE_SCALE * smooth).max(0.);
+    }
+    spectra
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::color::colorspace_conversion::{spectra_to_CIEXYZ, CIEXYZ_to_xyY};
+
+    use super::rgb_to_spectra;
+
+    fn chromaticity(rgb: (f32, f32, f32)) -> (f32, f32) {
+        let (x, y, _) = CIEXYZ_to_xyY(spectra_to_CIEXYZ(&rgb_to_spectra(rgb)));
+        (x, y)
+    }
+
+    #[test]
+    fn greys_are_flat() {
+        for grey in [0., 0.18, 0.5, 1.] {
+            let spectra = rgb_to_spectra((grey, grey, grey));
+            for value in spectra.spectra.iter() {
+                assert_abs_diff_eq!(*value, 0.94 * grey, epsilon = 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn primaries_keep_their_hue() {
+        let (white_x, white_y) = chromaticity((1., 1., 1.));
+        let (red_x, _) = chromaticity((0.8, 0.1, 0.1));
+        let (green_x, green_y) = chromaticity((0.1, 0.8, 0.1));
+        let (blue_x, blue_y) = chromaticity((0.1, 0.1, 0.8));
+        assert!(red_x > white_x + 0.1);
+        assert!(green_y > white_y + 0.1);
+        assert!(green_x < white_x);
+        assert!(blue_x < white_x && blue_y < white_y);
+
+        let red = rgb_to_spectra((0.8, 0.1, 0.1));
+        assert!(red.from_λ(650.) > 5. * red.from_λ(500.));
+    }
+}
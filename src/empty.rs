@@ -14,6 +14,8 @@ pub struct Empty {
     pub scale: Vector,
     pub children: Vec<EntityKey>,
     pub parent: Option<EntityKey>,
+    pub name: Option<String>,
+    pub tags: Vec<String>,
 }
 
 impl Default for Empty {
@@ -24,10 +26,15 @@ impl Default for Empty {
             scale: Vector::ones(),
             children: Vec::new(),
             parent: None,
+            name: None,
+            tags: Vec::new(),
         }
     }
 }
 impl Entity for Empty {
+    fn clone_entity(&self) -> Box<dyn Entity> {
+        Box::new(self.clone())
+    }
     fn get_position(&self) -> Vector {
         self.position
     }
@@ -43,6 +50,33 @@ impl Entity for Empty {
     fn set_parent(&mut self, parent: EntityKey) {
         self.parent = Some(parent);
     }
+    fn get_parent(&self) -> Option<EntityKey> {
+        self.parent
+    }
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+    fn set_position(&mut self, position: Vector) {
+        self.position = position;
+    }
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+    fn set_scale(&mut self, scale: Vector) {
+        self.scale = scale;
+    }
+    fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_string());
+    }
+    fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+    fn get_mut_tags(&mut self) -> &mut Vec<String> {
+        &mut self.tags
+    }
     fn get_children(&self) -> Vec<EntityKey> {
         self.children.clone()
     }
@@ -1,6 +1,12 @@
 use crate::{
     camera::Camera,
-    geometry::{orientation::Orientation, primitives::Vector},
+    geometry::{
+        orientation::Orientation,
+        primitives::Vector,
+        transformations::{
+            build_scale_transform, build_translation_transform, compile_transforms, Transform,
+        },
+    },
     lighting::Light,
     object::Object,
     scene::scene::EntityKey,
@@ -11,14 +17,43 @@ pub trait Entity: Sync + Send {
     fn get_orientation(&self) -> Orientation;
     fn get_scale(&self) -> Vector;
     fn set_parent(&mut self, parent: EntityKey) -> ();
+    fn get_parent(&self) -> Option<EntityKey>;
+    /// detaches from the parent without touching the parent's children
+    fn clear_parent(&mut self);
+    fn set_position(&mut self, position: Vector);
+    fn set_orientation(&mut self, orientation: Orientation);
+    fn set_scale(&mut self, scale: Vector);
+    fn get_name(&self) -> Option<&str>;
+    fn set_name(&mut self, name: &str);
+    fn get_tags(&self) -> &[String];
+    fn get_mut_tags(&mut self) -> &mut Vec<String>;
+    fn add_tag(&mut self, tag: &str) {
+        if !self.has_tag(tag) {
+            self.get_mut_tags().push(tag.to_string());
+        }
+    }
+    fn has_tag(&self, tag: &str) -> bool {
+        self.get_tags().iter().any(|t| t == tag)
+    }
     fn get_children(&self) -> Vec<EntityKey>;
     fn get_mut_children(&mut self) -> &mut Vec<EntityKey>;
     fn add_child(&mut self, child: EntityKey) {
         self.get_mut_children().push(child);
     }
+    /// relative to the parent: scaled first, then rotated, then moved into place
+    fn get_local_transform(&self) -> Transform {
+        compile_transforms(&vec![
+            build_scale_transform(self.get_scale()),
+            self.get_orientation().to_transform(),
+            build_translation_transform(self.get_position()),
+        ])
+    }
     fn as_light(&self) -> Option<&dyn Light> {
         None
     }
+    fn as_light_mut(&mut self) -> Option<&mut dyn Light> {
+        None
+    }
     fn as_object(&self) -> Option<&Object> {
         None
     }
@@ -31,6 +66,14 @@ pub trait Entity: Sync + Send {
     fn as_camera_mut(&mut self) -> Option<&mut Camera> {
         None
     }
+    /// a copy in a box of its own, so scenes can be cloned
+    fn clone_entity(&self) -> Box<dyn Entity>;
     // fn get_transforms(&self) -> &Vec<Transform>;
     // fn append_transforms(&self) -> &Vec<Transform>; // add position and scale and shit to log
 }
+
+impl Clone for Box<dyn Entity> {
+    fn clone(&self) -> Self {
+        self.clone_entity()
+    }
+}
@@ -0,0 +1,49 @@
+use std::path::Path;
+
+use image::{ImageBuffer, ImageFormat, ImageResult, Rgb, RgbImage};
+
+/// what a render leaves behind, one color per pixel of the camera's sensor
+#[derive(Clone, Debug, PartialEq)]
+pub struct Film {
+    image: RgbImage,
+}
+
+impl Film {
+    /// black all over
+    pub fn new(width: u32, height: u32) -> Film {
+        Film {
+            image: ImageBuffer::new(width, height),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.image.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.image.height()
+    }
+
+    /// x from the left, y from the top
+    pub fn pixel(&self, x: u32, y: u32) -> Rgb<u8> {
+        *self.image.get_pixel(x, y)
+    }
+
+    pub fn image(&self) -> &RgbImage {
+        &self.image
+    }
+
+    pub fn into_image(self) -> RgbImage {
+        self.image
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>, format: ImageFormat) -> ImageResult<()> {
+        self.image.save_with_format(path, format)
+    }
+}
+
+impl From<RgbImage> for Film {
+    fn from(image: RgbImage) -> Self {
+        Film { image }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod orientation;
 pub mod primitives;
 pub mod transformations;
+pub mod triangulation;
@@ -1,9 +1,19 @@
-use crate::geometry::primitives::Vector;
+use crate::geometry::{
+    primitives::Vector,
+    transformations::{
+        build_arbitrary_rotation_transform, build_quaternion_rotation_transform, Transform,
+    },
+};
 
-#[derive(Clone, Debug, Copy)]
+/// a rotation, stored as the unit quaternion w + x i + y j + z k
+/// everything is modeled facing UP, which is no rotation at all
+/// cameras look down -Z with +Y as the top of the frame
+#[derive(Clone, Debug, Copy, PartialEq)]
 pub struct Orientation {
-    _top: Vector,
-    _front: Vector,
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
 }
 
 // Basis Vectors
@@ -19,8 +29,8 @@ pub const J: Vector = Vector {
 };
 pub const _K: Vector = Vector {
     x: 0.,
-    y: 1.,
-    z: 0.,
+    y: 0.,
+    z: 1.,
 };
 pub const _NI: Vector = Vector {
     x: -1.,
@@ -39,12 +49,274 @@ pub const _NK: Vector = Vector {
 };
 
 // oriented
-pub const RIGHT: Orientation = Orientation { _top: J, _front: I };
+/// a third of a turn about (1, 1, -1), carries +Y onto +X
+pub const RIGHT: Orientation = Orientation {
+    w: 0.5,
+    x: 0.5,
+    y: 0.5,
+    z: -0.5,
+};
 pub const UP: Orientation = Orientation {
-    _top: _NK,
-    _front: J,
+    w: 1.,
+    x: 0.,
+    y: 0.,
+    z: 0.,
 };
+/// a quarter turn about +X, carries +Y onto +Z
 pub const _FORWARDS: Orientation = Orientation {
-    _top: J,
-    _front: _K,
+    w: std::f32::consts::FRAC_1_SQRT_2,
+    x: std::f32::consts::FRAC_1_SQRT_2,
+    y: 0.,
+    z: 0.,
 };
+
+impl Default for Orientation {
+    fn default() -> Self {
+        UP
+    }
+}
+
+/// composition, `a * b` rotates by b first and then by a
+impl std::ops::Mul<Orientation> for Orientation {
+    type Output = Orientation;
+    fn mul(self, rhs: Orientation) -> Orientation {
+        Orientation {
+            w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        }
+    }
+}
+
+impl Orientation {
+    /// assumes radians
+    pub fn from_axis_angle(θ: f32, axis: Vector) -> Orientation {
+        Orientation::from_transform(&build_arbitrary_rotation_transform(θ, axis))
+    }
+
+    /// rotates about x, then y, then z, all in radians and about the fixed world axes
+    pub fn from_euler(x: f32, y: f32, z: f32) -> Orientation {
+        Orientation::from_axis_angle(z, _K)
+            * Orientation::from_axis_angle(y, J)
+            * Orientation::from_axis_angle(x, I)
+    }
+
+    /// turns -Z toward the target, keeping +Y as close to up as it can
+    /// the target is relative to whatever is being oriented, for a point in the world pass `point - position`
+    pub fn look_at(target: Vector, up: Vector) -> Orientation {
+        let front = target.unitized();
+        let mut right = front.cross(&up);
+        if right.magnitude() < 1e-6 {
+            // looking straight along up, any top will do
+            let fallback = if front.x.abs() < 0.9 { I } else { J };
+            right = front.cross(&fallback.cross(&front));
+        }
+        let right = right.unitized();
+        let top = right.cross(&front);
+
+        let mut rotation = Transform::new();
+        for (column, axis) in [right, top, -front].iter().enumerate() {
+            for row in 0..3 {
+                rotation.matrix[[row, column]] = axis.axis(row);
+            }
+        }
+        Orientation::from_transform(&rotation)
+    }
+
+    /// reads the rotation out of the upper 3x3 of a transform, which should be orthonormal
+    pub fn from_transform(transform: &Transform) -> Orientation {
+        let m = |i: usize, j: usize| transform.matrix[[i, j]];
+        let trace = m(0, 0) + m(1, 1) + m(2, 2);
+        // divides by whichever component is largest to stay well conditioned
+        let q = if trace > 0. {
+            let s = 2. * (1. + trace).sqrt();
+            Orientation {
+                w: s / 4.,
+                x: (m(2, 1) - m(1, 2)) / s,
+                y: (m(0, 2) - m(2, 0)) / s,
+                z: (m(1, 0) - m(0, 1)) / s,
+            }
+        } else if m(0, 0) > m(1, 1) && m(0, 0) > m(2, 2) {
+            let s = 2. * (1. + m(0, 0) - m(1, 1) - m(2, 2)).sqrt();
+            Orientation {
+                w: (m(2, 1) - m(1, 2)) / s,
+                x: s / 4.,
+                y: (m(0, 1) + m(1, 0)) / s,
+                z: (m(0, 2) + m(2, 0)) / s,
+            }
+        } else if m(1, 1) > m(2, 2) {
+            let s = 2. * (1. + m(1, 1) - m(0, 0) - m(2, 2)).sqrt();
+            Orientation {
+                w: (m(0, 2) - m(2, 0)) / s,
+                x: (m(0, 1) + m(1, 0)) / s,
+                y: s / 4.,
+                z: (m(1, 2) + m(2, 1)) / s,
+            }
+        } else {
+            let s = 2. * (1. + m(2, 2) - m(0, 0) - m(1, 1)).sqrt();
+            Orientation {
+                w: (m(1, 0) - m(0, 1)) / s,
+                x: (m(0, 2) + m(2, 0)) / s,
+                y: (m(1, 2) + m(2, 1)) / s,
+                z: s / 4.,
+            }
+        };
+        q.normalized()
+    }
+
+    pub fn to_transform(self) -> Transform {
+        build_quaternion_rotation_transform(self.w, self.x, self.y, self.z)
+    }
+
+    /// spherical interpolation, constant angular speed from self at 0 to other at 1
+    /// always takes the short way around
+    pub fn slerp(self, other: Orientation, t: f32) -> Orientation {
+        let mut other = other;
+        let mut cosθ = self.dot(&other);
+        if cosθ < 0. {
+            // q and -q are the same rotation, pick the closer one
+            other = other.scaled(-1.);
+            cosθ = -cosθ;
+        }
+        if cosθ > 0.9995 {
+            // nearly parallel, sin θ is too small to divide by
+            return (self.scaled(1. - t) + other.scaled(t)).normalized();
+        }
+        let θ = cosθ.acos();
+        let a = ((1. - t) * θ).sin() / θ.sin();
+        let b = (t * θ).sin() / θ.sin();
+        (self.scaled(a) + other.scaled(b)).normalized()
+    }
+
+    /// the same rotation the other way
+    pub fn inverse(self) -> Orientation {
+        Orientation {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+
+    fn dot(&self, other: &Orientation) -> f32 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    fn scaled(self, factor: f32) -> Orientation {
+        Orientation {
+            w: factor * self.w,
+            x: factor * self.x,
+            y: factor * self.y,
+            z: factor * self.z,
+        }
+    }
+
+    fn normalized(self) -> Orientation {
+        self.scaled(1. / self.dot(&self).sqrt())
+    }
+}
+
+impl std::ops::Add<Orientation> for Orientation {
+    type Output = Orientation;
+    fn add(self, rhs: Orientation) -> Orientation {
+        Orientation {
+            w: self.w + rhs.w,
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::geometry::{
+        primitives::Vector,
+        transformations::{
+            _build_x_rotation_transform, _build_z_rotation_transform,
+            build_arbitrary_rotation_transform, build_y_rotation_transform, compile_transforms,
+            Transform,
+        },
+    };
+
+    use super::*;
+
+    fn assert_same_rotation(a: &Transform, b: &Transform) {
+        for (x, y) in a.matrix.iter().zip(b.matrix.iter()) {
+            assert_abs_diff_eq!(x, y, epsilon = 1e-5);
+        }
+    }
+
+    fn assert_same_direction(a: Vector, b: Vector) {
+        assert_abs_diff_eq!(a.x, b.x, epsilon = 1e-5);
+        assert_abs_diff_eq!(a.y, b.y, epsilon = 1e-5);
+        assert_abs_diff_eq!(a.z, b.z, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn axis_angle_round_trip() {
+        let axis = Vector::new(1., -2., 0.5);
+        for θ in [0., 0.3, PI / 2., 2.5, PI, 4.] {
+            let orientation = Orientation::from_axis_angle(θ, axis);
+            assert_same_rotation(
+                &orientation.to_transform(),
+                &build_arbitrary_rotation_transform(θ, axis),
+            );
+        }
+        assert_same_rotation(&UP.to_transform(), &Transform::new());
+    }
+
+    #[test]
+    fn euler_matches_single_axis_rotations() {
+        let (x, y, z) = (0.4, -1.1, 2.);
+        let expected = compile_transforms(&vec![
+            _build_x_rotation_transform(x),
+            build_y_rotation_transform(y),
+            _build_z_rotation_transform(z),
+        ]);
+        assert_same_rotation(&Orientation::from_euler(x, y, z).to_transform(), &expected);
+    }
+
+    #[test]
+    fn named_orientations() {
+        assert_same_direction(RIGHT.to_transform().apply_to_direction(&J), I);
+        assert_same_direction(_FORWARDS.to_transform().apply_to_direction(&J), _K);
+    }
+
+    #[test]
+    fn look_at_points_down_negative_z() {
+        let target = Vector::new(3., 1., -2.);
+        let orientation = Orientation::look_at(target, J);
+        let rotation = orientation.to_transform();
+        assert_same_direction(rotation.apply_to_direction(&_NK), target.unitized());
+        // the top of the frame stays level
+        let top = rotation.apply_to_direction(&J);
+        assert!(top.y > 0.);
+        assert_abs_diff_eq!(rotation.apply_to_direction(&I).y, 0., epsilon = 1e-5);
+
+        // straight up still yields a valid rotation
+        let up = Orientation::look_at(J, J).to_transform();
+        assert_same_direction(up.apply_to_direction(&_NK), J);
+    }
+
+    #[test]
+    fn slerp_is_constant_speed() {
+        let a = Orientation::from_axis_angle(0.2, J);
+        let b = Orientation::from_axis_angle(1.4, J);
+        assert_same_rotation(&a.slerp(b, 0.).to_transform(), &a.to_transform());
+        assert_same_rotation(&a.slerp(b, 1.).to_transform(), &b.to_transform());
+        assert_same_rotation(
+            &a.slerp(b, 0.25).to_transform(),
+            &Orientation::from_axis_angle(0.5, J).to_transform(),
+        );
+        // the long way around is never taken
+        let c = Orientation::from_axis_angle(-0.2, J).scaled(-1.);
+        assert_same_rotation(&a.slerp(c, 0.5).to_transform(), &UP.to_transform());
+        assert_same_rotation(&(a * a.inverse()).to_transform(), &UP.to_transform());
+    }
+}
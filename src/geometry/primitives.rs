@@ -1,8 +1,12 @@
 // use image::{ImageBuffer, Rgb, RgbImage};
 use crate::geometry::transformations::Transform;
+use crate::ray_tracing::bounding_volume_hierarchy::BoundingVolumeHierarchy;
 use image::{Rgb, RgbImage};
 use ndarray::Array1;
-use rand::{prelude::ThreadRng, Rng};
+use rand::Rng;
+
+use crate::random::Pcg32;
+use std::sync::{Arc, OnceLock};
 // use crate::ray_tracing::rendering_equation::BRDF;
 
 /// geometry defining spatial surface
@@ -10,6 +14,10 @@ use rand::{prelude::ThreadRng, Rng};
 pub struct Vertex {
     pub position: Vector,
     pub uv_coord: (f32, f32),
+    /// unit length, when the model supplies one, otherwise the polygon's own normal is used
+    pub normal: Option<Vector>,
+    /// linear RGB from 0 to 1, for models that paint their vertices
+    pub color: Option<(f32, f32, f32)>,
 }
 
 pub const ORIGIN: Vector = Vector {
@@ -40,6 +48,8 @@ impl Vertex {
         Vertex {
             position: self.position.inv(),
             uv_coord: self.uv_coord,
+            normal: self.normal,
+            color: self.color,
         }
     }
     pub fn new(x: f32, y: f32, z: f32) -> Vertex {
@@ -55,6 +65,8 @@ impl Default for Vertex {
         Vertex {
             position: ORIGIN,
             uv_coord: (0., 0.),
+            normal: None,
+            color: None,
         }
     }
 }
@@ -88,6 +100,12 @@ impl std::ops::Mul<Vector> for f32 {
     }
 }
 
+impl From<[f32; 3]> for Vector {
+    fn from([x, y, z]: [f32; 3]) -> Vector {
+        Vector { x, y, z }
+    }
+}
+
 impl std::ops::Div<f32> for Vector {
     type Output = Vector;
     fn div(self, rhs: f32) -> Vector {
@@ -162,6 +180,13 @@ impl Vector {
     pub fn dot(&self, other: &Vector) -> f32 {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
+    pub fn cross(&self, other: &Vector) -> Vector {
+        Vector {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
     pub fn minus(&self, other: &Vector) -> Vector {
         let x = self.x - other.x;
         let y = self.y - other.y;
@@ -197,6 +222,30 @@ impl Vector {
     pub fn min(&self) -> f32 {
         f32::min(self.x, f32::min(self.y, self.z))
     }
+    /// the component along axis 0, 1 or 2
+    pub fn axis(&self, axis: usize) -> f32 {
+        match axis {
+            0 => self.x,
+            1 => self.y,
+            _ => self.z,
+        }
+    }
+    /// the smaller of each pair of components
+    pub fn elementwise_min(&self, other: &Vector) -> Vector {
+        Vector::new(
+            f32::min(self.x, other.x),
+            f32::min(self.y, other.y),
+            f32::min(self.z, other.z),
+        )
+    }
+    /// the larger of each pair of components
+    pub fn elementwise_max(&self, other: &Vector) -> Vector {
+        Vector::new(
+            f32::max(self.x, other.x),
+            f32::max(self.y, other.y),
+            f32::max(self.z, other.z),
+        )
+    }
     pub fn ones() -> Vector {
         Vector::new(1., 1., 1.)
     }
@@ -209,7 +258,7 @@ impl Vector {
 }
 
 /// actually even in a cube
-pub fn _even_over_sphere(rng: &mut ThreadRng) -> Vector {
+pub fn _even_over_sphere(rng: &mut Pcg32) -> Vector {
     loop {
         let vector = Vector::new(rng.gen(), rng.gen(), rng.gen());
         if vector.magnitude() <= 1.0 {
@@ -219,7 +268,7 @@ pub fn _even_over_sphere(rng: &mut ThreadRng) -> Vector {
 }
 
 /// just an approximation
-pub fn even_over_hemisphere(normal: Vector, rng: &mut ThreadRng) -> Vector {
+pub fn even_over_hemisphere(normal: Vector, rng: &mut Pcg32) -> Vector {
     let output = _even_over_sphere(rng);
     if output.dot(&normal) < 0. {
         return -1. * output;
@@ -227,6 +276,37 @@ pub fn even_over_hemisphere(normal: Vector, rng: &mut ThreadRng) -> Vector {
     output
 }
 
+/// two unit vectors at right angles to a unit normal and to each other
+/// from Duff et al., "Building an Orthonormal Basis, Revisited" (2017)
+pub fn tangent_frame(normal: Vector) -> (Vector, Vector) {
+    let sign = 1f32.copysign(normal.z);
+    let scale = -1. / (sign + normal.z);
+    let shear = normal.x * normal.y * scale;
+    (
+        Vector::new(
+            1. + sign * normal.x * normal.x * scale,
+            sign * shear,
+            -sign * normal.x,
+        ),
+        Vector::new(shear, sign + normal.y * normal.y * scale, -normal.y),
+    )
+}
+
+/// directions about a unit normal given as (tangent, bitangent, normal) components
+pub fn from_tangent_frame(local: Vector, normal: Vector) -> Vector {
+    let (tangent, bitangent) = tangent_frame(normal);
+    tangent.times(local.x) + bitangent.times(local.y) + normal.times(local.z)
+}
+
+/// more often near the normal, in proportion to the cosine, with a density of cosθ / π
+pub fn cosine_over_hemisphere(normal: Vector, rng: &mut Pcg32) -> Vector {
+    // points even over a disk, lifted up onto the hemisphere
+    let r = rng.gen::<f32>().sqrt();
+    let φ = 2. * std::f32::consts::PI * rng.gen::<f32>();
+    let local = Vector::new(r * φ.cos(), r * φ.sin(), (1. - r * r).max(0.).sqrt());
+    from_tangent_frame(local, normal)
+}
+
 #[derive(Clone, Debug)]
 pub struct Polygon {
     pub a: Vertex,
@@ -253,42 +333,208 @@ impl Polygon {
         Vector::new(x, y, z).unitized()
     }
 
+    /// the vertex normals blended across the face, for smooth shading
+    /// only when every corner has one
+    pub fn shading_normal(&self, barycentric: (f32, f32, f32)) -> Option<Vector> {
+        let (u, v, w) = barycentric;
+        let normal = u * self.a.normal? + v * self.b.normal? + w * self.c.normal?;
+        if normal.magnitude() > 0. {
+            Some(normal.unitized())
+        } else {
+            None
+        }
+    }
+
     pub fn new(a: Vertex, b: Vertex, c: Vertex) -> Polygon {
         Polygon { a, b, c }
     }
 }
 
+/// axis aligned bounding box
+/// an empty box has its min above its max so that growing it by anything works
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: Vector,
+    pub max: Vector,
+}
+
+impl BoundingBox {
+    pub fn empty() -> BoundingBox {
+        BoundingBox {
+            min: Vector::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Vector::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+    pub fn from_polygon(polygon: &Polygon) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
+        bounds.grow(&polygon.a.position);
+        bounds.grow(&polygon.b.position);
+        bounds.grow(&polygon.c.position);
+        bounds
+    }
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+    /// expands the box to contain the point
+    pub fn grow(&mut self, point: &Vector) {
+        self.min = self.min.elementwise_min(point);
+        self.max = self.max.elementwise_max(point);
+    }
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min: self.min.elementwise_min(&other.min),
+            max: self.max.elementwise_max(&other.max),
+        }
+    }
+    pub fn centroid(&self) -> Vector {
+        0.5 * (self.min + self.max)
+    }
+    pub fn extent(&self) -> Vector {
+        self.max - self.min
+    }
+    /// the box around this box once transformed, which is looser than transforming the contents
+    pub fn transformed(&self, transform: &Transform) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
+        if self.is_empty() {
+            return bounds;
+        }
+        for corner in 0..8 {
+            let pick = |axis: usize| {
+                if corner & (1 << axis) == 0 {
+                    self.min.axis(axis)
+                } else {
+                    self.max.axis(axis)
+                }
+            };
+            bounds.grow(&transform.apply_to_point(&Vector::new(pick(0), pick(1), pick(2))));
+        }
+        bounds
+    }
+    pub fn surface_area(&self) -> f32 {
+        if self.is_empty() {
+            return 0.;
+        }
+        let d = self.extent();
+        2. * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+    /// slab test, returns the distance along the ray at which it enters the box
+    /// the inverse direction is passed in because it is shared by every box a ray is tested against
+    pub fn ray_intersection(
+        &self,
+        ray: &Ray,
+        inverse_direction: &Vector,
+        max_dist: f32,
+    ) -> Option<f32> {
+        let mut t_near: f32 = 0.;
+        let mut t_far: f32 = max_dist;
+        for axis in 0..3 {
+            let origin = ray.position.axis(axis);
+            let inverse = inverse_direction.axis(axis);
+            let t0 = (self.min.axis(axis) - origin) * inverse;
+            let t1 = (self.max.axis(axis) - origin) * inverse;
+            // min and max skip the NaNs that turn up when the ray lies on a slab
+            t_near = f32::max(t_near, f32::min(t0, t1));
+            t_far = f32::min(t_far, f32::max(t0, t1));
+        }
+        if t_near <= t_far {
+            Some(t_near)
+        } else {
+            None
+        }
+    }
+}
+
 pub struct _Line3D {
     pub a: Vertex,
     pub b: Vertex,
 }
 
+/// cloning a mesh is cheap, every clone shares the same geometry and hierarchy
+/// so a mesh can be placed in many objects while only being stored once
 #[derive(Clone, Debug)]
 pub struct Mesh {
     // pub position: Vector,
     // pub orientation: ,
-    pub vertices: Vec<Vertex>, // a mesh owns it's vertex information
+    pub vertices: Arc<Vec<Vertex>>, // a mesh owns it's vertex information
     // pub polygons: Vec<Polygon>, // it also owns it's polygon information
-    pub polygons: Vec<Vec<usize>>,
+    pub polygons: Arc<Vec<Vec<usize>>>,
+    /// per instance, only filled in for the rasterizer
     pub output_vertices: Vec<Vertex>,
     transform: Transform,
+    /// object space hierarchy over the polygons, built the first time it is needed
+    hierarchy: Arc<OnceLock<BoundingVolumeHierarchy>>,
+    /// the file this was read from, so scene files can refer back to it
+    pub source: Option<MeshSource>,
+}
+
+/// where in which file a mesh came from
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MeshSource {
+    /// as it was passed to the loader
+    pub path: String,
+    /// only the faces of this Wavefront obj group
+    pub group: Option<String>,
+    /// and of those only the ones using this material
+    pub material: Option<String>,
+}
+
+impl MeshSource {
+    pub fn file(path: &str) -> MeshSource {
+        MeshSource {
+            path: path.to_string(),
+            group: None,
+            material: None,
+        }
+    }
 }
 impl Mesh {
     /// I need to learn matrix math for this one
     /// transforms are kept as a list of transforms to be done, which is much more efficient
     pub fn apply_transformations(&mut self) {
-        self.output_vertices = self.vertices.clone();
-        self.output_vertices = self.transform.process(self.output_vertices.clone());
+        self.output_vertices = self.transform.process(self.vertices.to_vec());
     }
     pub fn add_transform(&mut self, transform: Transform) {
         self.transform.matrix = self.transform.matrix.dot(&transform.matrix);
     }
+    /// everything added with `add_transform` so far
+    pub fn get_transform(&self) -> &Transform {
+        &self.transform
+    }
+    /// the first three corners of a polygon in object space
+    pub fn polygon(&self, index: usize) -> Polygon {
+        let poly = &self.polygons[index];
+        Polygon::new(
+            self.vertices[poly[0]].clone(),
+            self.vertices[poly[1]].clone(),
+            self.vertices[poly[2]].clone(),
+        )
+    }
+    /// bottom level hierarchy in object space
+    /// shared by every clone of this mesh, so it is only ever built once
+    pub fn hierarchy(&self) -> &BoundingVolumeHierarchy {
+        self.hierarchy.get_or_init(|| {
+            let bounds: Vec<BoundingBox> = (0..self.polygons.len())
+                .map(|i| BoundingBox::from_polygon(&self.polygon(i)))
+                .collect();
+            BoundingVolumeHierarchy::build(&bounds)
+        })
+    }
+    /// object space bounds
+    pub fn bounds(&self) -> BoundingBox {
+        self.hierarchy().nodes[0].bounds
+    }
+    /// whether two meshes are clones of each other
+    pub fn shares_geometry(&self, other: &Mesh) -> bool {
+        Arc::ptr_eq(&self.vertices, &other.vertices)
+    }
     pub fn new(vertices: Vec<Vertex>, polygons: Vec<Vec<usize>>) -> Mesh {
         Mesh {
-            vertices,
-            polygons,
+            vertices: Arc::new(vertices),
+            polygons: Arc::new(polygons),
             output_vertices: Vec::new(),
             transform: Transform::new(),
+            hierarchy: Arc::new(OnceLock::new()),
+            source: None,
         }
     }
 
@@ -317,12 +563,7 @@ impl Mesh {
             vec![5, 3, 7], // left
         ];
 
-        Mesh {
-            vertices: vec![a, b, c, d, e, f, g, h],
-            polygons,
-            output_vertices: Vec::new(),
-            transform: Transform::new(),
-        }
+        Mesh::new(vec![a, b, c, d, e, f, g, h], polygons)
     }
 
     pub fn _sample_mesh() -> Mesh {
@@ -334,12 +575,7 @@ impl Mesh {
             vec![0, 1, 2], // bottom 0123
         ];
 
-        Mesh {
-            vertices: vec![a, b, c],
-            polygons,
-            output_vertices: Vec::new(),
-            transform: Transform::new(),
-        }
+        Mesh::new(vec![a, b, c], polygons)
     }
 }
 
@@ -437,7 +673,10 @@ pub fn spherical_to_euclidean(x: SphericalCoordinates) -> Vector {
 
 #[cfg(test)]
 mod tests {
-    use crate::geometry::primitives::Vector;
+    use approx::assert_abs_diff_eq;
+
+    use crate::geometry::primitives::{cosine_over_hemisphere, tangent_frame, Vector};
+    use crate::random::Pcg32;
 
     /// useful table: https://www.nikonians.org/reviews/fov-tables
     #[test]
@@ -446,4 +685,36 @@ mod tests {
         let anothervec = -myvec;
         assert_eq!(anothervec, Vector::new(-1., -2., -3.));
     }
+
+    #[test]
+    fn frames_are_orthonormal() {
+        for normal in [
+            Vector::new(0., 0., 1.),
+            Vector::new(0., 0., -1.),
+            Vector::new(1., 2., -3.).unitized(),
+        ] {
+            let (tangent, bitangent) = tangent_frame(normal);
+            assert_abs_diff_eq!(tangent.magnitude(), 1., epsilon = 1e-6);
+            assert_abs_diff_eq!(bitangent.magnitude(), 1., epsilon = 1e-6);
+            assert_abs_diff_eq!(tangent.dot(&normal), 0., epsilon = 1e-6);
+            assert_abs_diff_eq!(bitangent.dot(&normal), 0., epsilon = 1e-6);
+            assert_abs_diff_eq!(tangent.dot(&bitangent), 0., epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn cosine_weighted_directions_lean_toward_the_normal() {
+        let normal = Vector::new(0., -1., 0.);
+        let mut rng = Pcg32::new(5, 0);
+        let samples = 20_000;
+        let mut mean_cosine = 0.;
+        for _ in 0..samples {
+            let ω = cosine_over_hemisphere(normal, &mut rng);
+            assert_abs_diff_eq!(ω.magnitude(), 1., epsilon = 1e-5);
+            assert!(ω.dot(&normal) >= 0.);
+            mean_cosine += ω.dot(&normal) / samples as f32;
+        }
+        // against 1/2 for uniform directions
+        assert_abs_diff_eq!(mean_cosine, 2. / 3., epsilon = 1e-2);
+    }
 }
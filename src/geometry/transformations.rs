@@ -1,6 +1,9 @@
 use crate::{
     camera::Camera,
-    geometry::primitives::{vertex_from_array, Vector, Vertex},
+    geometry::{
+        orientation::Orientation,
+        primitives::{vertex_from_array, Vector, Vertex},
+    },
 };
 use ndarray::{arr1, arr2, Array2};
 
@@ -8,6 +11,12 @@ use ndarray::{arr1, arr2, Array2};
 pub struct Transform {
     pub matrix: Array2<f32>,
 }
+impl Default for Transform {
+    fn default() -> Self {
+        build_identity_transform()
+    }
+}
+
 impl Transform {
     pub fn process(&self, vertices: Vec<Vertex>) -> Vec<Vertex> {
         let mut out = Vec::new();
@@ -21,9 +30,98 @@ impl Transform {
         }
         out
     }
+    /// transforms a single position, including the homogenous divide
+    pub fn apply_to_point(&self, point: &Vector) -> Vector {
+        let m = &self.matrix;
+        let p = point.as_homogenous_array();
+        let row = |i: usize| m[[i, 0]] * p[0] + m[[i, 1]] * p[1] + m[[i, 2]] * p[2] + m[[i, 3]];
+        let w = row(3);
+        Vector::new(row(0) / w, row(1) / w, row(2) / w)
+    }
+    /// transforms a direction, which ignores translation
+    /// the result is not unitized so distances along it are preserved
+    pub fn apply_to_direction(&self, direction: &Vector) -> Vector {
+        let m = &self.matrix;
+        let d = direction;
+        let row = |i: usize| m[[i, 0]] * d.x + m[[i, 1]] * d.y + m[[i, 2]] * d.z;
+        Vector::new(row(0), row(1), row(2))
+    }
+    /// multiplies by the transpose of the upper 3x3
+    /// called on an inverse transform this carries surface normals along with the forward one
+    pub fn apply_transposed_to_direction(&self, direction: &Vector) -> Vector {
+        let m = &self.matrix;
+        let d = direction;
+        let column = |j: usize| m[[0, j]] * d.x + m[[1, j]] * d.y + m[[2, j]] * d.z;
+        Vector::new(column(0), column(1), column(2))
+    }
+    /// Gauss-Jordan elimination with partial pivoting, done in f64 to keep the round trip tight
+    /// singular matrices (like a zero scale) yield None
+    pub fn inverse(&self) -> Option<Transform> {
+        let mut a = [[0f64; 8]; 4];
+        for (i, row) in a.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().take(4).enumerate() {
+                *value = self.matrix[[i, j]] as f64;
+            }
+            row[4 + i] = 1.;
+        }
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&x, &y| a[x][column].abs().total_cmp(&a[y][column].abs()))
+                .unwrap();
+            if a[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(column, pivot);
+            let divisor = a[column][column];
+            for value in a[column].iter_mut() {
+                *value /= divisor;
+            }
+            let pivot_row = a[column];
+            for (i, row) in a.iter_mut().enumerate() {
+                if i != column {
+                    let factor = row[column];
+                    for (value, pivot_value) in row.iter_mut().zip(pivot_row) {
+                        *value -= factor * pivot_value;
+                    }
+                }
+            }
+        }
+        let mut matrix = Array2::zeros((4, 4));
+        for i in 0..4 {
+            for j in 0..4 {
+                matrix[[i, j]] = a[i][4 + j] as f32;
+            }
+        }
+        Some(Transform { matrix })
+    }
     pub fn new() -> Transform {
         build_identity_transform()
     }
+    /// splits an affine transform back into a translation, a rotation and a scale
+    /// the inverse of composing scale, then rotation, then translation
+    /// shear, which only comes from non uniform scales under rotations, can't be represented and is lost
+    pub fn decompose(&self) -> (Vector, Orientation, Vector) {
+        let m = &self.matrix;
+        let translation = Vector::new(m[[0, 3]], m[[1, 3]], m[[2, 3]]);
+        let column = |j: usize| Vector::new(m[[0, j]], m[[1, j]], m[[2, j]]);
+        let mut scale = Vector::new(
+            column(0).magnitude(),
+            column(1).magnitude(),
+            column(2).magnitude(),
+        );
+        // a mirror shows up as a left handed basis, it gets put on the x scale
+        if column(0).cross(&column(1)).dot(&column(2)) < 0. {
+            scale.x = -scale.x;
+        }
+        let mut rotation = Transform::new();
+        for j in 0..3 {
+            let axis = column(j) / scale.axis(j);
+            for i in 0..3 {
+                rotation.matrix[[i, j]] = axis.axis(i);
+            }
+        }
+        (translation, Orientation::from_transform(&rotation), scale)
+    }
 }
 
 pub fn build_identity_transform() -> Transform {
@@ -119,6 +217,11 @@ pub fn build_arbitrary_rotation_transform(θ: f32, mut axis: Vector) -> Transfor
     let q1 = axis.x * factor;
     let q2 = axis.y * factor;
     let q3 = axis.z * factor;
+    build_quaternion_rotation_transform(q0, q1, q2, q3)
+}
+
+/// rotation by the unit quaternion q0 + q1 i + q2 j + q3 k
+pub fn build_quaternion_rotation_transform(q0: f32, q1: f32, q2: f32, q3: f32) -> Transform {
     let matrix: ndarray::ArrayBase<ndarray::OwnedRepr<f32>, ndarray::Dim<[usize; 2]>> = arr2(&[
         [
             sq(q0) + sq(q1) - sq(q2) - sq(q3),
@@ -196,6 +299,8 @@ pub fn compile_transforms(transforms: &Vec<Transform>) -> Transform {
 
 #[cfg(test)]
 mod tests {
+    use approx::assert_abs_diff_eq;
+
     use crate::camera::{Camera, Lens, Sensor};
 
     use crate::geometry::primitives::Vertex;
@@ -322,6 +427,25 @@ mod tests {
         assert_eq!(arr1(&[0., 0., 1.]), myvertex_p);
     }
 
+    #[test]
+    fn verify_inverse() {
+        let transform = compile_transforms(&vec![
+            build_scale_transform(Vector::new(2., 3., 0.5)),
+            build_arbitrary_rotation_transform(0.7, Vector::new(1., 2., 3.)),
+            build_translation_transform(Vector::new(-4., 1., 9.)),
+        ]);
+        let inverse = transform.inverse().unwrap();
+        let point = Vector::new(0.3, -7., 2.);
+        let round_trip = inverse.apply_to_point(&transform.apply_to_point(&point));
+        assert_abs_diff_eq!(round_trip.x, point.x, epsilon = 1e-4);
+        assert_abs_diff_eq!(round_trip.y, point.y, epsilon = 1e-4);
+        assert_abs_diff_eq!(round_trip.z, point.z, epsilon = 1e-4);
+
+        assert!(build_scale_transform(Vector::new(1., 0., 1.))
+            .inverse()
+            .is_none());
+    }
+
     #[test]
     fn verify_projection_implementation() {
         let lens = Lens {
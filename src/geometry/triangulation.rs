@@ -0,0 +1,144 @@
+use crate::geometry::primitives::Vector;
+
+/// splits a planar polygon into triangles by ear clipping
+/// returns indices into `points`, wound the same way as the polygon
+///
+/// convex polygons come out as a fan from the first corner,
+/// concave ones are handled as long as the outline doesn't cross itself.
+/// if no ear can be found, which only happens for broken outlines, the rest is fanned
+pub fn triangulate(points: &[Vector]) -> Vec<[usize; 3]> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+    if points.len() == 3 {
+        return vec![[0, 1, 2]];
+    }
+    let flat = project_onto_plane(points);
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let prev = remaining[(i + n - 1) % n];
+            let current = remaining[i];
+            let next = remaining[(i + 1) % n];
+            is_ear(&flat, &remaining, prev, current, next)
+        });
+        let Some(i) = ear else {
+            break;
+        };
+        let prev = remaining[(i + n - 1) % n];
+        let next = remaining[(i + 1) % n];
+        triangles.push([prev, remaining[i], next]);
+        remaining.remove(i);
+    }
+    for i in 1..remaining.len() - 1 {
+        triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+    triangles
+}
+
+/// drops the axis the polygon faces most, flipped if needed so the outline runs counterclockwise
+fn project_onto_plane(points: &[Vector]) -> Vec<(f32, f32)> {
+    // Newell's method, robust to slightly non planar and concave outlines
+    let mut normal = Vector::new(0., 0., 0.);
+    for (i, a) in points.iter().enumerate() {
+        let b = &points[(i + 1) % points.len()];
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+    let (ax, ay, az) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+    let (u, v, facing) = if az >= ax && az >= ay {
+        (0, 1, normal.z)
+    } else if ax >= ay {
+        (1, 2, normal.x)
+    } else {
+        (2, 0, normal.y)
+    };
+    let sign = if facing < 0. { -1. } else { 1. };
+    points
+        .iter()
+        .map(|p| (p.axis(u), sign * p.axis(v)))
+        .collect()
+}
+
+/// twice the signed area, positive when a, b, c turn counterclockwise
+fn turn(a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> f32 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+fn is_ear(
+    flat: &[(f32, f32)],
+    remaining: &[usize],
+    prev: usize,
+    current: usize,
+    next: usize,
+) -> bool {
+    let (a, b, c) = (flat[prev], flat[current], flat[next]);
+    if turn(a, b, c) <= 0. {
+        // reflex or degenerate corner
+        return false;
+    }
+    // no other corner may sit inside the triangle being cut off
+    remaining
+        .iter()
+        .filter(|&&i| i != prev && i != current && i != next)
+        .all(|&i| {
+            let p = flat[i];
+            !(turn(a, b, p) >= 0. && turn(b, c, p) >= 0. && turn(c, a, p) >= 0.)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::primitives::Vector;
+
+    use super::triangulate;
+
+    fn area(points: &[Vector], triangles: &[[usize; 3]]) -> f32 {
+        triangles
+            .iter()
+            .map(|&[a, b, c]| {
+                let ab = points[b] - points[a];
+                let ac = points[c] - points[a];
+                0.5 * ab.cross(&ac).magnitude()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn convex_polygons_fan() {
+        let square = [
+            Vector::new(0., 0., 0.),
+            Vector::new(1., 0., 0.),
+            Vector::new(1., 1., 0.),
+            Vector::new(0., 1., 0.),
+        ];
+        let triangles = triangulate(&square);
+        assert_eq!(triangles.len(), 2);
+        assert!((area(&square, &triangles) - 1.).abs() < 1e-6);
+        assert!(triangulate(&square[..2]).is_empty());
+    }
+
+    #[test]
+    fn concave_polygons_keep_their_area_and_winding() {
+        // an L lying in the xz plane, wound so it faces +y
+        let l_shape = [
+            Vector::new(0., 0., 0.),
+            Vector::new(0., 0., 2.),
+            Vector::new(1., 0., 2.),
+            Vector::new(1., 0., 1.),
+            Vector::new(2., 0., 1.),
+            Vector::new(2., 0., 0.),
+        ];
+        let triangles = triangulate(&l_shape);
+        assert_eq!(triangles.len(), 4);
+        assert!((area(&l_shape, &triangles) - 3.).abs() < 1e-6);
+        for &[a, b, c] in &triangles {
+            let normal = (l_shape[b] - l_shape[a]).cross(&(l_shape[c] - l_shape[a]));
+            assert!(normal.y > 0.);
+        }
+    }
+}
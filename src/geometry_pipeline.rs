@@ -1,34 +1,48 @@
 use std::cmp::min;
 use std::sync::{mpsc, Arc};
 use std::thread;
-use std::time::Duration;
 
 use crate::application::application;
-use crate::camera::Camera;
+use crate::film::Film;
 use crate::geometry::primitives::{Triangle, Vector};
+use crate::render_observer::{Progress, RenderObserver, Silent};
 // use crate::primitives::LineCollection;
 use crate::object::Object;
-use crate::scene::scene::{EntityType, Rendermode, Scene, ShaderMode};
+use crate::scene::scene::{EntityKey, EntityType, Rendermode, Scene, ShaderMode};
 // use crate::primitives::PolygonCollection;
 use crate::geometry::transformations::{
     build_projection_transform, build_scale_transform, build_translation_transform,
     compile_transforms, Transform,
 };
 use crate::rasterization::rasterization::rasterize_triangle;
+use crate::ray_tracing::bounding_volume_hierarchy::InstanceHierarchy;
 use crate::ray_tracing::pixel_shader::{
     _solid_shader, bvh_shader, lit_shader, shade_pixels, z_shader,
 };
 use image::{GenericImage, ImageBuffer, Rgb, RgbImage};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::SeedableRng;
 use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
 use stopwatch::Stopwatch;
 
 /// transforms from world space to camera space
-fn build_camera_space_transform(camera: &Camera) -> Transform {
-    build_translation_transform(camera.position.inv())
+/// undoes wherever the camera was moved and turned to, which is exactly what camera rays get put through
+fn build_camera_space_transform(scene: &Scene) -> Transform {
+    scene
+        .world_transform(scene.active_camera_key())
+        .inverse()
+        .expect("the camera needs an invertible transform")
+}
 
-    // the camera rotation needs to be added
+/// world space straight to pixel coordinates
+fn build_view_transform(scene: &Scene) -> Transform {
+    compile_transforms(&vec![
+        build_camera_space_transform(scene),
+        build_to_projection_transform(scene),
+        build_to_display_transform(scene),
+    ])
 }
 
 fn build_to_projection_transform(scene: &Scene) -> Transform {
@@ -61,7 +75,7 @@ fn vertex_shader(scene: &mut Scene) {
 }
 
 fn apply_transforms(scene: &mut Scene) {
-    // calculate_global_space(scene);
+    scene.cascade_transforms();
     // view transform that goes from global space to clip space
 
     // world -> camera translation
@@ -69,37 +83,46 @@ fn apply_transforms(scene: &mut Scene) {
     // camera -> projection operation
 
     // --> clip coordinates
-    let mut uniform_view_transforms: Vec<Transform> = Vec::new();
-    // let to_camera_space = build_camera_space_transform(&scene.camera);
-    if scene.rendermode == Rendermode::Rasterize {
-        uniform_view_transforms.push(build_camera_space_transform(scene.active_camera()));
-        uniform_view_transforms.push(build_to_projection_transform(scene));
-        uniform_view_transforms.push(build_to_display_transform(scene));
-    }
-    let uniform_view_transform = compile_transforms(&uniform_view_transforms);
+    let rasterizing = scene.rendermode == Rendermode::Rasterize;
+    let uniform_view_transform = if rasterizing {
+        build_view_transform(scene)
+    } else {
+        Transform::new()
+    };
 
     // scene.transform_children
     // scene.build_camera_space_mesh
     // this will recursively apply transforms to nodes, then apply them to meshes
-    for key in scene.get_object_keys().clone() {
+    // worked out up front, changing the meshes drops the cache
+    let to_world: Vec<(EntityKey, Transform)> = scene
+        .get_object_keys()
+        .into_iter()
+        .map(|key| (key, scene.world_transform(key)))
+        .collect();
+    for (key, to_world_space) in to_world {
         let entity_type: &mut EntityType = scene.get_mut(key);
         let object: &mut Object = entity_type.as_object_mut().unwrap();
 
-        let to_world_space = build_translation_transform(object.position);
+        // into the world first, then through the camera
+        // the transform added to a mesh last is the first one its vertices go through, so the two are added as one
+        let to_output = compile_transforms(&vec![to_world_space, uniform_view_transform.clone()]);
         for mesh in &mut object.meshes {
             // for mesh in scene.meshes.iter_mut() {
-            mesh.add_transform(to_world_space.clone());
-
-            // if scene.rendermode == Rendermode::Rasterize {
-            //     // mesh.add_transform(to_camera_space.clone());
-            // }
-            mesh.add_transform(uniform_view_transform.clone());
+            mesh.add_transform(to_output.clone());
 
-            mesh.apply_transformations();
+            // ray tracing leaves the shared vertices alone and moves rays into each instance instead
+            if rasterizing {
+                mesh.apply_transformations();
+            }
         }
     }
-    // scene.root.cascade_transforms();
-    // scene.apply_transformations();
+    // cached again for the camera and lights to use while rendering
+    scene.cascade_transforms();
+}
+
+/// ray tracing needs every instance placed and sorted before any rays are cast
+fn build_acceleration_structure(scene: &mut Scene) {
+    scene.acceleration_structure = Some(InstanceHierarchy::build(scene));
 }
 
 fn wire_frame(canvas: &mut RgbImage, mut scene: Scene) {
@@ -108,7 +131,7 @@ fn wire_frame(canvas: &mut RgbImage, mut scene: Scene) {
         let object: &mut Object = entity_type.as_object_mut().unwrap();
         for mesh in &object.meshes {
             // mesh.apply_transformations();
-            for poly in &mesh.polygons {
+            for poly in mesh.polygons.iter() {
                 let a = &mesh.output_vertices[poly[0]]; // currently vertexes;
                 let b = &mesh.output_vertices[poly[1]];
                 let c = &mesh.output_vertices[poly[2]];
@@ -127,7 +150,7 @@ fn _solid(canvas: &mut RgbImage, mut scene: Scene) {
         let object: &mut Object = entity_type.as_object_mut().unwrap();
         for mesh in &mut object.meshes {
             mesh.apply_transformations();
-            for poly in &mut mesh.polygons {
+            for poly in mesh.polygons.iter() {
                 let a = &mesh.output_vertices[poly[0]]; // currently vertexes;
                 let b = &mesh.output_vertices[poly[1]];
                 let c = &mesh.output_vertices[poly[2]];
@@ -165,6 +188,7 @@ fn rasterize(canvas: &mut RgbImage, mut scene: Scene) {
     wire_frame(canvas, scene);
 }
 
+#[derive(Clone, Debug)]
 pub struct Tile {
     pub canvas: RgbImage,
     pub x_start: u32,
@@ -183,8 +207,9 @@ impl Tile {
         }
     }
 }
-fn ray_trace(canvas: &mut RgbImage, mut scene: Scene) {
+fn ray_trace(canvas: &mut RgbImage, mut scene: Scene, observer: &mut dyn RenderObserver) {
     apply_transforms(&mut scene);
+    build_acceleration_structure(&mut scene);
     let shadermode = match scene.shadermode {
         ShaderMode::Lit => lit_shader,
         ShaderMode::_BVH => bvh_shader,
@@ -201,9 +226,15 @@ fn ray_trace(canvas: &mut RgbImage, mut scene: Scene) {
     shade_pixels(&mut tile, &scene, shadermode);
 
     let _ = canvas.copy_from(&tile.canvas, 0, 0);
+    observer.tile_completed(&tile, canvas);
+    observer.progress(Progress {
+        completed: 1,
+        total: 1,
+    });
 }
-fn threaded_ray_trace(canvas: &mut RgbImage, mut scene: Scene) {
+fn threaded_ray_trace(canvas: &mut RgbImage, mut scene: Scene, observer: &mut dyn RenderObserver) {
     apply_transforms(&mut scene);
+    build_acceleration_structure(&mut scene);
 
     // let width = scene.active_camera.sensor.horizontal_res / scene.threads;
     let width = scene.active_camera().sensor.horizontal_res;
@@ -222,7 +253,6 @@ fn threaded_ray_trace(canvas: &mut RgbImage, mut scene: Scene) {
     let data = Arc::new(scene); // necessary for borrowing in threads
     let (sender, receiver) = mpsc::channel::<Tile>();
 
-    let mut render_timer = Stopwatch::start_new();
     // let mut handles = Vec::new();
 
     let mut tiles: Vec<Tile> = Vec::new();
@@ -253,68 +283,68 @@ fn threaded_ray_trace(canvas: &mut RgbImage, mut scene: Scene) {
             ));
         }
     }
-    tiles.shuffle(&mut thread_rng());
+    tiles.shuffle(&mut StdRng::seed_from_u64(data.seed));
     let total_tiles = tiles.len();
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(data.threads)
+        .build()
+        .expect("the render threads couldn't be started");
 
     let handle = thread::spawn(move || {
-        tiles.into_par_iter().for_each(move |mut tile| {
-            let mut thread_timer = Stopwatch::start_new();
-            shade_pixels(&mut tile, &Arc::clone(&data), shadermode);
-            thread_timer.stop();
-            let hue = thread_timer.elapsed_ms();
-            if data.hue_timer {
-                print!("thread timer: {thread_timer} - ");
-                for (x, y, _) in tile.canvas.clone().enumerate_pixels().clone() {
-                    tile.canvas.put_pixel(
-                        x,
-                        y,
-                        Rgb([
-                            min(hue, 255) as u8,
-                            min(hue / 8, 255) as u8,
-                            min(hue / 64, 255) as u8,
-                        ]),
-                    );
+        pool.install(move || {
+            tiles.into_par_iter().for_each(move |mut tile| {
+                let mut thread_timer = Stopwatch::start_new();
+                shade_pixels(&mut tile, &Arc::clone(&data), shadermode);
+                thread_timer.stop();
+                let hue = thread_timer.elapsed_ms();
+                if data.hue_timer {
+                    print!("thread timer: {thread_timer} - ");
+                    for (x, y, _) in tile.canvas.clone().enumerate_pixels().clone() {
+                        tile.canvas.put_pixel(
+                            x,
+                            y,
+                            Rgb([
+                                min(hue, 255) as u8,
+                                min(hue / 8, 255) as u8,
+                                min(hue / 64, 255) as u8,
+                            ]),
+                        );
+                    }
                 }
-            }
-            sender.clone().send(tile).unwrap();
+                sender.clone().send(tile).unwrap();
+            });
         });
     });
-    let mut current_tile = 0;
-    let mut partial_writer = Stopwatch::start_new();
-    let disk_update_interval = Duration::from_millis(100);
-
-    for tile in receiver {
-        println!("{}/{}", current_tile + 1, total_tiles);
-        current_tile += 1;
+    for (completed, tile) in receiver.into_iter().enumerate() {
         canvas
             .copy_from(&tile.canvas, tile.x_start, tile.y_start)
             .unwrap();
-
-        if disk_update_interval < partial_writer.elapsed() {
-            partial_writer.restart();
-            canvas.save("partial.png").unwrap();
-        }
+        observer.tile_completed(&tile, canvas);
+        observer.progress(Progress {
+            completed: completed + 1,
+            total: total_tiles,
+        });
     }
-
-    canvas.save("partial.png").unwrap();
     handle.join().unwrap();
-
-    render_timer.stop();
-    println!("parallel ray tracing: {:?}", render_timer.elapsed());
 }
 
 /// this serves as an abstraction away from rasterization, so that ray tracing can be dropped into the pipeline
 /// it owns nothing, it just operates on a canvas
 /// I am unsure of the best way to pass it information
 ///
-fn render(canvas: &mut RgbImage, scene: Scene) {
+/// the observer hears about it as it goes
+fn draw(canvas: &mut RgbImage, scene: Scene, observer: &mut dyn RenderObserver) {
+    observer.started(canvas.width(), canvas.height());
+    let mut render_timer = Stopwatch::start_new();
     match &scene.rendermode {
-        Rendermode::ThreadedRayTrace => threaded_ray_trace(canvas, scene),
+        Rendermode::ThreadedRayTrace => threaded_ray_trace(canvas, scene, observer),
 
-        Rendermode::_RayTrace => ray_trace(canvas, scene),
+        Rendermode::_RayTrace => ray_trace(canvas, scene, observer),
 
         Rendermode::Rasterize => rasterize(canvas, scene),
     }
+    render_timer.stop();
+    observer.finished(canvas, render_timer.elapsed());
 }
 
 /// Application
@@ -326,14 +356,110 @@ fn render(canvas: &mut RgbImage, scene: Scene) {
 /// animations, real time, whatever. It serves as an abstraction
 ///
 /// Currently, this is a purely software implementation that runs on a single core
-pub fn geometry_pipeline(mut scene: Scene) -> RgbImage {
+pub fn geometry_pipeline(mut scene: Scene, observer: &mut dyn RenderObserver) -> RgbImage {
     application(&mut scene); // arrives at the geometry to render
 
     let horizontal_res = scene.active_camera().sensor.horizontal_res;
     let vertical_res = scene.active_camera().sensor.vertical_res;
     let mut canvas: RgbImage = ImageBuffer::new(horizontal_res, vertical_res);
 
-    render(&mut canvas, scene);
+    draw(&mut canvas, scene, observer);
 
     canvas
 }
+
+/// renders the scene as it stands at its tick
+/// the pipeline works on a copy, so the scene can be rendered again, or changed and rendered again
+pub fn render(scene: &Scene) -> Film {
+    render_observed(scene, &mut Silent)
+}
+
+/// `render`, telling the observer about each tile as it finishes
+pub fn render_observed(scene: &Scene, observer: &mut dyn RenderObserver) -> Film {
+    Film::from(geometry_pipeline(scene.clone(), observer))
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::{
+        camera::Camera,
+        film::Film,
+        geometry::{
+            orientation::{Orientation, J},
+            primitives::{Mesh, Vector},
+        },
+        object::Object,
+        scene::scene::{Rendermode, Scene, ShaderMode},
+    };
+
+    use super::{build_view_transform, render};
+
+    /// the smallest box around every pixel that isn't black
+    fn drawn_bounds(film: &Film) -> Option<(u32, u32, u32, u32)> {
+        let mut bounds: Option<(u32, u32, u32, u32)> = None;
+        for (x, y, pixel) in film.image().enumerate_pixels() {
+            if pixel.0 == [0, 0, 0] {
+                continue;
+            }
+            bounds = Some(match bounds {
+                None => (x, y, x, y),
+                Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+            });
+        }
+        bounds
+    }
+
+    #[test]
+    fn rasterizer_and_camera_rays_agree() {
+        let mut scene = Scene::default();
+        let camera = scene.active_camera_mut();
+        camera.sensor.horizontal_res = 64;
+        camera.sensor.vertical_res = 48;
+        camera.position = Vector::new(1., 2., 6.);
+        camera.orientation = Orientation::look_at(Vector::new(-1., -2.5, -6.), J);
+        scene.cascade_transforms();
+
+        let view = build_view_transform(&scene);
+        for (x, y) in [(0, 0), (63, 0), (10, 30), (32, 24), (63, 47)] {
+            let ray =
+                scene.camera_to_world(Camera::straight_pixel_to_ray(scene.active_camera(), x, y));
+            // anything along a camera ray lands on the pixel the ray came from
+            for dist in [1., 4., 30.] {
+                let pixel = view.apply_to_point(&(ray.position + dist * ray.direction));
+                assert_abs_diff_eq!(pixel.x, x as f32 + 0.5, epsilon = 1e-3);
+                assert_abs_diff_eq!(pixel.y, y as f32 + 0.5, epsilon = 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn rasterizer_and_ray_tracer_place_objects_alike() {
+        let mut scene = Scene::default();
+        let camera = scene.active_camera_mut();
+        camera.sensor.horizontal_res = 64;
+        camera.sensor.vertical_res = 48;
+        scene.shadermode = ShaderMode::_Solid;
+        scene.push_object(Object {
+            position: Vector::new(0.8, 0.3, -12.),
+            orientation: Orientation::from_euler(0.4, 0.7, 0.2),
+            meshes: vec![Mesh::_unit_cube()],
+            ..Object::default()
+        });
+
+        scene.rendermode = Rendermode::_RayTrace;
+        let traced = drawn_bounds(&render(&scene)).unwrap();
+        scene.rendermode = Rendermode::Rasterize;
+        let rasterized = drawn_bounds(&render(&scene)).unwrap();
+        // the wireframe's edges run along the silhouette, give or take a pixel
+        let close = |a: u32, b: u32| a.abs_diff(b) <= 1;
+        assert!(
+            close(traced.0, rasterized.0)
+                && close(traced.1, rasterized.1)
+                && close(traced.2, rasterized.2)
+                && close(traced.3, rasterized.3),
+            "traced {traced:?} rasterized {rasterized:?}"
+        );
+    }
+}
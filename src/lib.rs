@@ -0,0 +1,53 @@
+#![allow(mixed_script_confusables)] // allows unicode characters
+//! a spectral renderer
+//!
+//! build a `Scene`, in code, from one of the loaders or from a scene file, then `render` it into a `Film`
+pub mod animation;
+pub mod application;
+pub mod camera;
+pub mod color;
+pub mod empty;
+pub mod entity;
+pub mod film;
+pub mod geometry_pipeline;
+pub mod lighting;
+pub mod load_error;
+pub mod load_gltf;
+pub mod load_material_file;
+pub mod load_object_file;
+pub mod load_ply;
+pub mod load_stl;
+pub mod object;
+pub mod random;
+pub mod render_observer;
+pub mod save_mesh;
+pub mod scene;
+
+pub mod geometry;
+pub mod material;
+pub mod rasterization;
+pub mod ray_tracing;
+
+extern crate approx;
+extern crate rand;
+extern crate slotmap;
+extern crate stopwatch;
+
+pub use camera::{Camera, Lens, Sensor};
+pub use entity::Entity;
+pub use film::Film;
+pub use geometry::primitives::{Mesh, Vector, Vertex};
+pub use geometry_pipeline::{render, render_observed};
+pub use lighting::{Light, PointLight, Spectra};
+pub use material::BRDF;
+pub use object::Object;
+pub use render_observer::RenderObserver;
+pub use scene::scene::Scene;
+
+// the loaders, each returns a `LoadError` saying where a file went wrong
+pub use load_error::{LoadError, Strictness};
+pub use load_gltf::load_gltf;
+pub use load_object_file::{load_wavefront_obj, load_wavefront_objects};
+pub use load_ply::load_ply;
+pub use load_stl::load_stl;
+pub use scene::scene_file::{load_scene_file, save_scene_file};
@@ -29,6 +29,8 @@ pub trait Light: Debug + Sync + Send + Entity {
     /// the apex is where the light source is observed from
     /// as it currently stands, all position vectors exist in worldspace
     fn radiant_intensity(&self, apex: Vector) -> RadiantIntensity;
+    fn radiant_flux(&self) -> &RadiantFlux;
+    fn set_radiant_flux(&mut self, radiant_flux: RadiantFlux);
 }
 
 /// Isotrophic light source with output measured in watts in each wavelength
@@ -37,9 +39,12 @@ pub trait Light: Debug + Sync + Send + Entity {
 pub struct PointLight {
     pub position: Vector, // as always, this is relative to it's parent
     pub orientation: Orientation,
+    pub scale: Vector,
     pub radiant_flux: RadiantFlux, // power in each wavelength
     pub children: Vec<EntityKey>,
     pub parent: Option<EntityKey>,
+    pub name: Option<String>,
+    pub tags: Vec<String>,
 }
 
 impl Default for PointLight {
@@ -47,9 +52,12 @@ impl Default for PointLight {
         PointLight {
             position: ORIGIN,
             orientation: UP,
+            scale: Vector::ones(),
             children: Vec::new(),
 
             parent: None,
+            name: None,
+            tags: Vec::new(),
             radiant_flux: incandescent_spectra(2000., 1000.),
         }
     }
@@ -76,9 +84,18 @@ impl Light for PointLight {
     {
         (1. / (4. * _π) * self.radiant_flux.clone().0).into()
     }
+    fn radiant_flux(&self) -> &RadiantFlux {
+        &self.radiant_flux
+    }
+    fn set_radiant_flux(&mut self, radiant_flux: RadiantFlux) {
+        self.radiant_flux = radiant_flux;
+    }
 }
 
 impl Entity for PointLight {
+    fn clone_entity(&self) -> Box<dyn Entity> {
+        Box::new(self.clone())
+    }
     fn get_position(&self) -> Vector {
         self.position
     }
@@ -86,11 +103,38 @@ impl Entity for PointLight {
         self.orientation
     }
     fn get_scale(&self) -> Vector {
-        Vector::ones()
+        self.scale
     }
     fn set_parent(&mut self, parent: EntityKey) {
         self.parent = Some(parent);
     }
+    fn get_parent(&self) -> Option<EntityKey> {
+        self.parent
+    }
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+    fn set_position(&mut self, position: Vector) {
+        self.position = position;
+    }
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+    fn set_scale(&mut self, scale: Vector) {
+        self.scale = scale;
+    }
+    fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_string());
+    }
+    fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+    fn get_mut_tags(&mut self) -> &mut Vec<String> {
+        &mut self.tags
+    }
     fn get_children(&self) -> Vec<EntityKey> {
         self.children.clone()
     }
@@ -100,6 +144,9 @@ impl Entity for PointLight {
     fn as_light(&self) -> Option<&dyn Light> {
         Some(self)
     }
+    fn as_light_mut(&mut self) -> Option<&mut dyn Light> {
+        Some(self)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -235,6 +282,22 @@ pub fn const_spectra(value: f32) -> Spectra {
     }
 }
 
+/// [nanometers, value] pairs in increasing order, interpolated between and held past either end
+/// there has to be at least one
+pub fn tabulated_spectra(table: &[[f32; 2]]) -> Spectra {
+    let mut spectra = black_spectra();
+    for (i, value) in spectra.spectra.iter_mut().enumerate() {
+        let λ = i as f32 * 10. + 380.;
+        let above = table.partition_point(|&[table_λ, _]| table_λ < λ);
+        *value = match (table.get(above.wrapping_sub(1)), table.get(above)) {
+            (Some(&[λ0, v0]), Some(&[λ1, v1])) => v0 + (v1 - v0) * (λ - λ0) / (λ1 - λ0),
+            (Some(&[_, v]), None) | (None, Some(&[_, v])) => v,
+            (None, None) => panic!("a table needs at least one entry"),
+        };
+    }
+    spectra
+}
+
 pub fn monochroma_spectra(λ: f32, value: f32) -> Spectra {
     let mut spectra = black_spectra();
 
@@ -0,0 +1,220 @@
+use std::fmt;
+use std::str::SplitAsciiWhitespace;
+
+/// where in a file something went wrong, lines and columns count from 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// everything that can go wrong reading a model
+#[derive(Debug)]
+pub enum LoadError {
+    /// the file couldn't be read at all
+    Io { file: String, error: std::io::Error },
+    /// the file as a whole doesn't make sense, for formats without lines to point at
+    Invalid { file: String, reason: String },
+    /// a statement is missing values, or has ones that can't be read
+    Malformed { location: Location, reason: String },
+    /// a face refers to an element the file never defines
+    IndexOutOfRange {
+        location: Location,
+        /// "vertex", "texture coordinate", ...
+        element: &'static str,
+        /// as written in the file
+        index: i64,
+        /// how many of that element there are
+        count: usize,
+    },
+}
+
+impl LoadError {
+    pub fn io(file: &str, error: std::io::Error) -> LoadError {
+        LoadError::Io {
+            file: file.to_string(),
+            error,
+        }
+    }
+
+    pub fn invalid(file: &str, reason: impl fmt::Display) -> LoadError {
+        LoadError::Invalid {
+            file: file.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// None for errors that aren't about one spot in the file
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            LoadError::Io { .. } | LoadError::Invalid { .. } => None,
+            LoadError::Malformed { location, .. } => Some(location),
+            LoadError::IndexOutOfRange { location, .. } => Some(location),
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io { file, error } => write!(f, "{file}: {error}"),
+            LoadError::Invalid { file, reason } => write!(f, "{file}: {reason}"),
+            LoadError::Malformed { location, reason } => write!(f, "{location}: {reason}"),
+            LoadError::IndexOutOfRange {
+                location,
+                element,
+                index,
+                count,
+            } => write!(
+                f,
+                "{location}: {element} {index} is out of range, there are {count}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// what a loader does with a line it can't make sense of
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// the first problem is returned as the error
+    #[default]
+    Strict,
+    /// bad lines are skipped and collected as warnings
+    Lenient,
+}
+
+impl Strictness {
+    /// hands back the error when strict, otherwise files it under warnings
+    pub fn report(self, error: LoadError, warnings: &mut Vec<LoadError>) -> Result<(), LoadError> {
+        match self {
+            Strictness::Strict => Err(error),
+            Strictness::Lenient => {
+                warnings.push(error);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// one line of a text format, read a word at a time
+/// remembers where it is so anything wrong can be pointed at
+pub struct Statement<'a> {
+    file: &'a str,
+    /// from 1
+    line_number: usize,
+    line: &'a str,
+    words: SplitAsciiWhitespace<'a>,
+}
+
+impl<'a> Statement<'a> {
+    pub fn new(file: &'a str, line_number: usize, line: &'a str) -> Statement<'a> {
+        Statement {
+            file,
+            line_number,
+            line,
+            words: line.split_ascii_whitespace(),
+        }
+    }
+
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    pub fn next_word(&mut self) -> Option<&'a str> {
+        self.words.next()
+    }
+
+    /// whatever is left of the line, one word at a time
+    pub fn remaining(&mut self) -> impl Iterator<Item = &'a str> + '_ {
+        &mut self.words
+    }
+
+    /// a word from this line, or the end of the line when it's missing
+    pub fn location_of(&self, word: Option<&str>) -> Location {
+        let offset = match word {
+            // words are slices of the line, so their distance from its start is the column
+            Some(word) => word.as_ptr() as usize - self.line.as_ptr() as usize,
+            None => self.line.trim_end().len(),
+        };
+        Location {
+            file: self.file.to_string(),
+            line: self.line_number,
+            column: offset + 1,
+        }
+    }
+
+    pub fn malformed(&self, word: Option<&str>, reason: String) -> LoadError {
+        LoadError::Malformed {
+            location: self.location_of(word),
+            reason,
+        }
+    }
+
+    /// the next word, which has to be there
+    pub fn expect_word(&mut self, what: &str) -> Result<&'a str, LoadError> {
+        let word = self.words.next();
+        word.ok_or_else(|| self.malformed(None, format!("expected {what}")))
+    }
+
+    /// the next word as a number, `what` names it in the error
+    pub fn float(&mut self, what: &str) -> Result<f32, LoadError> {
+        let word = self.expect_word(what)?;
+        self.parse(word, what)
+    }
+
+    /// the next word as a number, if there is one
+    pub fn optional_float(&mut self, what: &str) -> Result<Option<f32>, LoadError> {
+        match self.words.next() {
+            Some(word) => self.parse(word, what).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn parse<T: std::str::FromStr>(&self, word: &str, what: &str) -> Result<T, LoadError> {
+        word.parse()
+            .map_err(|_| self.malformed(Some(word), format!("couldn't read `{word}` as {what}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LoadError, Statement, Strictness};
+
+    #[test]
+    fn statements_point_at_the_bad_word() {
+        let mut statement = Statement::new("box.obj", 7, "v  1.0 one");
+        statement.next_word();
+        assert_eq!(statement.float("x").unwrap(), 1.);
+        let error = statement.float("y").unwrap_err();
+        assert_eq!(error.to_string(), "box.obj:7:8: couldn't read `one` as y");
+        let error = statement.float("z").unwrap_err();
+        assert_eq!(error.location().unwrap().column, 11);
+        assert_eq!(error.to_string(), "box.obj:7:11: expected z");
+    }
+
+    #[test]
+    fn lenient_keeps_going() {
+        let error = || LoadError::io("gone.obj", std::io::ErrorKind::NotFound.into());
+        let mut warnings = Vec::new();
+        assert!(Strictness::Strict.report(error(), &mut warnings).is_err());
+        assert!(warnings.is_empty());
+        assert!(Strictness::Lenient.report(error(), &mut warnings).is_ok());
+        assert_eq!(warnings.len(), 1);
+    }
+}
@@ -0,0 +1,412 @@
+use std::{collections::HashMap, f32::consts::PI, fs, path::Path, sync::Arc};
+
+use base64::Engine;
+use gltf::{buffer::Source, camera::Projection, khr_lights_punctual::Kind, mesh::Mode, Gltf};
+
+use crate::{
+    camera::{Camera, Lens, Sensor},
+    color::rgb_to_spectra::rgb_to_spectra,
+    empty::Empty,
+    geometry::{
+        orientation::Orientation,
+        primitives::{Mesh, Vector, Vertex},
+    },
+    lighting::{black_spectra, PointLight},
+    load_error::LoadError,
+    material::{glossy::Glossy, Sidedness, BRDF},
+    object::Object,
+    scene::scene::{EntityKey, Scene},
+};
+
+/// lumens per watt at 555nm, where the eye is most sensitive
+const LUMINOUS_EFFICACY: f32 = 683.;
+
+/// a fresh scene holding the file's default scene, along with whatever in it was skipped
+pub fn load_gltf(path: String) -> Result<(Scene, Vec<LoadError>), LoadError> {
+    let mut scene = Scene::default();
+    let (_, warnings) = import_gltf(&mut scene, path)?;
+    Ok((scene, warnings))
+}
+
+/// adds the default scene of a .gltf or .glb file beneath the root
+/// the first camera found becomes the active camera
+/// returns the entities made for the top level nodes, and warnings for what couldn't be imported
+pub fn import_gltf(
+    scene: &mut Scene,
+    path: String,
+) -> Result<(Vec<EntityKey>, Vec<LoadError>), LoadError> {
+    let bytes = fs::read(&path).map_err(|error| LoadError::io(&path, error))?;
+    let Gltf { document, blob } =
+        Gltf::from_slice(&bytes).map_err(|error| LoadError::invalid(&path, error))?;
+    let buffers = load_buffers(&document, blob, &path)?;
+    let gltf_scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| LoadError::invalid(&path, "there is no scene in the file"))?;
+
+    let mut importer = Importer {
+        file: &path,
+        buffers,
+        materials: HashMap::new(),
+        found_camera: false,
+        warnings: Vec::new(),
+    };
+    let root = scene.root;
+    let keys = gltf_scene
+        .nodes()
+        .map(|node| importer.import_node(scene, &node, root))
+        .collect::<Result<_, _>>()?;
+    Ok((keys, importer.warnings))
+}
+
+/// the buffers a document refers to, from the glb's binary chunk, data uris or files beside it
+fn load_buffers(
+    document: &gltf::Document,
+    mut blob: Option<Vec<u8>>,
+    path: &str,
+) -> Result<Vec<Vec<u8>>, LoadError> {
+    let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+    document
+        .buffers()
+        .map(|buffer| {
+            let data = match buffer.source() {
+                Source::Bin => blob
+                    .take()
+                    .ok_or_else(|| LoadError::invalid(path, "the binary chunk is missing"))?,
+                Source::Uri(uri) => match uri.strip_prefix("data:") {
+                    Some(data_uri) => {
+                        let (_, encoded) = data_uri.split_once(";base64,").ok_or_else(|| {
+                            LoadError::invalid(path, "only base64 data uris are supported")
+                        })?;
+                        base64::engine::general_purpose::STANDARD
+                            .decode(encoded)
+                            .map_err(|error| LoadError::invalid(path, error))?
+                    }
+                    None => {
+                        let file = directory.join(uri);
+                        fs::read(&file)
+                            .map_err(|error| LoadError::io(&file.to_string_lossy(), error))?
+                    }
+                },
+            };
+            if data.len() < buffer.length() {
+                return Err(LoadError::invalid(
+                    path,
+                    format!("buffer {} is shorter than it claims", buffer.index()),
+                ));
+            }
+            Ok(data)
+        })
+        .collect()
+}
+
+/// something a node carries
+enum Part {
+    Camera(Camera),
+    Light(PointLight),
+    Object(Object),
+}
+
+struct Importer<'a> {
+    file: &'a str,
+    buffers: Vec<Vec<u8>>,
+    /// shared between every primitive using the same material
+    materials: HashMap<usize, Arc<dyn BRDF>>,
+    found_camera: bool,
+    /// what was skipped or only partly imported
+    warnings: Vec<LoadError>,
+}
+
+impl Importer<'_> {
+    /// a node and everything beneath it
+    /// a node carrying a single thing becomes that thing,
+    /// otherwise it's an empty with each thing hanging beneath it
+    fn import_node(
+        &mut self,
+        scene: &mut Scene,
+        node: &gltf::Node,
+        parent: EntityKey,
+    ) -> Result<EntityKey, LoadError> {
+        let mut parts = Vec::new();
+        if let Some(camera) = node.camera() {
+            parts.extend(self.camera(&camera).map(Part::Camera));
+        }
+        if let Some(light) = node.light() {
+            parts.extend(self.light(&light).map(Part::Light));
+        }
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                if let Some(mut object) = self.object(&primitive)? {
+                    object.name = mesh.name().map(str::to_string);
+                    parts.push(Part::Object(object));
+                }
+            }
+        }
+
+        let key = if parts.len() == 1 {
+            self.insert(scene, parts.pop().unwrap())
+        } else {
+            let key = scene.insert(Empty::default());
+            for part in parts {
+                let part = self.insert(scene, part);
+                scene.attach(part, key);
+            }
+            key
+        };
+        scene.attach(key, parent);
+
+        let (translation, [x, y, z, w], scale) = node.transform().decomposed();
+        let entity = scene.get_mut(key);
+        entity.set_position(translation.into());
+        entity.set_orientation(Orientation { w, x, y, z });
+        entity.set_scale(scale.into());
+        if let Some(name) = node.name() {
+            entity.set_name(name);
+        }
+
+        for child in node.children() {
+            self.import_node(scene, &child, key)?;
+        }
+        Ok(key)
+    }
+
+    fn warn(&mut self, reason: impl std::fmt::Display) {
+        self.warnings.push(LoadError::invalid(self.file, reason));
+    }
+
+    fn insert(&mut self, scene: &mut Scene, part: Part) -> EntityKey {
+        match part {
+            Part::Camera(camera) => {
+                let key = scene.insert(camera);
+                if !self.found_camera {
+                    scene.set_active_camera(key);
+                    self.found_camera = true;
+                }
+                key
+            }
+            Part::Light(light) => scene.push_simple_light(light),
+            Part::Object(object) => scene.push_object(object),
+        }
+    }
+
+    /// the default sensor, with the focal length and resolution giving the same view
+    fn camera(&mut self, camera: &gltf::Camera) -> Option<Camera> {
+        let Projection::Perspective(perspective) = camera.projection() else {
+            self.warn("orthographic cameras are skipped");
+            return None;
+        };
+        let mut sensor = Sensor::default();
+        if let Some(aspect_ratio) = perspective.aspect_ratio() {
+            sensor.vertical_res = (sensor.horizontal_res as f32 / aspect_ratio).round() as u32;
+        }
+        // gltf gives the vertical field of view
+        let focal_length = sensor.height() / (2. * (perspective.yfov() / 2.).tan());
+        Some(Camera {
+            lens: Lens {
+                focal_length,
+                ..Lens::default()
+            },
+            sensor,
+            ..Camera::default()
+        })
+    }
+
+    /// point lights, spot lights shine in every direction for now
+    fn light(&mut self, light: &gltf::khr_lights_punctual::Light) -> Option<PointLight> {
+        match light.kind() {
+            Kind::Point => {}
+            Kind::Spot { .. } => self.warn("spot lights are imported as point lights"),
+            Kind::Directional => {
+                self.warn("directional lights are skipped");
+                return None;
+            }
+        }
+        // candela is lumens per steradian, over the whole sphere and turned into watts
+        let watts = 4. * PI * light.intensity() / LUMINOUS_EFFICACY;
+        let [r, g, b] = light.color();
+        let color = rgb_to_spectra((r, g, b));
+        let total = color.integrated();
+        let radiant_flux = if total > 0. {
+            (watts / total) * color
+        } else {
+            black_spectra()
+        };
+        Some(PointLight {
+            radiant_flux: radiant_flux.into(),
+            ..PointLight::default()
+        })
+    }
+
+    fn object(&mut self, primitive: &gltf::Primitive) -> Result<Option<Object>, LoadError> {
+        if primitive.mode() != Mode::Triangles {
+            self.warn(format!(
+                "only triangle primitives are imported, skipping {:?}",
+                primitive.mode()
+            ));
+            return Ok(None);
+        }
+        let material = self.material(&primitive.material());
+
+        let reader = primitive.reader(|buffer| self.buffers.get(buffer.index()).map(Vec::as_slice));
+        let positions = reader
+            .read_positions()
+            .ok_or_else(|| LoadError::invalid(self.file, "a primitive has no positions"))?;
+        let mut normals = reader.read_normals();
+        let mut uvs = reader.read_tex_coords(0).map(|uvs| uvs.into_f32());
+        let vertices: Vec<Vertex> = positions
+            .map(|[x, y, z]| Vertex {
+                uv_coord: uvs
+                    .as_mut()
+                    .and_then(Iterator::next)
+                    .map(|[u, v]| (u, v))
+                    .unwrap_or((0., 0.)),
+                normal: normals
+                    .as_mut()
+                    .and_then(Iterator::next)
+                    .map(|normal| Vector::from(normal).unitized()),
+                ..Vertex::new(x, y, z)
+            })
+            .collect();
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..vertices.len()).collect(),
+        };
+
+        if !indices.len().is_multiple_of(3) {
+            return Err(LoadError::invalid(
+                self.file,
+                format!("{} indices don't make whole triangles", indices.len()),
+            ));
+        }
+        if let Some(index) = indices.iter().find(|&&i| i >= vertices.len()) {
+            return Err(LoadError::invalid(
+                self.file,
+                format!(
+                    "vertex {index} is out of range, there are {}",
+                    vertices.len()
+                ),
+            ));
+        }
+        let polygons = indices.chunks_exact(3).map(<[usize]>::to_vec).collect();
+
+        Ok(Some(Object {
+            meshes: vec![Mesh::new(vertices, polygons)],
+            material,
+            ..Object::default()
+        }))
+    }
+
+    /// metallic-roughness materials, the base color becomes the albedo
+    fn material(&mut self, material: &gltf::Material) -> Arc<dyn BRDF> {
+        let build = || -> Arc<dyn BRDF> {
+            let pbr = material.pbr_metallic_roughness();
+            let [r, g, b, _alpha] = pbr.base_color_factor();
+            Arc::new(Glossy {
+                albedo: rgb_to_spectra((r, g, b)),
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                // single sided gltf materials are culled from behind
+                sidedness: if material.double_sided() {
+                    Sidedness::TwoSided
+                } else {
+                    Sidedness::BackfaceCulled
+                },
+            })
+        };
+        match material.index() {
+            Some(index) => self.materials.entry(index).or_insert_with(build).clone(),
+            // the default material isn't in the file
+            None => build(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::{geometry::primitives::Vector, lighting::white_spectra, scene::scene::Scene};
+
+    use super::load_gltf;
+
+    fn check_rig(scene: &mut Scene) {
+        scene.cascade_transforms();
+        let rig = scene.find_by_name("Rig").unwrap();
+        assert_eq!(scene.get(rig).get_parent(), Some(scene.root));
+        assert_eq!(scene.get(rig).get_children().len(), 4);
+
+        // rotated a quarter turn about y and doubled, under a parent moved along x
+        let triangle = scene.find_by_name("Triangle").unwrap();
+        assert_eq!(scene.get(triangle).get_parent(), Some(rig));
+        let corner = scene
+            .world_transform(triangle)
+            .apply_to_point(&Vector::new(1., 0., 0.));
+        assert_abs_diff_eq!(corner.x, 1., epsilon = 1e-5);
+        assert_abs_diff_eq!(corner.z, -2., epsilon = 1e-5);
+        let object = scene.get(triangle).as_object().unwrap();
+        let mesh = &object.meshes[0];
+        assert_eq!(mesh.polygons.len(), 1);
+        assert_eq!(mesh.vertices[1].uv_coord, (1., 0.));
+        assert_eq!(mesh.vertices[2].normal, Some(Vector::new(0., 0., 1.)));
+        let up = Vector::new(0., 0., 1.);
+        let red = object
+            .material
+            .rendering_equation(&up, &up, &up, &up, white_spectra().into())
+            .0;
+        assert!(red.from_λ(650.) > 3. * red.from_λ(530.));
+
+        // two primitives with their own materials hang beneath an empty
+        let pair = scene.find_by_name("Pair").unwrap();
+        let primitives = scene.get(pair).get_children();
+        assert!(scene.get(pair).as_object().is_none());
+        assert_eq!(primitives.len(), 2);
+        let first = scene.get(primitives[0]).as_object().unwrap();
+        let second = scene.get(primitives[1]).as_object().unwrap();
+        assert!(std::sync::Arc::ptr_eq(&first.material, &object.material));
+        assert!(!std::sync::Arc::ptr_eq(&second.material, &object.material));
+        assert_eq!(second.meshes[0].polygons[0], vec![0, 1, 2]);
+
+        // yfov of 0.5 radians at 3:2
+        let camera = scene.find_by_name("Camera").unwrap();
+        assert_eq!(scene.active_camera_key(), camera);
+        let camera = scene.active_camera();
+        assert_eq!(camera.sensor.res(), (1500, 1000));
+        assert_abs_diff_eq!(
+            camera._vertical_field_of_view().to_radians(),
+            0.5,
+            epsilon = 1e-5
+        );
+        assert_abs_diff_eq!(scene.world_position(scene.active_camera_key()).x, 1.);
+
+        // the sun has nothing to become, the lamp is 100 candela
+        let sun = scene.find_by_name("Sun").unwrap();
+        assert!(scene.get(sun).as_light().is_none());
+        assert_eq!(scene.simple_lights.len(), 1);
+        let (position, lamp) = scene.simple_lights().next().unwrap();
+        assert_abs_diff_eq!(position.y, 3.);
+        let intensity = lamp.radiant_intensity(position).0.integrated();
+        assert_abs_diff_eq!(intensity, 100. / 683., epsilon = 1e-4);
+    }
+
+    #[test]
+    fn imports_gltf() {
+        let (mut scene, warnings) = load_gltf("models/rig.gltf".to_string()).unwrap();
+        check_rig(&mut scene);
+        let warnings: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec!["models/rig.gltf: directional lights are skipped"]
+        );
+    }
+
+    #[test]
+    fn imports_glb() {
+        check_rig(&mut load_gltf("models/rig.glb".to_string()).unwrap().0);
+    }
+
+    #[test]
+    fn missing_files_are_errors() {
+        let error = load_gltf("models/missing.glb".to_string()).err().unwrap();
+        assert!(error.to_string().starts_with("models/missing.glb: "));
+    }
+}
@@ -0,0 +1,337 @@
+use crate::color::rgb_to_spectra::rgb_to_spectra;
+use crate::load_error::{LoadError, Statement, Strictness};
+use crate::material::dielectric::{Dielectric, Dispersion};
+use crate::material::glossy::Glossy;
+use crate::material::{emissive::Emissive, Diffuse, BRDF};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+/// for see-through materials that don't give an `Ni`, about that of window glass
+const DEFAULT_IOR: f32 = 1.5;
+
+/// one `newmtl` block of a Wavefront mtl file
+/// colors are linear RGB, exactly as written in the file
+#[derive(Clone, Debug, PartialEq)]
+pub struct MtlMaterial {
+    pub name: String,
+    /// Kd
+    pub diffuse: (f32, f32, f32),
+    /// Ks
+    pub specular: (f32, f32, f32),
+    /// Ns, 0 to 1000
+    pub specular_exponent: f32,
+    /// Ni
+    pub index_of_refraction: f32,
+    /// d, 1 is fully opaque. `Tr` is read as 1 - d
+    pub dissolve: f32,
+    /// Ke
+    pub emission: (f32, f32, f32),
+    /// illum
+    pub illumination_model: u32,
+    /// `map_Kd`, `map_Ks`, `map_Bump`, ... keyed by the statement, paths relative to the mtl file
+    pub texture_maps: HashMap<String, String>,
+}
+
+impl MtlMaterial {
+    pub fn new(name: &str) -> MtlMaterial {
+        MtlMaterial {
+            name: name.to_string(),
+            diffuse: (0.8, 0.8, 0.8),
+            specular: (0., 0., 0.),
+            specular_exponent: 0.,
+            index_of_refraction: 1.,
+            dissolve: 1.,
+            emission: (0., 0., 0.),
+            illumination_model: 2,
+            texture_maps: HashMap::new(),
+        }
+    }
+
+    /// the closest BRDF the renderer has
+    /// - anything with an emission is a light
+    /// - anything see-through, by `d` or by an illum model with refraction, is glass of index `Ni`
+    /// - illum models with ray traced reflection but no refraction are metal, tinted by `Ks`
+    /// - any other illum model from 2 up with a `Ks` is glossy, with `Ns` setting how sharp the highlight is
+    /// - the rest are diffuse
+    ///
+    /// Glossy always reflects 4% straight on, so `Ks` only says whether there's a highlight, not how strong it is
+    pub fn to_brdf(&self) -> Arc<dyn BRDF> {
+        if self.emission != (0., 0., 0.) {
+            return Arc::new(Emissive {
+                radiance: rgb_to_spectra(self.emission),
+                ..Emissive::default()
+            });
+        }
+        let has_specular = self.specular != (0., 0., 0.);
+        if self.dissolve < 1. || matches!(self.illumination_model, 4 | 6 | 7 | 9) {
+            // 1 is the default and would make it invisible, files that leave it out get ordinary glass
+            let ior = if self.index_of_refraction > 1. {
+                self.index_of_refraction
+            } else {
+                DEFAULT_IOR
+            };
+            return Arc::new(Dielectric {
+                ior: Dispersion::constant(ior),
+            });
+        }
+        if matches!(self.illumination_model, 3 | 5 | 8) && has_specular {
+            return Arc::new(Glossy {
+                albedo: rgb_to_spectra(self.specular),
+                metallic: 1.,
+                roughness: self.roughness(),
+                ..Glossy::default()
+            });
+        }
+        if self.illumination_model >= 2 && has_specular {
+            return Arc::new(Glossy {
+                albedo: rgb_to_spectra(self.diffuse),
+                roughness: self.roughness(),
+                ..Glossy::default()
+            });
+        }
+        Arc::new(Diffuse {
+            albedo: rgb_to_spectra(self.diffuse),
+            ..Diffuse::default()
+        })
+    }
+
+    /// the usual match of a Phong exponent to a microfacet width, α = √(2 / (Ns + 2)), as a perceptual roughness
+    fn roughness(&self) -> f32 {
+        let alpha = (2. / (self.specular_exponent.max(0.) + 2.)).sqrt();
+        alpha.sqrt()
+    }
+}
+
+/// everything read out of a Wavefront mtl file
+#[derive(Debug)]
+pub struct MaterialLibrary {
+    pub materials: Vec<MtlMaterial>,
+    /// what was skipped when read leniently
+    pub warnings: Vec<LoadError>,
+}
+
+pub fn read_wavefront_mtl(
+    path: String,
+    strictness: Strictness,
+) -> Result<MaterialLibrary, LoadError> {
+    let data = fs::read_to_string(&path).map_err(|error| LoadError::io(&path, error))?;
+    parse_wavefront_mtl(&data, &path, strictness)
+}
+
+/// `file` only names the source in errors
+pub fn parse_wavefront_mtl(
+    data: &str,
+    file: &str,
+    strictness: Strictness,
+) -> Result<MaterialLibrary, LoadError> {
+    let mut materials: Vec<MtlMaterial> = Vec::new();
+    let mut warnings = Vec::new();
+    for (i, line) in data.lines().enumerate() {
+        let statement = Statement::new(file, i + 1, line);
+        if let Err(error) = read_statement(statement, &mut materials) {
+            strictness.report(error, &mut warnings)?;
+        }
+    }
+    Ok(MaterialLibrary {
+        materials,
+        warnings,
+    })
+}
+
+/// a line is either read entirely or leaves the materials as they were
+fn read_statement(
+    mut statement: Statement,
+    materials: &mut Vec<MtlMaterial>,
+) -> Result<(), LoadError> {
+    let Some(keyword) = statement.next_word() else {
+        return Ok(());
+    };
+    if keyword.starts_with('#') {
+        return Ok(());
+    }
+    if keyword == "newmtl" {
+        let name = statement.remaining().collect::<Vec<_>>().join(" ");
+        materials.push(MtlMaterial::new(&name));
+        return Ok(());
+    }
+    // anything before the first newmtl has nothing to belong to
+    let Some(material) = materials.last_mut() else {
+        return Ok(());
+    };
+    match keyword {
+        "Kd" => material.diffuse = read_rgb(&mut statement)?,
+        "Ks" => material.specular = read_rgb(&mut statement)?,
+        "Ke" => material.emission = read_rgb(&mut statement)?,
+        "Ns" => material.specular_exponent = statement.float("an exponent")?,
+        "Ni" => material.index_of_refraction = statement.float("an index of refraction")?,
+        "d" => material.dissolve = statement.float("a dissolve")?,
+        "Tr" => material.dissolve = 1. - statement.float("a transparency")?,
+        "illum" => {
+            let word = statement.expect_word("an illumination model")?;
+            material.illumination_model = statement.parse(word, "an illumination model")?;
+        }
+        map if map.starts_with("map_") || map == "bump" || map == "disp" || map == "norm" => {
+            // options like `-bm 1.0` come first, the file name is always last
+            let Some(file) = statement.remaining().last() else {
+                return Err(statement.malformed(None, "expected a texture file".to_string()));
+            };
+            material
+                .texture_maps
+                .insert(map.to_string(), file.to_string());
+        }
+        // ambient color, transmission filter, sharpness and the rest are ignored
+        _ => {}
+    }
+    Ok(())
+}
+
+/// loads every library an obj file refers to, as BRDFs keyed by material name
+/// library paths are taken relative to the directory of the obj file
+pub fn load_material_libraries(
+    obj_path: &str,
+    libraries: &[String],
+    strictness: Strictness,
+    warnings: &mut Vec<LoadError>,
+) -> Result<HashMap<String, Arc<dyn BRDF>>, LoadError> {
+    let directory = std::path::Path::new(obj_path)
+        .parent()
+        .unwrap_or(std::path::Path::new(""));
+    let mut brdfs = HashMap::new();
+    for library in libraries {
+        let path = directory.join(library).to_string_lossy().to_string();
+        // a missing library costs the colors, not the model
+        let library = match read_wavefront_mtl(path, strictness) {
+            Ok(library) => library,
+            Err(error) => {
+                strictness.report(error, warnings)?;
+                continue;
+            }
+        };
+        warnings.extend(library.warnings);
+        for material in library.materials {
+            brdfs.insert(material.name.clone(), material.to_brdf());
+        }
+    }
+    Ok(brdfs)
+}
+
+/// a single value stands for a grey
+fn read_rgb(statement: &mut Statement) -> Result<(f32, f32, f32), LoadError> {
+    let r = statement.float("red")?;
+    match statement.optional_float("green")? {
+        Some(g) => Ok((r, g, statement.float("blue")?)),
+        None => Ok((r, r, r)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::{load_error::Strictness, scene::scene_file::MaterialDescription};
+
+    use super::parse_wavefront_mtl;
+
+    #[test]
+    fn reads_every_statement() {
+        let library = parse_wavefront_mtl(
+            "# a comment\n\
+             newmtl glass\n\
+             Kd 0.1 0.2 0.3\n\
+             Ks 0.5\n\
+             Ns 250\n\
+             Ni 1.45\n\
+             Tr 0.9\n\
+             illum 7\n\
+             map_Kd -s 1 1 1 textures/glass.png\n\
+             bump normal.png\n\
+             \n\
+             newmtl light\n\
+             Ke 17 12 4\n",
+            "test.mtl",
+            Strictness::Strict,
+        )
+        .unwrap();
+        let materials = library.materials;
+        assert_eq!(materials.len(), 2);
+        let glass = &materials[0];
+        assert_eq!(glass.name, "glass");
+        assert_eq!(glass.diffuse, (0.1, 0.2, 0.3));
+        assert_eq!(glass.specular, (0.5, 0.5, 0.5));
+        assert_eq!(glass.specular_exponent, 250.);
+        assert_eq!(glass.index_of_refraction, 1.45);
+        assert_abs_diff_eq!(glass.dissolve, 0.1, epsilon = 1e-6);
+        assert_eq!(glass.illumination_model, 7);
+        assert_eq!(glass.texture_maps["map_Kd"], "textures/glass.png");
+        assert_eq!(glass.texture_maps["bump"], "normal.png");
+
+        let light = &materials[1];
+        assert_eq!(light.emission, (17., 12., 4.));
+        // defaults for what the file leaves out
+        assert_eq!(light.diffuse, (0.8, 0.8, 0.8));
+        assert_eq!(light.dissolve, 1.);
+    }
+
+    #[test]
+    fn bad_values_are_located() {
+        let data = "newmtl red\nKd 1 0.1\nNs 10\n";
+        let error = parse_wavefront_mtl(data, "red.mtl", Strictness::Strict).unwrap_err();
+        assert_eq!(error.to_string(), "red.mtl:2:9: expected blue");
+
+        let library = parse_wavefront_mtl(data, "red.mtl", Strictness::Lenient).unwrap();
+        assert_eq!(library.warnings.len(), 1);
+        // the bad line is skipped whole, the rest still counts
+        assert_eq!(library.materials[0].diffuse, (0.8, 0.8, 0.8));
+        assert_eq!(library.materials[0].specular_exponent, 10.);
+    }
+
+    #[test]
+    fn materials_map_to_the_closest_brdf() {
+        let library = parse_wavefront_mtl(
+            "newmtl matte\nKd 0.5\nillum 1\n\
+             newmtl plastic\nKd 0.5\nKs 1\nNs 1000\nillum 2\n\
+             newmtl chrome\nKs 0.9\nNs 100\nillum 3\n\
+             newmtl water\nd 0.2\nNi 1.33\n\
+             newmtl window\nillum 4\n\
+             newmtl lamp\nKe 5\nd 0.5\n",
+            "kinds.mtl",
+            Strictness::Strict,
+        )
+        .unwrap();
+        let described: Vec<MaterialDescription> = library
+            .materials
+            .iter()
+            .map(|material| material.to_brdf().describe().unwrap())
+            .collect();
+        assert!(matches!(described[0], MaterialDescription::Diffuse { .. }));
+        let MaterialDescription::Glossy {
+            metallic,
+            roughness: sharp,
+            ..
+        } = described[1]
+        else {
+            panic!("expected plastic to be glossy");
+        };
+        assert_eq!(metallic, 0.);
+        let MaterialDescription::Glossy {
+            metallic,
+            roughness,
+            ..
+        } = described[2]
+        else {
+            panic!("expected chrome to be glossy");
+        };
+        assert_eq!(metallic, 1.);
+        // a higher exponent is a tighter highlight
+        assert!(sharp < roughness && roughness < 1.);
+        let ior = |description: &MaterialDescription| match description {
+            MaterialDescription::Dielectric { ior } => ior.to_dispersion().unwrap().ior(550.),
+            _ => panic!("expected a dielectric"),
+        };
+        assert_abs_diff_eq!(ior(&described[3]), 1.33, epsilon = 1e-6);
+        assert_abs_diff_eq!(ior(&described[4]), 1.5, epsilon = 1e-6);
+        // glowing wins over everything
+        assert!(matches!(described[5], MaterialDescription::Emissive { .. }));
+    }
+}
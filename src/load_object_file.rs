@@ -1,44 +1,574 @@
-use crate::geometry::primitives::{Mesh, Vertex};
+use crate::geometry::primitives::{Mesh, MeshSource, Vector, Vertex};
+use crate::geometry::triangulation::triangulate;
+use crate::load_error::{LoadError, Location, Statement, Strictness};
+use crate::load_material_file::load_material_libraries;
+use crate::material::BRDF;
+use crate::object::Object;
+use std::collections::HashMap;
 use std::fs;
-pub fn load_wavefront_obj(path: String) -> Mesh {
-    let data = fs::read_to_string(path).expect("Unable to read file");
+use std::sync::Arc;
 
+/// a run of faces in an obj file that share an `o` or `g` name and a material
+#[derive(Clone, Debug)]
+pub struct ObjGroup {
+    pub name: String,
+    /// the `usemtl` name, looked up in the file's material libraries
+    pub material: Option<String>,
+    pub mesh: Mesh,
+}
+
+/// everything read out of a Wavefront obj file
+#[derive(Debug)]
+pub struct WavefrontObj {
+    /// `mtllib` paths, relative to the obj file
+    pub material_libraries: Vec<String>,
+    pub groups: Vec<ObjGroup>,
+    /// what was skipped when read leniently
+    pub warnings: Vec<LoadError>,
+}
+
+impl WavefrontObj {
+    /// every group in one mesh
+    pub fn merged(&self) -> Mesh {
+        self.merge(|_| true)
+    }
+
+    /// the groups with this name and material in one mesh, either can be left out to take any
+    /// None when no group matches
+    pub fn part(&self, group: Option<&str>, material: Option<&str>) -> Option<Mesh> {
+        let matches = |candidate: &ObjGroup| {
+            group.is_none_or(|name| candidate.name == name)
+                && material.is_none_or(|name| candidate.material.as_deref() == Some(name))
+        };
+        self.groups.iter().any(matches).then(|| self.merge(matches))
+    }
+
+    fn merge(&self, keep: impl Fn(&ObjGroup) -> bool) -> Mesh {
+        let mut vertices = Vec::new();
+        let mut polygons = Vec::new();
+        for group in self.groups.iter().filter(|group| keep(group)) {
+            let offset = vertices.len();
+            vertices.extend(group.mesh.vertices.iter().cloned());
+            for polygon in group.mesh.polygons.iter() {
+                polygons.push(polygon.iter().map(|i| i + offset).collect());
+            }
+        }
+        Mesh::new(vertices, polygons)
+    }
+
+    /// an object per group, named after it
+    /// groups whose material isn't in `materials` keep the default one
+    pub fn into_objects(self, materials: &HashMap<String, Arc<dyn BRDF>>) -> Vec<Object> {
+        self.groups
+            .into_iter()
+            .map(|group| {
+                let mut object = Object {
+                    name: Some(group.name),
+                    meshes: vec![group.mesh],
+                    ..Object::default()
+                };
+                if let Some(brdf) = group.material.and_then(|name| materials.get(&name)) {
+                    object.material = brdf.clone();
+                }
+                object
+            })
+            .collect()
+    }
+}
+
+/// a single corner of a face, as indices into the file's v, vt and vn lists
+type Corner = (usize, Option<usize>, Option<usize>);
+
+struct Face {
+    corners: Vec<Corner>,
+    line: usize,
+    /// where each corner was written, for pointing at bad indices
+    columns: Vec<usize>,
+}
+
+struct GroupBuilder {
+    name: String,
+    material: Option<String>,
+    faces: Vec<Face>,
+}
+
+pub fn load_wavefront_obj(path: String) -> Result<Mesh, LoadError> {
+    let mut mesh = read_wavefront_obj(path.clone(), Strictness::Strict)?.merged();
+    mesh.source = Some(MeshSource::file(&path));
+    Ok(mesh)
+}
+
+/// an object per group, wearing the materials from the file's `mtllib`s
+/// when lenient, whatever was skipped is returned alongside them, for the caller to pass on
+pub fn load_wavefront_objects(
+    path: String,
+    strictness: Strictness,
+) -> Result<(Vec<Object>, Vec<LoadError>), LoadError> {
+    let mut obj = read_wavefront_obj(path.clone(), strictness)?;
+    let materials = load_material_libraries(
+        &path,
+        &obj.material_libraries,
+        strictness,
+        &mut obj.warnings,
+    )?;
+    let warnings = std::mem::take(&mut obj.warnings);
+    Ok((obj.into_objects(&materials), warnings))
+}
+
+/// every group's mesh remembers which part of the file it is
+pub fn read_wavefront_obj(path: String, strictness: Strictness) -> Result<WavefrontObj, LoadError> {
+    let data = fs::read_to_string(&path).map_err(|error| LoadError::io(&path, error))?;
+    let mut obj = parse_wavefront_obj(&data, &path, strictness)?;
+    for group in &mut obj.groups {
+        group.mesh.source = Some(MeshSource {
+            group: Some(group.name.clone()),
+            material: group.material.clone(),
+            ..MeshSource::file(&path)
+        });
+    }
+    Ok(obj)
+}
+
+/// faces are triangulated, and vertices are split wherever corners sharing a position
+/// disagree on their texture coordinate or normal
+/// `file` only names the source in errors
+pub fn parse_wavefront_obj(
+    data: &str,
+    file: &str,
+    strictness: Strictness,
+) -> Result<WavefrontObj, LoadError> {
+    let mut obj = ObjReader {
+        positions: Vec::new(),
+        uvs: Vec::new(),
+        normals: Vec::new(),
+        material_libraries: Vec::new(),
+        groups: vec![GroupBuilder {
+            name: "default".to_string(),
+            material: None,
+            faces: Vec::new(),
+        }],
+    };
+    let mut warnings = Vec::new();
+    for (i, line) in data.lines().enumerate() {
+        let statement = Statement::new(file, i + 1, line);
+        if let Err(error) = obj.read(statement) {
+            strictness.report(error, &mut warnings)?;
+        }
+    }
+
+    let counts = [obj.positions.len(), obj.uvs.len(), obj.normals.len()];
+    let mut groups = Vec::new();
+    for mut group in obj.groups {
+        // indices are only checked once the whole file is read, faces may refer ahead
+        let mut faces = Vec::with_capacity(group.faces.len());
+        for face in group.faces.drain(..) {
+            match check_indices(&face, counts, file) {
+                Ok(()) => faces.push(face),
+                Err(error) => strictness.report(error, &mut warnings)?,
+            }
+        }
+        if faces.is_empty() {
+            continue;
+        }
+        groups.push(ObjGroup {
+            mesh: build_mesh(&faces, &obj.positions, &obj.uvs, &obj.normals),
+            name: group.name,
+            material: group.material,
+        });
+    }
+
+    Ok(WavefrontObj {
+        material_libraries: obj.material_libraries,
+        groups,
+        warnings,
+    })
+}
+
+/// everything gathered so far while reading an obj file
+struct ObjReader {
+    positions: Vec<Vector>,
+    uvs: Vec<(f32, f32)>,
+    normals: Vec<Vector>,
+    material_libraries: Vec<String>,
+    groups: Vec<GroupBuilder>,
+}
+
+impl ObjReader {
+    /// a line is either read entirely or leaves everything as it was
+    fn read(&mut self, mut statement: Statement) -> Result<(), LoadError> {
+        let Some(keyword) = statement.next_word() else {
+            return Ok(());
+        };
+        match keyword {
+            "v" => {
+                let x = statement.float("x")?;
+                let y = statement.float("y")?;
+                let z = statement.float("z")?;
+                self.positions.push(Vector::new(x, y, z));
+            }
+            "vt" => {
+                let u = statement.float("u")?;
+                let v = statement.optional_float("v")?.unwrap_or(0.);
+                self.uvs.push((u, v));
+            }
+            "vn" => {
+                let x = statement.float("x")?;
+                let y = statement.float("y")?;
+                let z = statement.float("z")?;
+                self.normals.push(Vector::new(x, y, z).unitized());
+            }
+            "f" => {
+                let face = self.read_face(statement)?;
+                self.groups.last_mut().unwrap().faces.push(face);
+            }
+            // objects and groups both start a new group, the material carries over
+            "o" | "g" => {
+                let name = statement.remaining().collect::<Vec<_>>().join(" ");
+                let material = self.groups.last().unwrap().material.clone();
+                self.groups.push(GroupBuilder {
+                    name,
+                    material,
+                    faces: Vec::new(),
+                });
+            }
+            // a material change splits the group so every mesh has one material
+            "usemtl" => {
+                let material = statement.expect_word("a material name")?.to_string();
+                let name = self.groups.last().unwrap().name.clone();
+                self.groups.push(GroupBuilder {
+                    name,
+                    material: Some(material),
+                    faces: Vec::new(),
+                });
+            }
+            "mtllib" => {
+                self.material_libraries
+                    .extend(statement.remaining().map(str::to_string));
+            }
+            // comments, smoothing groups, lines, points and anything else are ignored
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// `v`, `v/vt`, `v//vn` or `v/vt/vn` for every corner
+    fn read_face(&self, mut statement: Statement) -> Result<Face, LoadError> {
+        let mut corners = Vec::new();
+        let mut columns = Vec::new();
+        while let Some(word) = statement.next_word() {
+            let mut indices = word.split('/');
+            let position = indices.next().unwrap_or("");
+            let uv = indices.next().unwrap_or("");
+            let normal = indices.next().unwrap_or("");
+            if position.is_empty() || indices.next().is_some() {
+                return Err(
+                    statement.malformed(Some(word), format!("`{word}` isn't a face corner"))
+                );
+            }
+            let position =
+                self.resolve(&statement, word, position, "vertex", self.positions.len())?;
+            let uv = self.resolve(&statement, word, uv, "texture coordinate", self.uvs.len())?;
+            let normal = self.resolve(&statement, word, normal, "normal", self.normals.len())?;
+            corners.push((position.unwrap(), uv, normal));
+            columns.push(statement.location_of(Some(word)).column);
+        }
+        if corners.len() < 3 {
+            return Err(statement.malformed(None, "a face needs at least 3 corners".to_string()));
+        }
+        Ok(Face {
+            corners,
+            line: statement.line_number(),
+            columns,
+        })
+    }
+
+    /// obj starts counting at 1, and negative indices count back from the latest element
+    fn resolve(
+        &self,
+        statement: &Statement,
+        word: &str,
+        index: &str,
+        element: &'static str,
+        count: usize,
+    ) -> Result<Option<usize>, LoadError> {
+        if index.is_empty() {
+            return Ok(None);
+        }
+        let written: i64 = statement.parse(index, "an index")?;
+        match written {
+            0 => Err(statement.malformed(Some(word), format!("{element} indices start at 1"))),
+            i if i < 0 => {
+                let resolved = count as i64 + i;
+                if resolved < 0 {
+                    Err(LoadError::IndexOutOfRange {
+                        location: statement.location_of(Some(word)),
+                        element,
+                        index: written,
+                        count,
+                    })
+                } else {
+                    Ok(Some(resolved as usize))
+                }
+            }
+            i => Ok(Some(i as usize - 1)),
+        }
+    }
+}
+
+/// every corner has to land on something the file defined
+fn check_indices(face: &Face, counts: [usize; 3], file: &str) -> Result<(), LoadError> {
+    for (&(position, uv, normal), &column) in face.corners.iter().zip(&face.columns) {
+        let checks = [
+            (Some(position), "vertex", counts[0]),
+            (uv, "texture coordinate", counts[1]),
+            (normal, "normal", counts[2]),
+        ];
+        for (index, element, count) in checks {
+            if let Some(index) = index.filter(|&i| i >= count) {
+                return Err(LoadError::IndexOutOfRange {
+                    location: Location {
+                        file: file.to_string(),
+                        line: face.line,
+                        column,
+                    },
+                    element,
+                    index: index as i64 + 1,
+                    count,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn build_mesh(
+    faces: &[Face],
+    positions: &[Vector],
+    uvs: &[(f32, f32)],
+    normals: &[Vector],
+) -> Mesh {
     let mut vertices = Vec::new();
     let mut polygons = Vec::new();
-
-    // println!("{}", data);
-    for line in data.split('\n') {
-        // discards if it's a comment
-        if line.starts_with('#') {
-        }
-        // adds a vertex
-        else if line.starts_with("v ") {
-            let mut numbers = line
-                .trim()
-                .strip_prefix("v ")
-                .unwrap()
-                .split_ascii_whitespace();
-            let x: f32 = numbers.next().unwrap().parse().unwrap();
-            let y: f32 = numbers.next().unwrap().parse().unwrap();
-            let z: f32 = numbers.next().unwrap().parse().unwrap();
-            vertices.push(Vertex::new(x, y, z));
-        }
-        // adds a face
-        else if line.starts_with("f ") {
-            let numbers = line
-                .trim()
-                .strip_prefix("f ")
-                .unwrap()
-                .split_ascii_whitespace();
-            let mut polygon: Vec<usize> = Vec::new();
-            for number in numbers {
-                let number: &str = number.split('/').next().unwrap();
-                let mut number: usize = number.parse().unwrap();
-                number -= 1; // because obj starts at 1 which is stupid
-                polygon.push(number);
-            }
-            polygons.push(polygon);
+    let mut welded: HashMap<Corner, usize> = HashMap::new();
+    for face in faces {
+        let corners: Vec<usize> = face
+            .corners
+            .iter()
+            .map(|&corner| {
+                *welded.entry(corner).or_insert_with(|| {
+                    let (position, uv, normal) = corner;
+                    let Vector { x, y, z } = positions[position];
+                    vertices.push(Vertex {
+                        uv_coord: uv.map(|i| uvs[i]).unwrap_or((0., 0.)),
+                        normal: normal.map(|i| normals[i]),
+                        ..Vertex::new(x, y, z)
+                    });
+                    vertices.len() - 1
+                })
+            })
+            .collect();
+        let outline: Vec<Vector> = corners.iter().map(|&i| vertices[i].position).collect();
+        for [a, b, c] in triangulate(&outline) {
+            polygons.push(vec![corners[a], corners[b], corners[c]]);
         }
     }
     Mesh::new(vertices, polygons)
 }
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use std::collections::HashMap;
+
+    use crate::{
+        geometry::primitives::Vector,
+        lighting::white_spectra,
+        load_error::{LoadError, Strictness},
+    };
+
+    use super::{
+        load_wavefront_obj, load_wavefront_objects, parse_wavefront_obj, read_wavefront_obj,
+    };
+
+    #[test]
+    fn corners_keep_their_normals_and_uvs() {
+        let obj = parse_wavefront_obj(
+            "v 0 0 0\n\
+             v 1 0 0\n\
+             v 1 1 0\n\
+             v 0 1 0\n\
+             vt 0 0\n\
+             vt 1 1\n\
+             vn 0 0 2\n\
+             vn 0 1 0\n\
+             f 1/1/1 2/2/1 3/1/2 4//2\n",
+            "quad.obj",
+            Strictness::Strict,
+        )
+        .unwrap();
+        let mesh = obj.merged();
+        // the quad is split in two
+        assert_eq!(mesh.polygons.len(), 2);
+        assert!(mesh.polygons.iter().all(|p| p.len() == 3));
+        let first = &mesh.vertices[0];
+        assert_eq!(first.uv_coord, (0., 0.));
+        assert_abs_diff_eq!(first.normal.unwrap().z, 1.);
+        assert_eq!(mesh.vertices[1].uv_coord, (1., 1.));
+        assert_eq!(mesh.vertices[3].normal.unwrap().y, 1.);
+    }
+
+    #[test]
+    fn relative_indices_and_splitting_on_differences() {
+        let obj = parse_wavefront_obj(
+            "v 0 0 0\n\
+             v 1 0 0\n\
+             v 0 1 0\n\
+             vn 0 0 1\n\
+             vn 0 0 -1\n\
+             f -3//-2 -2//-2 -1//-2\n\
+             f 1//2 3//2 2//2\n",
+            "pair.obj",
+            Strictness::Strict,
+        )
+        .unwrap();
+        let mesh = obj.merged();
+        assert_eq!(mesh.polygons.len(), 2);
+        // same positions, different normals, so nothing is shared
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(mesh.polygons[0], vec![0, 1, 2]);
+        assert_eq!(mesh.vertices[5].position.x, 1.);
+    }
+
+    #[test]
+    fn groups_and_materials_split_meshes() {
+        let obj = parse_wavefront_obj(
+            "mtllib box.mtl\n\
+             v 0 0 0\n\
+             v 1 0 0\n\
+             v 0 1 0\n\
+             o first\n\
+             usemtl red\n\
+             f 1 2 3\n\
+             usemtl blue\n\
+             f 1 3 2\n\
+             g second\n\
+             f 3 2 1\n\
+             o empty\n",
+            "groups.obj",
+            Strictness::Strict,
+        )
+        .unwrap();
+        assert_eq!(obj.material_libraries, vec!["box.mtl".to_string()]);
+        let names: Vec<(&str, Option<&str>)> = obj
+            .groups
+            .iter()
+            .map(|g| (g.name.as_str(), g.material.as_deref()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("first", Some("red")),
+                ("first", Some("blue")),
+                ("second", Some("blue"))
+            ]
+        );
+        // only the vertices a group uses end up in its mesh
+        assert!(obj.groups.iter().all(|g| g.mesh.vertices.len() == 3));
+        let objects = obj.into_objects(&HashMap::new());
+        assert_eq!(objects.len(), 3);
+        assert_eq!(objects[2].name.as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn models_load() {
+        let sphere = load_wavefront_obj("models/sphere.obj".to_string()).unwrap();
+        assert_eq!(sphere.polygons.len(), 80);
+        assert!(sphere.vertices.iter().all(|v| v.normal.is_some()));
+        let cube = load_wavefront_obj("models/cube.obj".to_string()).unwrap();
+        assert_eq!(cube.polygons.len(), 12);
+        assert_eq!(cube.vertices.len(), 8);
+    }
+
+    #[test]
+    fn cornell_box_wears_its_colors() {
+        let (objects, warnings) =
+            load_wavefront_objects("models/cornell.obj".to_string(), Strictness::Strict).unwrap();
+        assert!(warnings.is_empty());
+        let reflected = |name: &str, λ: f32| {
+            let object = objects
+                .iter()
+                .find(|o| o.name.as_deref() == Some(name))
+                .unwrap();
+            let up = Vector::new(0., 1., 0.);
+            object
+                .material
+                .rendering_equation(&up, &up, &up, &up, white_spectra().into())
+                .0
+                .from_λ(λ)
+        };
+        assert!(reflected("left_wall", 650.) > 3. * reflected("left_wall", 530.));
+        assert!(reflected("right_wall", 530.) > 2. * reflected("right_wall", 650.));
+        assert_abs_diff_eq!(
+            reflected("floor", 650.),
+            reflected("floor", 530.),
+            epsilon = 0.01
+        );
+        // and the light panel glows warm
+        let light = objects
+            .iter()
+            .find(|o| o.name.as_deref() == Some("light"))
+            .unwrap();
+        let emission = light.material.emission().unwrap();
+        assert!(emission.from_λ(650.) > 3. * emission.from_λ(450.));
+        assert!(objects
+            .iter()
+            .filter(|o| o.name.as_deref() != Some("light"))
+            .all(|o| o.material.emission().is_none()));
+    }
+
+    #[test]
+    fn errors_say_where() {
+        let broken = "v 0 0 0\n\
+                      v 1 0 0\n\
+                      v 0 1 x\n\
+                      v 0 1 0\n\
+                      f 1 2 3\n\
+                      f 1 2\n\
+                      f 1 2 -9\n\
+                      f 1 0 3\n\
+                      f 1 2 7\n";
+        let strict = parse_wavefront_obj(broken, "broken.obj", Strictness::Strict);
+        assert_eq!(
+            strict.unwrap_err().to_string(),
+            "broken.obj:3:7: couldn't read `x` as z"
+        );
+
+        let lenient = parse_wavefront_obj(broken, "broken.obj", Strictness::Lenient).unwrap();
+        let warnings: Vec<String> = lenient.warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "broken.obj:3:7: couldn't read `x` as z",
+                "broken.obj:6:6: a face needs at least 3 corners",
+                "broken.obj:7:7: vertex -9 is out of range, there are 3",
+                "broken.obj:8:5: vertex indices start at 1",
+                // only checked once every vertex is known
+                "broken.obj:9:7: vertex 7 is out of range, there are 3",
+            ]
+        );
+        // the one good face survives
+        assert_eq!(lenient.merged().polygons.len(), 1);
+
+        // and loading objects hands the warnings back rather than printing them
+        let path = std::env::temp_dir().join("broken_objects.obj");
+        std::fs::write(&path, broken).unwrap();
+        let path = path.to_string_lossy().to_string();
+        let (objects, warnings) = load_wavefront_objects(path, Strictness::Lenient).unwrap();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(warnings.len(), 5);
+
+        let missing = read_wavefront_obj("models/missing.obj".to_string(), Strictness::Lenient);
+        assert!(matches!(missing, Err(LoadError::Io { .. })));
+    }
+}
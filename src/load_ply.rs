@@ -0,0 +1,543 @@
+use crate::geometry::primitives::{Mesh, MeshSource, Vector, Vertex};
+use crate::geometry::triangulation::triangulate;
+use crate::load_error::{LoadError, Location, Statement};
+use std::fs;
+
+/// how the elements after the header are stored
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// the number types a property can have
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Scalar {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl Scalar {
+    /// both the original names and the sized ones are in use
+    fn from_name(name: &str) -> Option<Scalar> {
+        Some(match name {
+            "char" | "int8" => Scalar::Int8,
+            "uchar" | "uint8" => Scalar::UInt8,
+            "short" | "int16" => Scalar::Int16,
+            "ushort" | "uint16" => Scalar::UInt16,
+            "int" | "int32" => Scalar::Int32,
+            "uint" | "uint32" => Scalar::UInt32,
+            "float" | "float32" => Scalar::Float32,
+            "double" | "float64" => Scalar::Float64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::Int8 | Scalar::UInt8 => 1,
+            Scalar::Int16 | Scalar::UInt16 => 2,
+            Scalar::Int32 | Scalar::UInt32 | Scalar::Float32 => 4,
+            Scalar::Float64 => 8,
+        }
+    }
+
+    /// `bytes` is exactly `size` long
+    fn decode(self, bytes: &[u8], format: Format) -> f64 {
+        macro_rules! read {
+            ($t:ty) => {{
+                let bytes = bytes.try_into().unwrap();
+                match format {
+                    Format::BinaryBigEndian => <$t>::from_be_bytes(bytes) as f64,
+                    _ => <$t>::from_le_bytes(bytes) as f64,
+                }
+            }};
+        }
+        match self {
+            Scalar::Int8 => read!(i8),
+            Scalar::UInt8 => read!(u8),
+            Scalar::Int16 => read!(i16),
+            Scalar::UInt16 => read!(u16),
+            Scalar::Int32 => read!(i32),
+            Scalar::UInt32 => read!(u32),
+            Scalar::Float32 => read!(f32),
+            Scalar::Float64 => read!(f64),
+        }
+    }
+
+    /// what a color channel of this type reads as at full strength
+    fn full_scale(self) -> f32 {
+        match self {
+            Scalar::UInt8 => u8::MAX as f32,
+            Scalar::UInt16 => u16::MAX as f32,
+            _ => 1.,
+        }
+    }
+}
+
+struct Property {
+    name: String,
+    /// the type of the length in front, for list properties
+    list: Option<Scalar>,
+    scalar: Scalar,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn property(&self, names: &[&str]) -> Option<usize> {
+        self.properties
+            .iter()
+            .position(|property| names.contains(&property.name.as_str()))
+    }
+}
+
+struct Header<'a> {
+    format: Format,
+    /// in the order they're stored
+    elements: Vec<Element>,
+    /// how many lines the header took up
+    lines: usize,
+    /// everything after `end_header`
+    body: &'a [u8],
+}
+
+/// one element's worth of values, a single one for scalar properties
+type Row = Vec<Vec<f64>>;
+
+/// reads rows one after the other from whatever follows the header
+enum Body<'a> {
+    Ascii {
+        lines: std::iter::Enumerate<std::str::Lines<'a>>,
+        /// lines taken up by the header, so errors point at the right line
+        header_lines: usize,
+    },
+    Binary {
+        bytes: &'a [u8],
+        format: Format,
+    },
+}
+
+impl Body<'_> {
+    /// a row, and for ASCII files where it was written
+    fn row(
+        &mut self,
+        element: &Element,
+        index: usize,
+        file: &str,
+    ) -> Result<(Row, Option<Location>), LoadError> {
+        match self {
+            Body::Ascii {
+                lines,
+                header_lines,
+            } => {
+                let Some((i, line)) = lines.find(|(_, line)| !line.trim().is_empty()) else {
+                    return Err(LoadError::invalid(
+                        file,
+                        format!("ends before {} {index}", element.name),
+                    ));
+                };
+                let mut statement = Statement::new(file, *header_lines + i + 1, line);
+                let location = Location {
+                    file: file.to_string(),
+                    line: statement.line_number(),
+                    column: line.len() - line.trim_start().len() + 1,
+                };
+                let mut row = Vec::with_capacity(element.properties.len());
+                for property in &element.properties {
+                    let count: usize = match property.list {
+                        Some(_) => {
+                            let word = statement.expect_word(&property.name)?;
+                            statement.parse(word, "a list length")?
+                        }
+                        None => 1,
+                    };
+                    // the length comes from the file, so it's only believed as far as there are words to back it up
+                    let mut values = Vec::new();
+                    for _ in 0..count {
+                        let word = statement.expect_word(&property.name)?;
+                        values.push(statement.parse(word, &property.name)?);
+                    }
+                    row.push(values);
+                }
+                Ok((row, Some(location)))
+            }
+            Body::Binary { bytes, format } => {
+                let format = *format;
+                let mut take = |scalar: Scalar| {
+                    let (value, rest) = bytes.split_at_checked(scalar.size())?;
+                    *bytes = rest;
+                    Some(scalar.decode(value, format))
+                };
+                let truncated = || {
+                    LoadError::invalid(
+                        file,
+                        format!("ends partway through {} {index}", element.name),
+                    )
+                };
+                let mut row = Vec::with_capacity(element.properties.len());
+                for property in &element.properties {
+                    let count = match property.list {
+                        Some(scalar) => take(scalar).ok_or_else(truncated)?,
+                        None => 1.,
+                    };
+                    if count < 0. {
+                        return Err(LoadError::invalid(
+                            file,
+                            format!(
+                                "{} {index} has a negative {} length",
+                                element.name, property.name
+                            ),
+                        ));
+                    }
+                    let values = (0..count as usize)
+                        .map(|_| take(property.scalar))
+                        .collect::<Option<Vec<f64>>>()
+                        .ok_or_else(truncated)?;
+                    row.push(values);
+                }
+                Ok((row, None))
+            }
+        }
+    }
+}
+
+pub fn load_ply(path: String) -> Result<Mesh, LoadError> {
+    let data = fs::read(&path).map_err(|error| LoadError::io(&path, error))?;
+    let mut mesh = parse_ply(&data, &path)?;
+    mesh.source = Some(MeshSource::file(&path));
+    Ok(mesh)
+}
+
+/// ASCII and both binary flavours
+/// positions, normals, texture coordinates and colors are read from `vertex`, polygons from `face`
+/// and are triangulated. Every other element is skipped
+/// `file` only names the source in errors
+pub fn parse_ply(data: &[u8], file: &str) -> Result<Mesh, LoadError> {
+    let Header {
+        format,
+        elements,
+        lines: header_lines,
+        body,
+    } = read_header(data, file)?;
+    let mut body = match format {
+        Format::Ascii => Body::Ascii {
+            lines: std::str::from_utf8(body)
+                .map_err(|_| LoadError::invalid(file, "ASCII data isn't text"))?
+                .lines()
+                .enumerate(),
+            header_lines,
+        },
+        binary => Body::Binary {
+            bytes: body,
+            format: binary,
+        },
+    };
+
+    let mut vertices = Vec::new();
+    let mut faces = Vec::new();
+    for element in &elements {
+        for index in 0..element.count {
+            let (row, location) = body.row(element, index, file)?;
+            match element.name.as_str() {
+                "vertex" => vertices.push(read_vertex(element, &row)),
+                "face" => {
+                    if let Some(corners) = element.property(&["vertex_indices", "vertex_index"]) {
+                        faces.push((row.into_iter().nth(corners).unwrap(), index, location));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut polygons = Vec::with_capacity(faces.len());
+    for (corners, index, location) in faces {
+        let corners = corners
+            .into_iter()
+            .map(|corner| {
+                // read as floats whatever the declared type, so `nan` or `2.7` would otherwise slip through as some other vertex
+                if !corner.is_finite() || corner.fract() != 0. {
+                    let reason = format!("vertex index {corner} isn't a whole number");
+                    return Err(match location.clone() {
+                        Some(location) => LoadError::Malformed { location, reason },
+                        None => LoadError::invalid(file, format!("face {index}: {reason}")),
+                    });
+                }
+                if corner < 0. || corner as usize >= vertices.len() {
+                    return Err(match location.clone() {
+                        Some(location) => LoadError::IndexOutOfRange {
+                            location,
+                            element: "vertex",
+                            index: corner as i64,
+                            count: vertices.len(),
+                        },
+                        None => LoadError::invalid(
+                            file,
+                            format!(
+                                "face {index} refers to vertex {corner}, there are {}",
+                                vertices.len()
+                            ),
+                        ),
+                    });
+                }
+                Ok(corner as usize)
+            })
+            .collect::<Result<Vec<usize>, LoadError>>()?;
+        let outline: Vec<Vector> = corners.iter().map(|&i| vertices[i].position).collect();
+        for [a, b, c] in triangulate(&outline) {
+            polygons.push(vec![corners[a], corners[b], corners[c]]);
+        }
+    }
+    Ok(Mesh::new(vertices, polygons))
+}
+
+fn read_header<'a>(data: &'a [u8], file: &str) -> Result<Header<'a>, LoadError> {
+    if !data.starts_with(b"ply") {
+        return Err(LoadError::invalid(file, "not a PLY file"));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut rest = data;
+    let mut line_number = 0;
+    loop {
+        let Some(end) = rest.iter().position(|&byte| byte == b'\n') else {
+            return Err(LoadError::invalid(file, "the header never ends"));
+        };
+        let line = std::str::from_utf8(&rest[..end])
+            .map_err(|_| LoadError::invalid(file, "the header isn't text"))?;
+        rest = &rest[end + 1..];
+        line_number += 1;
+
+        let mut statement = Statement::new(file, line_number, line);
+        match statement.next_word() {
+            Some("end_header") => break,
+            Some("format") => {
+                let word = statement.expect_word("a format")?;
+                format = Some(match word {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => {
+                        return Err(
+                            statement.malformed(Some(word), format!("`{word}` isn't a PLY format"))
+                        )
+                    }
+                });
+            }
+            Some("element") => {
+                let name = statement.expect_word("an element name")?.to_string();
+                let word = statement.expect_word("an element count")?;
+                let count = statement.parse(word, "an element count")?;
+                elements.push(Element {
+                    name,
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            Some("property") => {
+                let scalar = |statement: &mut Statement| {
+                    let word = statement.expect_word("a property type")?;
+                    Scalar::from_name(word).ok_or_else(|| {
+                        statement.malformed(Some(word), format!("`{word}` isn't a PLY type"))
+                    })
+                };
+                let word = statement.expect_word("a property type")?;
+                let list = if word == "list" {
+                    Some(scalar(&mut statement)?)
+                } else {
+                    None
+                };
+                let scalar = match list {
+                    Some(_) => scalar(&mut statement)?,
+                    None => Scalar::from_name(word).ok_or_else(|| {
+                        statement.malformed(Some(word), format!("`{word}` isn't a PLY type"))
+                    })?,
+                };
+                let name = statement.expect_word("a property name")?.to_string();
+                let Some(element) = elements.last_mut() else {
+                    return Err(statement
+                        .malformed(None, "a property needs an element to belong to".to_string()));
+                };
+                element.properties.push(Property { name, list, scalar });
+            }
+            // the magic word, comments and obj_info
+            _ => {}
+        }
+    }
+    let format = format.ok_or_else(|| LoadError::invalid(file, "the header has no format"))?;
+    Ok(Header {
+        format,
+        elements,
+        lines: line_number,
+        body: rest,
+    })
+}
+
+fn read_vertex(element: &Element, row: &Row) -> Vertex {
+    let value = |names: &[&str]| element.property(names).map(|i| row[i][0] as f32);
+    let channel = |name: &str| {
+        let i = element.property(&[name])?;
+        Some(row[i][0] as f32 / element.properties[i].scalar.full_scale())
+    };
+    let x = value(&["x"]).unwrap_or(0.);
+    let y = value(&["y"]).unwrap_or(0.);
+    let z = value(&["z"]).unwrap_or(0.);
+    let normal = match (value(&["nx"]), value(&["ny"]), value(&["nz"])) {
+        (Some(x), Some(y), Some(z)) => Some(Vector::new(x, y, z).unitized()),
+        _ => None,
+    };
+    let u = value(&["u", "s", "texture_u", "texture_s"]);
+    let v = value(&["v", "t", "texture_v", "texture_t"]);
+    let color = match (channel("red"), channel("green"), channel("blue")) {
+        (Some(r), Some(g), Some(b)) => Some((r, g, b)),
+        _ => None,
+    };
+    Vertex {
+        uv_coord: (u.unwrap_or(0.), v.unwrap_or(0.)),
+        normal,
+        color,
+        ..Vertex::new(x, y, z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::load_error::LoadError;
+
+    use super::{load_ply, parse_ply};
+
+    const SQUARE: &str = "ply\n\
+        format ascii 1.0\n\
+        comment a unit square with a painted corner\n\
+        element vertex 4\n\
+        property float x\n\
+        property float y\n\
+        property float z\n\
+        property float nx\n\
+        property float ny\n\
+        property float nz\n\
+        property uchar red\n\
+        property uchar green\n\
+        property uchar blue\n\
+        element face 1\n\
+        property list uchar int vertex_indices\n\
+        end_header\n\
+        0 0 0 0 0 2 255 0 0\n\
+        1 0 0 0 0 1 255 255 255\n\
+        1 1 0 0 0 1 255 255 255\n\
+        0 1 0 0 0 1 255 255 255\n\
+        4 0 1 2 3\n";
+
+    #[test]
+    fn reads_ascii() {
+        let mesh = parse_ply(SQUARE.as_bytes(), "square.ply").unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        // the square is split in two
+        assert_eq!(mesh.polygons.len(), 2);
+        let first = &mesh.vertices[0];
+        assert_eq!(first.color, Some((1., 0., 0.)));
+        assert_abs_diff_eq!(first.normal.unwrap().z, 1.);
+        assert_eq!(mesh.vertices[2].position.y, 1.);
+    }
+
+    #[test]
+    fn reads_binary() {
+        let mut data = b"ply\n\
+            format binary_big_endian 1.0\n\
+            element vertex 3\n\
+            property double x\n\
+            property double y\n\
+            property double z\n\
+            property float s\n\
+            property float t\n\
+            element edge 1\n\
+            property int vertex1\n\
+            property int vertex2\n\
+            element face 1\n\
+            property list uchar uint vertex_index\n\
+            end_header\n"
+            .to_vec();
+        for (x, y, s) in [(0., 0., 0f32), (2., 0., 1.), (0., 2., 0.5)] {
+            for value in [x, y, 0f64] {
+                data.extend(value.to_be_bytes());
+            }
+            data.extend(s.to_be_bytes());
+            data.extend(0f32.to_be_bytes());
+        }
+        // the edge is skipped
+        data.extend([0u8; 8]);
+        data.push(3);
+        for corner in [0u32, 1, 2] {
+            data.extend(corner.to_be_bytes());
+        }
+
+        let mesh = parse_ply(&data, "triangle.ply").unwrap();
+        assert_eq!(mesh.polygons.as_slice(), &[vec![0, 1, 2]]);
+        assert_eq!(mesh.vertices[1].position.x, 2.);
+        assert_eq!(mesh.vertices[2].uv_coord, (0.5, 0.));
+        assert_eq!(mesh.vertices[0].color, None);
+
+        let error = parse_ply(&data[..data.len() - 2], "triangle.ply").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "triangle.ply: ends partway through face 0"
+        );
+    }
+
+    #[test]
+    fn bad_files_are_located() {
+        let out_of_range = SQUARE.replace("4 0 1 2 3", "3 0 1 4");
+        let error = parse_ply(out_of_range.as_bytes(), "square.ply").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "square.ply:21:1: vertex 4 is out of range, there are 4"
+        );
+
+        let misspelled = SQUARE.replace("uchar green", "uchr green");
+        let error = parse_ply(misspelled.as_bytes(), "square.ply").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "square.ply:12:10: `uchr` isn't a PLY type"
+        );
+
+        let short = SQUARE.replace("1 1 0 0 0 1 255 255 255", "1 1 0 0 0 1 255 255");
+        let error = parse_ply(short.as_bytes(), "square.ply").unwrap_err();
+        assert_eq!(error.to_string(), "square.ply:19:20: expected blue");
+
+        let endless = SQUARE.replace("4 0 1 2 3", "99999999999 0 1 2 3");
+        let error = parse_ply(endless.as_bytes(), "square.ply").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "square.ply:21:20: expected vertex_indices"
+        );
+
+        // only whole numbers name a vertex
+        for (corners, index) in [("4 0 nan 2 3", "NaN"), ("4 0 1 2.7 3", "2.7")] {
+            let fractional = SQUARE.replace("4 0 1 2 3", corners);
+            let error = parse_ply(fractional.as_bytes(), "square.ply").unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("square.ply:21:1: vertex index {index} isn't a whole number")
+            );
+        }
+
+        assert!(matches!(
+            load_ply("models/missing.ply".to_string()),
+            Err(LoadError::Io { .. })
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::geometry::primitives::{Mesh, MeshSource, Vector, Vertex};
+use crate::load_error::{LoadError, Statement};
+use std::collections::HashMap;
+use std::fs;
+
+/// 80 bytes of header then the triangle count
+const BINARY_HEADER: usize = 84;
+/// a normal and three corners as floats, then two bytes of attributes
+const BINARY_TRIANGLE: usize = 50;
+
+pub fn load_stl(path: String) -> Result<Mesh, LoadError> {
+    let data = fs::read(&path).map_err(|error| LoadError::io(&path, error))?;
+    let mut mesh = parse_stl(&data, &path)?;
+    mesh.source = Some(MeshSource::file(&path));
+    Ok(mesh)
+}
+
+/// ASCII or binary, whichever the data turns out to be
+/// corners at the same position are welded back together, the facet normals are ignored
+/// since the winding already says which way a triangle faces
+/// `file` only names the source in errors
+pub fn parse_stl(data: &[u8], file: &str) -> Result<Mesh, LoadError> {
+    let mut mesh = WeldedMesh::default();
+    // plenty of binary files start with `solid` too, the length is the better tell
+    let binary_count = data
+        .get(80..BINARY_HEADER)
+        .map(|count| u32::from_le_bytes(count.try_into().unwrap()) as usize);
+    match binary_count {
+        Some(count) if data.len() == BINARY_HEADER + count * BINARY_TRIANGLE => {
+            for triangle in data[BINARY_HEADER..].chunks_exact(BINARY_TRIANGLE) {
+                let float = |i: usize| {
+                    let start = 12 + 4 * i;
+                    f32::from_le_bytes(triangle[start..start + 4].try_into().unwrap())
+                };
+                let corner =
+                    |i: usize| Vector::new(float(3 * i), float(3 * i + 1), float(3 * i + 2));
+                mesh.push_triangle([corner(0), corner(1), corner(2)]);
+            }
+        }
+        _ if data.starts_with(b"solid") => {
+            let text = std::str::from_utf8(data)
+                .map_err(|_| LoadError::invalid(file, "ASCII data isn't text"))?;
+            read_ascii(text, file, &mut mesh)?;
+        }
+        Some(count) => {
+            return Err(LoadError::invalid(
+                file,
+                format!(
+                    "{count} triangles should take {} bytes, there are {}",
+                    BINARY_HEADER + count * BINARY_TRIANGLE,
+                    data.len()
+                ),
+            ))
+        }
+        None => return Err(LoadError::invalid(file, "too short to be an STL file")),
+    }
+    Ok(Mesh::new(mesh.vertices, mesh.polygons))
+}
+
+/// `solid`, then `facet`s each with an `outer loop` of three `vertex`es
+fn read_ascii(text: &str, file: &str, mesh: &mut WeldedMesh) -> Result<(), LoadError> {
+    let mut corners = Vec::with_capacity(3);
+    for (i, line) in text.lines().enumerate() {
+        let mut statement = Statement::new(file, i + 1, line);
+        match statement.next_word() {
+            Some("vertex") => {
+                let x = statement.float("x")?;
+                let y = statement.float("y")?;
+                let z = statement.float("z")?;
+                corners.push(Vector::new(x, y, z));
+            }
+            Some(word @ "endfacet") => {
+                let [a, b, c] = corners[..] else {
+                    return Err(statement.malformed(
+                        Some(word),
+                        format!("a facet needs 3 vertices, this one has {}", corners.len()),
+                    ));
+                };
+                mesh.push_triangle([a, b, c]);
+                corners.clear();
+            }
+            // solid names, facet normals and loop markers carry nothing that's needed
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct WeldedMesh {
+    vertices: Vec<Vertex>,
+    polygons: Vec<Vec<usize>>,
+    /// by the bits of the position, only exact matches are welded
+    welded: HashMap<[u32; 3], usize>,
+}
+
+impl WeldedMesh {
+    fn push_triangle(&mut self, corners: [Vector; 3]) {
+        let polygon = corners
+            .iter()
+            .map(|corner| {
+                let key = corner.as_array().map(f32::to_bits);
+                *self.welded.entry(key).or_insert_with(|| {
+                    self.vertices
+                        .push(Vertex::new(corner.x, corner.y, corner.z));
+                    self.vertices.len() - 1
+                })
+            })
+            .collect();
+        self.polygons.push(polygon);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::load_error::LoadError;
+
+    use super::{load_stl, parse_stl};
+
+    #[test]
+    fn reads_ascii() {
+        let data = "solid wedge\n\
+            facet normal 0 0 1\n\
+              outer loop\n\
+                vertex 0 0 0\n\
+                vertex 1 0 0\n\
+                vertex 0 1 0\n\
+              endloop\n\
+            endfacet\n\
+            facet normal 0 0 0\n\
+              outer loop\n\
+                vertex 1 0 0\n\
+                vertex 1 1 0\n\
+                vertex 0 1 0\n\
+              endloop\n\
+            endfacet\n\
+            endsolid wedge\n";
+        let mesh = parse_stl(data.as_bytes(), "wedge.stl").unwrap();
+        // the shared edge is welded
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.polygons.as_slice(), &[vec![0, 1, 2], vec![1, 3, 2]]);
+
+        let short = data.replacen("vertex 0 1 0\n", "", 1);
+        let error = parse_stl(short.as_bytes(), "wedge.stl").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "wedge.stl:7:1: a facet needs 3 vertices, this one has 2"
+        );
+    }
+
+    #[test]
+    fn reads_binary() {
+        // headers that start like an ASCII file are common
+        let mut data = b"solid but really binary".to_vec();
+        data.resize(80, 0);
+        data.extend(1u32.to_le_bytes());
+        for value in [0., 0., 1., 0., 0., 0., 2., 0., 0., 0., 2., 0.] {
+            data.extend(f32::to_le_bytes(value));
+        }
+        data.extend([0, 0]);
+        let mesh = parse_stl(&data, "triangle.stl").unwrap();
+        assert_eq!(mesh.polygons.as_slice(), &[vec![0, 1, 2]]);
+        assert_eq!(mesh.vertices[1].position.x, 2.);
+        assert_eq!(mesh.vertices[2].position.y, 2.);
+
+        data[80] = 2;
+        assert!(parse_stl(&data, "triangle.stl").is_err());
+        assert!(matches!(
+            load_stl("models/missing.stl".to_string()),
+            Err(LoadError::Io { .. })
+        ));
+    }
+}
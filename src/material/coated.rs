@@ -0,0 +1,259 @@
+//! a clear coat over another material, like varnished wood, lacquered metal or car paint
+//! light is reflected off the coat, or passes through it to the base and back out, losing some of itself to the coat both ways
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::{
+    geometry::primitives::Vector,
+    lighting::{black_spectra, const_spectra, white_spectra, Spectra},
+    material::{
+        cosθ,
+        dielectric::{fresnel_dielectric, refract},
+        microfacet::{
+            ggx_distribution, ggx_reflection_pdf, roughness_to_alpha, sample_ggx_reflection,
+            smith_g,
+        },
+        Diffuse, Sidedness, BRDF,
+    },
+    random::Pcg32,
+    scene::scene_file::{MaterialDescription, SpectrumDescription},
+};
+
+/// after Weidlich and Wilkie, "Arbitrarily Layered Micro-Facet Surfaces" (2007)
+/// light inside the coat that meets its surface past the critical angle is taken as lost, so this errs dark
+#[derive(Clone, Debug)]
+pub struct Coated {
+    /// what's under the coat, seen at the angles light takes inside it
+    pub base: Arc<dyn BRDF>,
+    /// the coat's refractive index, at least 1
+    pub ior: f32,
+    /// of the coat's surface, 0 is polished
+    pub roughness: f32,
+    /// the coat's optical depth straight through, in each wavelength, 0 for a perfectly clear coat
+    /// light going through at an angle has further to go
+    pub absorption: Spectra,
+    pub sidedness: Sidedness,
+}
+
+impl Default for Coated {
+    fn default() -> Self {
+        Coated {
+            base: Arc::new(Diffuse::default()),
+            ior: 1.5,
+            roughness: 0.1,
+            absorption: const_spectra(0.),
+            sidedness: Sidedness::default(),
+        }
+    }
+}
+
+impl Coated {
+    /// the direction inside the coat, pointing up toward its surface, that light along ω is bent into
+    fn entering_coat(&self, ω: &Vector, normal: &Vector) -> Option<Vector> {
+        refract(ω, normal, self.ior).map(|inside| -inside)
+    }
+
+    /// where light rising through the coat along `inside` leaves it, None if it's reflected back in
+    fn leaving_coat(&self, inside: &Vector, normal: &Vector) -> Option<Vector> {
+        refract(&-*inside, &-*normal, 1. / self.ior)
+    }
+
+    /// how often `sample` draws from the coat's reflection rather than the base
+    fn coat_probability(&self, ω_o: &Vector, normal: &Vector) -> f32 {
+        fresnel_dielectric(cosθ(ω_o, normal), self.ior).clamp(0.2, 0.8)
+    }
+}
+
+impl BRDF for Coated {
+    fn evaluate(&self, ω_i: &Vector, ω_o: &Vector, normal: &Vector) -> Spectra {
+        let cosθ_i = cosθ(ω_i, normal);
+        let cosθ_o = cosθ(ω_o, normal);
+        if cosθ_i <= 0. || cosθ_o <= 0. {
+            return black_spectra();
+        }
+        let (Some(inside_i), Some(inside_o)) = (
+            self.entering_coat(ω_i, normal),
+            self.entering_coat(ω_o, normal),
+        ) else {
+            return black_spectra();
+        };
+
+        let half = (ω_i.unitized() + ω_o.unitized()).unitized();
+        let alpha = roughness_to_alpha(self.roughness);
+        let d = ggx_distribution(cosθ(&half, normal), alpha);
+        let g = smith_g(cosθ_i, cosθ_o, alpha);
+        let fresnel = fresnel_dielectric(half.dot(&ω_i.unitized()), self.ior);
+        let coat = d * g * fresnel / (4. * cosθ_i * cosθ_o);
+
+        // in through the coat and back out, where radiance spreads back out over the wider angles
+        let transmitted = (1. - fresnel_dielectric(cosθ_i, self.ior))
+            * (1. - fresnel_dielectric(cosθ_o, self.ior))
+            / (self.ior * self.ior);
+        let path = 1. / cosθ(&inside_i, normal) + 1. / cosθ(&inside_o, normal);
+        let absorbed = Spectra {
+            spectra: self.absorption.spectra.mapv(|depth| (-depth * path).exp()),
+        };
+        let base = self.base.evaluate(&inside_i, &inside_o, normal);
+        coat * white_spectra() + transmitted * absorbed * base
+    }
+
+    /// off the coat, or from the base by its own sampling and bent on the way out
+    fn sample(&self, ω_o: &Vector, normal: &Vector, rng: &mut Pcg32) -> (Vector, f32, Spectra) {
+        let ω_i = if rng.gen::<f32>() < self.coat_probability(ω_o, normal) {
+            sample_ggx_reflection(ω_o, normal, roughness_to_alpha(self.roughness), rng)
+        } else {
+            let Some(inside_o) = self.entering_coat(ω_o, normal) else {
+                return (*normal, 0., black_spectra());
+            };
+            let (inside_i, _, _) = self.base.sample(&inside_o, normal, rng);
+            if cosθ(&inside_i, normal) <= 0. {
+                return (inside_i, 0., black_spectra());
+            }
+            match self.leaving_coat(&inside_i, normal) {
+                Some(ω_i) => ω_i,
+                None => return (inside_i, 0., black_spectra()),
+            }
+        };
+        (
+            ω_i,
+            self.pdf(&ω_i, ω_o, normal),
+            self.evaluate(&ω_i, ω_o, normal),
+        )
+    }
+
+    fn pdf(&self, ω_i: &Vector, ω_o: &Vector, normal: &Vector) -> f32 {
+        let cosθ_i = cosθ(ω_i, normal);
+        if cosθ_i <= 0. {
+            return 0.;
+        }
+        let coat = self.coat_probability(ω_o, normal);
+        let alpha = roughness_to_alpha(self.roughness);
+        let reflected = ggx_reflection_pdf(ω_i, ω_o, normal, alpha);
+        let base = match (
+            self.entering_coat(ω_i, normal),
+            self.entering_coat(ω_o, normal),
+        ) {
+            // refraction squeezes solid angle, by cosθ / (η² cosθ') from inside to out
+            (Some(inside_i), Some(inside_o)) => {
+                self.base.pdf(&inside_i, &inside_o, normal) * cosθ_i
+                    / (self.ior * self.ior * cosθ(&inside_i, normal))
+            }
+            _ => 0.,
+        };
+        coat * reflected + (1. - coat) * base
+    }
+
+    fn sidedness(&self) -> Sidedness {
+        self.sidedness
+    }
+
+    /// only when the base can be written down too
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Coated {
+            base: Box::new(self.base.describe()?),
+            ior: self.ior,
+            roughness: self.roughness,
+            absorption: SpectrumDescription::from(&self.absorption),
+            sidedness: self.sidedness,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::{
+        geometry::primitives::Vector,
+        lighting::{const_spectra, tabulated_spectra},
+        material::{
+            conductor::{Metal, RoughConductor},
+            furnace::{reflectance, sampled_reflectance},
+            glossy::Glossy,
+            Diffuse, BRDF,
+        },
+        random::Pcg32,
+    };
+
+    use super::Coated;
+
+    fn white_diffuse() -> Arc<dyn BRDF> {
+        Arc::new(Diffuse {
+            albedo: const_spectra(1.),
+            ..Diffuse::default()
+        })
+    }
+
+    #[test]
+    fn coats_never_give_back_more_than_they_get() {
+        let normal = Vector::new(0., 0., 1.);
+        let white_metal: Arc<dyn BRDF> = Arc::new(Glossy {
+            albedo: const_spectra(1.),
+            metallic: 1.,
+            roughness: 0.3,
+            ..Glossy::default()
+        });
+        for base in [white_diffuse(), white_metal] {
+            for (ior, roughness) in [(1.5, 0.3), (1.5, 0.8), (2.4, 0.5)] {
+                let coated = Coated {
+                    base: base.clone(),
+                    ior,
+                    roughness,
+                    ..Coated::default()
+                };
+                for ω_o in [Vector::new(0., 0., 1.), Vector::new(0.9, 0., 0.3)] {
+                    let total = reflectance(&coated, &ω_o, &normal, 550.);
+                    assert!(total <= 1., "{ior} {roughness} reflects {total}");
+                    assert!(total > 0.2, "{ior} {roughness} reflects {total}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tinted_coats_absorb_their_colors() {
+        let normal = Vector::new(0., 0., 1.);
+        let ω_o = Vector::new(0.3, 0., 1.);
+        let clear = Coated {
+            base: white_diffuse(),
+            ..Coated::default()
+        };
+        // a coat that soaks up blue, like amber varnish
+        let amber = Coated {
+            absorption: tabulated_spectra(&[[450., 1.5], [600., 0.]]),
+            ..clear.clone()
+        };
+        let clear_blue = reflectance(&clear, &ω_o, &normal, 420.);
+        let clear_red = reflectance(&clear, &ω_o, &normal, 650.);
+        assert_abs_diff_eq!(clear_blue, clear_red, epsilon = 1e-4);
+        assert!(reflectance(&amber, &ω_o, &normal, 420.) < 0.3 * clear_blue);
+        assert_abs_diff_eq!(
+            reflectance(&amber, &ω_o, &normal, 650.),
+            clear_red,
+            epsilon = 1e-4
+        );
+    }
+
+    #[test]
+    fn sampling_agrees_with_evaluating() {
+        let normal = Vector::new(0., 0., 1.);
+        let ω_o = Vector::new(0.5, -0.2, 0.8);
+        let mut rng = Pcg32::new(8, 1);
+        for base in [
+            white_diffuse(),
+            Arc::new(RoughConductor::new(Metal::Gold, 0.4)) as Arc<dyn BRDF>,
+        ] {
+            let coated = Coated {
+                base,
+                roughness: 0.3,
+                ..Coated::default()
+            };
+            let expected = reflectance(&coated, &ω_o, &normal, 550.);
+            let estimate = sampled_reflectance(&coated, &ω_o, &normal, 550., &mut rng);
+            assert_abs_diff_eq!(estimate, expected, epsilon = 0.03 * expected);
+        }
+    }
+}
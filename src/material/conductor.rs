@@ -0,0 +1,373 @@
+//! metals, whose color comes from how their complex refractive index changes across the spectrum
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    geometry::primitives::Vector,
+    lighting::{black_spectra, tabulated_spectra, Spectra},
+    material::{
+        cosθ,
+        microfacet::{
+            ggx_distribution, ggx_reflection_pdf, roughness_to_alpha, sample_ggx_reflection,
+            smith_g,
+        },
+        reflect, Sidedness, BRDF,
+    },
+    random::Pcg32,
+    scene::scene_file::{IorDescription, MaterialDescription},
+};
+
+/// [nanometers, η, k], from Johnson and Christy, "Optical Constants of the Noble Metals" (1972)
+const GOLD: [[f32; 3]; 15] = [
+    [381., 1.46, 1.933],
+    [397., 1.47, 1.952],
+    [413., 1.46, 1.958],
+    [430., 1.45, 1.948],
+    [451., 1.38, 1.914],
+    [471., 1.31, 1.849],
+    [496., 1.04, 1.833],
+    [521., 0.62, 2.081],
+    [549., 0.43, 2.455],
+    [582., 0.29, 2.863],
+    [617., 0.21, 3.272],
+    [659., 0.14, 3.697],
+    [704., 0.13, 4.103],
+    [756., 0.14, 4.542],
+    [821., 0.16, 5.083],
+];
+
+const SILVER: [[f32; 3]; 15] = [
+    [381., 0.05, 1.864],
+    [397., 0.05, 2.070],
+    [413., 0.05, 2.275],
+    [430., 0.04, 2.462],
+    [451., 0.04, 2.657],
+    [471., 0.05, 2.869],
+    [496., 0.05, 3.093],
+    [521., 0.05, 3.324],
+    [549., 0.06, 3.586],
+    [582., 0.05, 3.858],
+    [617., 0.06, 4.152],
+    [659., 0.05, 4.483],
+    [704., 0.04, 4.838],
+    [756., 0.03, 5.242],
+    [821., 0.04, 5.727],
+];
+
+const COPPER: [[f32; 3]; 15] = [
+    [381., 1.33, 2.07],
+    [397., 1.32, 2.13],
+    [413., 1.28, 2.21],
+    [430., 1.25, 2.25],
+    [451., 1.24, 2.30],
+    [471., 1.25, 2.37],
+    [496., 1.22, 2.45],
+    [521., 1.18, 2.53],
+    [549., 1.02, 2.58],
+    [582., 0.47, 2.81],
+    [617., 0.27, 3.11],
+    [659., 0.21, 3.42],
+    [704., 0.21, 3.75],
+    [756., 0.24, 4.21],
+    [821., 0.26, 4.67],
+];
+
+/// from Rakić, "Algorithm for the determination of intrinsic optical constants of metal films" (1995)
+const ALUMINIUM: [[f32; 3]; 10] = [
+    [380., 0.45, 4.62],
+    [400., 0.49, 4.86],
+    [450., 0.62, 5.47],
+    [500., 0.77, 6.08],
+    [550., 0.96, 6.69],
+    [600., 1.20, 7.26],
+    [650., 1.47, 7.79],
+    [700., 1.83, 8.31],
+    [750., 2.40, 8.62],
+    [800., 2.80, 8.45],
+];
+
+/// metals with measured optical constants
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metal {
+    Gold,
+    Silver,
+    Copper,
+    Aluminium,
+}
+
+impl Metal {
+    pub const ALL: [Metal; 4] = [Metal::Gold, Metal::Silver, Metal::Copper, Metal::Aluminium];
+
+    pub fn ior(self) -> ComplexIor {
+        match self {
+            Metal::Gold => ComplexIor::tabulated(&GOLD),
+            Metal::Silver => ComplexIor::tabulated(&SILVER),
+            Metal::Copper => ComplexIor::tabulated(&COPPER),
+            Metal::Aluminium => ComplexIor::tabulated(&ALUMINIUM),
+        }
+    }
+}
+
+/// refractive index η and extinction coefficient k in every bin, relative to the air outside
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComplexIor {
+    pub η: Spectra,
+    pub k: Spectra,
+}
+
+impl ComplexIor {
+    /// [nanometers, η, k] in increasing order, interpolated between
+    pub fn tabulated(table: &[[f32; 3]]) -> ComplexIor {
+        let η: Vec<[f32; 2]> = table.iter().map(|&[λ, η, _]| [λ, η]).collect();
+        let k: Vec<[f32; 2]> = table.iter().map(|&[λ, _, k]| [λ, k]).collect();
+        ComplexIor {
+            η: tabulated_spectra(&η),
+            k: tabulated_spectra(&k),
+        }
+    }
+
+    /// the fraction of unpolarized light reflected in each bin, `cosθ_i` off the normal
+    pub fn fresnel(&self, cosθ_i: f32) -> Spectra {
+        let mut reflectance = black_spectra();
+        for ((r, &η), &k) in reflectance
+            .spectra
+            .iter_mut()
+            .zip(self.η.spectra.iter())
+            .zip(self.k.spectra.iter())
+        {
+            *r = fresnel_conductor(cosθ_i, η, k);
+        }
+        reflectance
+    }
+
+    /// the preset this came from, if it did
+    pub fn metal(&self) -> Option<Metal> {
+        Metal::ALL.into_iter().find(|metal| metal.ior() == *self)
+    }
+}
+
+/// the exact Fresnel equations for an absorbing medium, averaged over both polarizations
+pub fn fresnel_conductor(cosθ_i: f32, η: f32, k: f32) -> f32 {
+    let cos2 = cosθ_i.clamp(0., 1.).powi(2);
+    let sin2 = 1. - cos2;
+    let t0 = η * η - k * k - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4. * η * η * k * k).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let real = (0.5 * (a2_plus_b2 + t0)).max(0.).sqrt();
+    let t2 = 2. * cosθ_i.clamp(0., 1.) * real;
+    let r_s = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let r_p = r_s * (t3 - t4) / (t3 + t4);
+    0.5 * (r_p + r_s)
+}
+
+/// a perfectly smooth metal, reflecting each direction into exactly one other
+/// point lights are never in exactly that direction, so it only shows what reflected rays find
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mirror {
+    pub ior: ComplexIor,
+    pub sidedness: Sidedness,
+}
+
+impl Mirror {
+    pub fn new(metal: Metal) -> Mirror {
+        Mirror {
+            ior: metal.ior(),
+            sidedness: Sidedness::default(),
+        }
+    }
+}
+
+impl Default for Mirror {
+    fn default() -> Self {
+        Mirror::new(Metal::Silver)
+    }
+}
+
+impl BRDF for Mirror {
+    /// nothing outside the one reflected direction
+    fn evaluate(&self, _ω_i: &Vector, _ω_o: &Vector, _normal: &Vector) -> Spectra {
+        black_spectra()
+    }
+
+    fn specular_reflection(&self, ω_o: &Vector, normal: &Vector) -> Option<(Vector, Spectra)> {
+        let cosθ_o = cosθ(ω_o, normal);
+        if cosθ_o <= 0. {
+            return None;
+        }
+        Some((reflect(ω_o, normal), self.ior.fresnel(cosθ_o)))
+    }
+
+    fn sidedness(&self) -> Sidedness {
+        self.sidedness
+    }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Mirror {
+            ior: IorDescription::from(&self.ior),
+            sidedness: self.sidedness,
+        })
+    }
+}
+
+/// brushed, sandblasted or just weathered metal, a microfacet surface where each facet is a mirror
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoughConductor {
+    pub ior: ComplexIor,
+    /// 0 is polished, 1 is as rough as it gets
+    pub roughness: f32,
+    pub sidedness: Sidedness,
+}
+
+impl RoughConductor {
+    pub fn new(metal: Metal, roughness: f32) -> RoughConductor {
+        RoughConductor {
+            ior: metal.ior(),
+            roughness,
+            sidedness: Sidedness::default(),
+        }
+    }
+}
+
+impl Default for RoughConductor {
+    fn default() -> Self {
+        RoughConductor::new(Metal::Aluminium, 0.3)
+    }
+}
+
+impl BRDF for RoughConductor {
+    fn evaluate(&self, ω_i: &Vector, ω_o: &Vector, normal: &Vector) -> Spectra {
+        let cosθ_i = cosθ(ω_i, normal);
+        let cosθ_o = cosθ(ω_o, normal);
+        if cosθ_i <= 0. || cosθ_o <= 0. {
+            return black_spectra();
+        }
+        let half = (ω_i.unitized() + ω_o.unitized()).unitized();
+        let alpha = roughness_to_alpha(self.roughness);
+        let d = ggx_distribution(cosθ(&half, normal), alpha);
+        let g = smith_g(cosθ_i, cosθ_o, alpha);
+        let fresnel = self.ior.fresnel(half.dot(&ω_i.unitized()));
+        (d * g / (4. * cosθ_i * cosθ_o)) * fresnel
+    }
+
+    /// by the facets' distribution, so polished metal doesn't wait on a sample to land in its narrow highlight
+    fn sample(&self, ω_o: &Vector, normal: &Vector, rng: &mut Pcg32) -> (Vector, f32, Spectra) {
+        let alpha = roughness_to_alpha(self.roughness);
+        let ω_i = sample_ggx_reflection(ω_o, normal, alpha, rng);
+        (
+            ω_i,
+            ggx_reflection_pdf(&ω_i, ω_o, normal, alpha),
+            self.evaluate(&ω_i, ω_o, normal),
+        )
+    }
+
+    fn pdf(&self, ω_i: &Vector, ω_o: &Vector, normal: &Vector) -> f32 {
+        ggx_reflection_pdf(ω_i, ω_o, normal, roughness_to_alpha(self.roughness))
+    }
+
+    fn sidedness(&self) -> Sidedness {
+        self.sidedness
+    }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Conductor {
+            ior: IorDescription::from(&self.ior),
+            roughness: self.roughness,
+            sidedness: self.sidedness,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::{
+        geometry::primitives::Vector,
+        material::{furnace::reflectance, BRDF},
+        scene::scene_file::MaterialDescription,
+    };
+
+    use super::{fresnel_conductor, Metal, Mirror, RoughConductor};
+
+    #[test]
+    fn fresnel_follows_the_closed_forms() {
+        let (η, k) = (0.2, 3.5);
+        let straight_on = ((η - 1.) * (η - 1.) + k * k) / ((η + 1.) * (η + 1.) + k * k);
+        assert_abs_diff_eq!(fresnel_conductor(1., η, k), straight_on, epsilon = 1e-5);
+        assert_abs_diff_eq!(fresnel_conductor(0., η, k), 1., epsilon = 1e-5);
+        // a dielectric is a conductor that doesn't absorb
+        let glass = ((1.5f32 - 1.) / (1.5 + 1.)).powi(2);
+        assert_abs_diff_eq!(fresnel_conductor(1., 1.5, 0.), glass, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn metals_have_their_colors() {
+        let straight_on = |metal: Metal| metal.ior().fresnel(1.);
+        let gold = straight_on(Metal::Gold);
+        let copper = straight_on(Metal::Copper);
+        let silver = straight_on(Metal::Silver);
+        // gold and copper give back much more red than blue
+        assert!(gold.from_λ(650.) > 0.9 && gold.from_λ(450.) < 0.45);
+        assert!(copper.from_λ(650.) > 0.9 && copper.from_λ(450.) < 0.6);
+        // silver and aluminium are bright all the way across
+        assert!(silver.spectra.iter().all(|&r| r > 0.85));
+        assert!(straight_on(Metal::Aluminium)
+            .spectra
+            .iter()
+            .all(|&r| r > 0.85));
+        // and at grazing angles every metal turns white
+        assert!(Metal::Gold
+            .ior()
+            .fresnel(0.01)
+            .spectra
+            .iter()
+            .all(|&r| r > 0.9));
+        for metal in Metal::ALL {
+            assert_eq!(metal.ior().metal(), Some(metal));
+        }
+    }
+
+    #[test]
+    fn mirrors_reflect() {
+        let normal = Vector::new(0., 1., 0.);
+        let mirror = Mirror::new(Metal::Gold);
+        let (ω_i, reflectance) = mirror
+            .specular_reflection(&Vector::new(1., 1., 0.), &normal)
+            .unwrap();
+        assert_abs_diff_eq!(ω_i.x, -(0.5f32).sqrt(), epsilon = 1e-6);
+        assert_abs_diff_eq!(ω_i.y, (0.5f32).sqrt(), epsilon = 1e-6);
+        assert_eq!(reflectance, Metal::Gold.ior().fresnel((0.5f32).sqrt()));
+        assert!(mirror
+            .specular_reflection(&Vector::new(1., -1., 0.), &normal)
+            .is_none());
+        assert!(matches!(
+            mirror.describe(),
+            Some(MaterialDescription::Mirror { .. })
+        ));
+    }
+
+    #[test]
+    fn rough_metal_is_reciprocal_and_loses_energy() {
+        let normal = Vector::new(0., 0., 1.);
+        let metal = RoughConductor::new(Metal::Silver, 0.5);
+        let a = Vector::new(0.3, -0.2, 0.8);
+        let b = Vector::new(-0.5, 0.4, 0.6);
+        let ab = metal.evaluate(&a, &b, &normal);
+        let ba = metal.evaluate(&b, &a, &normal);
+        for (x, y) in ab.spectra.iter().zip(ba.spectra.iter()) {
+            assert_abs_diff_eq!(x, y, epsilon = 1e-5);
+        }
+
+        // the light reflected toward any one direction, integrated over every incoming direction
+        for roughness in [0.3, 0.6, 1.] {
+            let metal = RoughConductor::new(Metal::Silver, roughness);
+            for ω_o in [Vector::new(0., 0., 1.), Vector::new(0.8, 0., 0.2)] {
+                let reflected = reflectance(&metal, &ω_o, &normal, 550.);
+                // some is always lost to facets shadowing each other, more the rougher they are
+                assert!(reflected < 1. && reflected > 0.25);
+            }
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! glass, water and gems, clear materials that bend light by an amount that depends on its wavelength
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    geometry::primitives::Vector,
+    lighting::{black_spectra, monochroma_spectra, white_spectra, Spectra},
+    material::{cosθ, reflect, BRDF},
+    random::Pcg32,
+    scene::scene_file::{DispersionDescription, MaterialDescription},
+};
+
+/// how the refractive index changes with wavelength
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Dispersion {
+    /// n² = 1 + Σ b λ² / (λ² - c), λ in micrometers and c in square micrometers
+    Sellmeier { b: [f32; 3], c: [f32; 3] },
+    /// n = a + b / λ², λ in micrometers, good enough across the visible range
+    Cauchy { a: f32, b: f32 },
+}
+
+impl Dispersion {
+    /// the same index at every wavelength
+    pub fn constant(ior: f32) -> Dispersion {
+        Dispersion::Cauchy { a: ior, b: 0. }
+    }
+
+    /// the refractive index at λ nanometers
+    pub fn ior(&self, λ: f32) -> f32 {
+        let μm = λ / 1000.;
+        let μm2 = μm * μm;
+        match self {
+            Dispersion::Sellmeier { b, c } => {
+                let n2: f32 = 1.
+                    + b.iter()
+                        .zip(c)
+                        .map(|(b, c)| b * μm2 / (μm2 - c))
+                        .sum::<f32>();
+                n2.sqrt()
+            }
+            Dispersion::Cauchy { a, b } => a + b / μm2,
+        }
+    }
+
+    /// whether different wavelengths take different paths through it
+    pub fn is_dispersive(&self) -> bool {
+        self.ior(380.) != self.ior(780.)
+    }
+}
+
+/// clear materials with measured dispersion
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Glass {
+    /// the common optical crown glass
+    Bk7,
+    FusedSilica,
+    Diamond,
+    Water,
+}
+
+impl Glass {
+    pub const ALL: [Glass; 4] = [Glass::Bk7, Glass::FusedSilica, Glass::Diamond, Glass::Water];
+
+    pub fn dispersion(self) -> Dispersion {
+        match self {
+            // Schott's datasheet
+            Glass::Bk7 => Dispersion::Sellmeier {
+                b: [1.039_612, 0.231_792_34, 1.010_469_4],
+                c: [0.006_000_699, 0.020_017_914, 103.560_65],
+            },
+            // Malitson (1965)
+            Glass::FusedSilica => Dispersion::Sellmeier {
+                b: [0.696_166_3, 0.407_942_6, 0.897_479_4],
+                c: [0.004_679_148, 0.013_512_063, 97.934_0],
+            },
+            // Peter (1923)
+            Glass::Diamond => Dispersion::Sellmeier {
+                b: [0.3306, 4.3356, 0.],
+                c: [0.030_625, 0.011_236, 0.],
+            },
+            // fitted to Hale and Querry (1973) at room temperature
+            Glass::Water => Dispersion::Cauchy {
+                a: 1.324,
+                b: 0.003_06,
+            },
+        }
+    }
+}
+
+/// the fraction of unpolarized light reflected at a boundary into a medium `η` times as dense
+/// `cosθ_i` is on the side the light arrives from, total internal reflection gives 1
+pub fn fresnel_dielectric(cosθ_i: f32, η: f32) -> f32 {
+    let cosθ_i = cosθ_i.clamp(0., 1.);
+    let sin2θ_t = (1. - cosθ_i * cosθ_i) / (η * η);
+    if sin2θ_t >= 1. {
+        return 1.;
+    }
+    let cosθ_t = (1. - sin2θ_t).sqrt();
+    let r_s = (cosθ_i - η * cosθ_t) / (cosθ_i + η * cosθ_t);
+    let r_p = (η * cosθ_i - cosθ_t) / (η * cosθ_i + cosθ_t);
+    0.5 * (r_s * r_s + r_p * r_p)
+}
+
+/// where light going toward the surface along -ω continues after crossing into a medium `η` times as dense
+/// ω and the normal are on the same side, None for total internal reflection
+pub fn refract(ω: &Vector, normal: &Vector, η: f32) -> Option<Vector> {
+    let ω = ω.unitized();
+    let normal = normal.unitized();
+    let cosθ_i = ω.dot(&normal);
+    let sin2θ_t = (1. - cosθ_i * cosθ_i) / (η * η);
+    if sin2θ_t >= 1. {
+        return None;
+    }
+    let cosθ_t = (1. - sin2θ_t).sqrt();
+    Some((-ω) / η + normal.times(cosθ_i / η - cosθ_t))
+}
+
+/// a smooth clear material, light is either reflected or refracted at its surface
+/// it has to be closed, the back faces are where light leaves again
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dielectric {
+    pub ior: Dispersion,
+}
+
+impl Dielectric {
+    pub fn new(glass: Glass) -> Dielectric {
+        Dielectric {
+            ior: glass.dispersion(),
+        }
+    }
+}
+
+impl Default for Dielectric {
+    fn default() -> Self {
+        Dielectric::new(Glass::Bk7)
+    }
+}
+
+impl BRDF for Dielectric {
+    /// all the light it passes on is along the one reflected or refracted direction
+    fn evaluate(&self, _ω_i: &Vector, _ω_o: &Vector, _normal: &Vector) -> Spectra {
+        black_spectra()
+    }
+
+    fn specular_scatter(
+        &self,
+        ω_o: &Vector,
+        normal: &Vector,
+        front_face: bool,
+        wavelength: &mut Option<f32>,
+        rng: &mut Pcg32,
+    ) -> Option<(Vector, Spectra)> {
+        // a dispersive interface splits white light, so from here the path follows a single wavelength
+        // picked evenly from the bins, which makes up for the others by carrying all of their share
+        let mut weight = white_spectra();
+        let λ = match *wavelength {
+            Some(λ) => λ,
+            None if self.ior.is_dispersive() => {
+                let bins = weight.spectra.len();
+                let λ = 380. + 10. * rng.gen_range(0..bins) as f32;
+                weight = monochroma_spectra(λ, bins as f32);
+                *wavelength = Some(λ);
+                λ
+            }
+            None => 550.,
+        };
+        let n = self.ior.ior(λ);
+        // going out of the material the ratio is the other way up
+        let η = if front_face { n } else { 1. / n };
+
+        let cosθ_o = cosθ(ω_o, normal);
+        let reflectance = fresnel_dielectric(cosθ_o, η);
+        let direction = if rng.gen::<f32>() < reflectance {
+            reflect(ω_o, normal)
+        } else {
+            refract(ω_o, normal, η)?
+        };
+        Some((direction, weight))
+    }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Dielectric {
+            ior: DispersionDescription::from(&self.ior),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::{
+        geometry::primitives::Vector, lighting::monochroma_spectra, material::BRDF, random::Pcg32,
+    };
+
+    use super::{fresnel_dielectric, refract, Dielectric, Dispersion, Glass};
+
+    #[test]
+    fn indices_match_their_datasheets() {
+        // at the sodium D line
+        let d = 589.3;
+        assert_abs_diff_eq!(Glass::Bk7.dispersion().ior(d), 1.5168, epsilon = 1e-3);
+        assert_abs_diff_eq!(
+            Glass::FusedSilica.dispersion().ior(d),
+            1.4585,
+            epsilon = 1e-3
+        );
+        assert_abs_diff_eq!(Glass::Diamond.dispersion().ior(d), 2.417, epsilon = 2e-3);
+        assert_abs_diff_eq!(Glass::Water.dispersion().ior(d), 1.333, epsilon = 1e-3);
+        // blue is always bent more than red
+        for glass in Glass::ALL {
+            let dispersion = glass.dispersion();
+            assert!(dispersion.ior(400.) > dispersion.ior(700.));
+        }
+        assert!(!Dispersion::constant(1.5).is_dispersive());
+    }
+
+    #[test]
+    fn snell_and_fresnel_agree() {
+        let normal = Vector::new(0., 1., 0.);
+        let ω = Vector::new(1., 1., 0.);
+        let t = refract(&ω, &normal, 1.5).unwrap();
+        assert_abs_diff_eq!(t.magnitude(), 1., epsilon = 1e-6);
+        // sinθ_i = η sinθ_t, and the ray carries on through the surface
+        assert_abs_diff_eq!((0.5f32).sqrt(), 1.5 * -t.x, epsilon = 1e-6);
+        assert!(t.y < 0.);
+        // straight on, and at the critical angle on the way out
+        let glass = ((1.5f32 - 1.) / (1.5 + 1.)).powi(2);
+        assert_abs_diff_eq!(fresnel_dielectric(1., 1.5), glass, epsilon = 1e-6);
+        assert_abs_diff_eq!(fresnel_dielectric(1., 1. / 1.5), glass, epsilon = 1e-6);
+        assert_eq!(fresnel_dielectric((0.5f32).sqrt(), 1. / 1.5), 1.);
+        assert!(refract(&ω, &normal, 1. / 1.5).is_none());
+    }
+
+    #[test]
+    fn dispersion_splits_white_light() {
+        let normal = Vector::new(0., 1., 0.);
+        let ω_o = Vector::new(1., 0.5, 0.);
+        let prism = Dielectric::new(Glass::Diamond);
+        let mut rng = Pcg32::new(3, 0);
+        // the first dispersive surface picks a wavelength for the path and weights it to stand for all of them
+        let mut refracted = Vec::new();
+        while refracted.len() < 2 {
+            let mut wavelength = None;
+            let (direction, weight) = prism
+                .specular_scatter(&ω_o, &normal, true, &mut wavelength, &mut rng)
+                .unwrap();
+            let λ = wavelength.unwrap();
+            assert_eq!(weight, monochroma_spectra(λ, 40.));
+            if direction.y < 0. && !refracted.iter().any(|&(seen, _)| seen == λ) {
+                refracted.push((λ, direction));
+            }
+        }
+        // different wavelengths go different ways
+        let [(_, a), (_, b)] = [refracted[0], refracted[1]];
+        assert!(a.dot(&b) < 1. - 1e-7);
+        // and later surfaces keep to the one already picked
+        let mut wavelength = Some(450.);
+        let (_, weight) = prism
+            .specular_scatter(&ω_o, &normal, false, &mut wavelength, &mut rng)
+            .unwrap();
+        assert_eq!(wavelength, Some(450.));
+        assert!(weight.spectra.iter().all(|&w| w == 1.));
+    }
+}
@@ -0,0 +1,47 @@
+//! surfaces that give off light of their own, like lamp panels, screens and anything glowing hot
+//! any mesh wearing one is a light, sampled for direct lighting just like the point lights are
+use crate::{
+    geometry::primitives::Vector,
+    lighting::{black_spectra, white_spectra, Spectra},
+    material::{Sidedness, BRDF},
+    scene::scene_file::{MaterialDescription, SpectrumDescription},
+};
+
+/// gives off the same radiance in every direction and reflects nothing
+/// one sided emitters only shine from the front, the side their polygons wind counterclockwise toward
+#[derive(Clone, Debug, PartialEq)]
+pub struct Emissive {
+    /// W/sr/m² in each wavelength
+    pub radiance: Spectra,
+    pub sidedness: Sidedness,
+}
+
+impl Default for Emissive {
+    fn default() -> Self {
+        Emissive {
+            radiance: white_spectra(),
+            sidedness: Sidedness::default(),
+        }
+    }
+}
+
+impl BRDF for Emissive {
+    fn evaluate(&self, _ω_i: &Vector, _ω_o: &Vector, _normal: &Vector) -> Spectra {
+        black_spectra()
+    }
+
+    fn emission(&self) -> Option<&Spectra> {
+        Some(&self.radiance)
+    }
+
+    fn sidedness(&self) -> Sidedness {
+        self.sidedness
+    }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Emissive {
+            radiance: SpectrumDescription::from(&self.radiance),
+            sidedness: self.sidedness,
+        })
+    }
+}
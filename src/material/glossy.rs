@@ -0,0 +1,197 @@
+//! the metallic-roughness model glTF and most authoring tools use
+//! a GGX specular layer over a diffuse base, with metals keeping only the specular layer tinted by their albedo
+use rand::Rng;
+
+use crate::{
+    geometry::primitives::{cosine_over_hemisphere, Vector},
+    lighting::{black_spectra, const_spectra, Spectra},
+    material::{
+        cosθ,
+        microfacet::{
+            ggx_distribution, ggx_reflection_pdf, roughness_to_alpha, sample_ggx_reflection,
+            smith_g,
+        },
+        Sidedness, BRDF,
+    },
+    random::Pcg32,
+    scene::scene_file::{MaterialDescription, SpectrumDescription},
+};
+use std::f32::consts::PI;
+
+/// how much a dielectric reflects straight on, about right for plastics, paints and glass
+const DIELECTRIC_REFLECTANCE: f32 = 0.04;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Glossy {
+    /// the diffuse color, or for metals the color of the reflection
+    pub albedo: Spectra,
+    /// 0 for dielectrics, 1 for metals, in between for blending the two
+    pub metallic: f32,
+    /// 0 is polished, 1 is as rough as it gets
+    pub roughness: f32,
+    pub sidedness: Sidedness,
+}
+
+impl Default for Glossy {
+    fn default() -> Self {
+        Glossy {
+            albedo: const_spectra(0.3),
+            metallic: 0.,
+            roughness: 0.5,
+            sidedness: Sidedness::default(),
+        }
+    }
+}
+
+impl Glossy {
+    /// reflectance straight on
+    fn f0(&self) -> Spectra {
+        const_spectra(DIELECTRIC_REFLECTANCE * (1. - self.metallic))
+            + self.metallic * self.albedo.clone()
+    }
+
+    /// Schlick's approximation of the Fresnel equations
+    fn fresnel(&self, cosθ: f32) -> Spectra {
+        let grazing = (1. - cosθ.clamp(0., 1.)).powi(5);
+        Spectra {
+            spectra: self.f0().spectra.mapv(|f0| f0 + (1. - f0) * grazing),
+        }
+    }
+
+    /// how often `sample` follows the specular layer rather than the diffuse one
+    /// roughly in proportion to how much light each reflects, but never so rarely that highlights stay noisy
+    fn specular_probability(&self) -> f32 {
+        let specular = self.f0().spectra.mean().unwrap_or(0.);
+        let diffuse =
+            (1. - self.metallic) * self.albedo.spectra.mean().unwrap_or(0.) * (1. - specular);
+        if specular + diffuse <= 0. {
+            return 0.5;
+        }
+        (specular / (specular + diffuse)).clamp(0.25, 1.)
+    }
+}
+
+impl BRDF for Glossy {
+    fn evaluate(&self, ω_i: &Vector, ω_o: &Vector, normal: &Vector) -> Spectra {
+        let cosθ_i = cosθ(ω_i, normal);
+        let cosθ_o = cosθ(ω_o, normal);
+        if cosθ_i <= 0. || cosθ_o <= 0. {
+            return black_spectra();
+        }
+        let half = (ω_i.unitized() + ω_o.unitized()).unitized();
+        let alpha = roughness_to_alpha(self.roughness);
+        let fresnel = self.fresnel(half.dot(&ω_i.unitized()));
+
+        let d = ggx_distribution(cosθ(&half, normal), alpha);
+        let g = smith_g(cosθ_i, cosθ_o, alpha);
+        let specular = (d * g / (4. * cosθ_i * cosθ_o)) * fresnel;
+        // what the specular layer doesn't reflect gets through to the base, less of it toward grazing angles
+        // Ashikhmin and Shirley's fit, which keeps the two layers together from reflecting more than arrives
+        let through = |cosθ: f32| 1. - (1. - cosθ / 2.).powi(5);
+        let transmitted = Spectra {
+            spectra: self.f0().spectra.mapv(|f0| 1. - f0),
+        };
+        let diffuse = ((1. - self.metallic) * 28. / (23. * PI) * through(cosθ_i) * through(cosθ_o))
+            * self.albedo.clone()
+            * transmitted;
+        specular + diffuse
+    }
+
+    /// from one layer or the other, the density is of both together
+    fn sample(&self, ω_o: &Vector, normal: &Vector, rng: &mut Pcg32) -> (Vector, f32, Spectra) {
+        let ω_i = if rng.gen::<f32>() < self.specular_probability() {
+            sample_ggx_reflection(ω_o, normal, roughness_to_alpha(self.roughness), rng)
+        } else {
+            cosine_over_hemisphere(normal.unitized(), rng)
+        };
+        (
+            ω_i,
+            self.pdf(&ω_i, ω_o, normal),
+            self.evaluate(&ω_i, ω_o, normal),
+        )
+    }
+
+    fn pdf(&self, ω_i: &Vector, ω_o: &Vector, normal: &Vector) -> f32 {
+        let cosθ_i = cosθ(ω_i, normal);
+        if cosθ_i <= 0. {
+            return 0.;
+        }
+        let specular = self.specular_probability();
+        let alpha = roughness_to_alpha(self.roughness);
+        specular * ggx_reflection_pdf(ω_i, ω_o, normal, alpha) + (1. - specular) * cosθ_i / PI
+    }
+
+    fn sidedness(&self) -> Sidedness {
+        self.sidedness
+    }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Glossy {
+            albedo: SpectrumDescription::from(&self.albedo),
+            metallic: self.metallic,
+            roughness: self.roughness,
+            sidedness: self.sidedness,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::{
+        geometry::primitives::Vector,
+        lighting::const_spectra,
+        material::{
+            conductor::RoughConductor,
+            furnace::{reflectance, sampled_reflectance},
+            BRDF,
+        },
+        random::Pcg32,
+    };
+
+    use super::Glossy;
+
+    #[test]
+    fn white_glossy_surfaces_keep_to_the_light_they_get() {
+        let normal = Vector::new(0., 0., 1.);
+        for (metallic, roughness) in [(0., 0.2), (0., 1.), (1., 0.4), (0.5, 0.7)] {
+            let white = Glossy {
+                albedo: const_spectra(1.),
+                metallic,
+                roughness,
+                ..Glossy::default()
+            };
+            for ω_o in [Vector::new(0., 0., 1.), Vector::new(0.9, 0., 0.3)] {
+                let total = reflectance(&white, &ω_o, &normal, 550.);
+                assert!(total <= 1.01, "{metallic} {roughness} reflects {total}");
+                assert!(total > 0.5, "{metallic} {roughness} reflects {total}");
+            }
+        }
+    }
+
+    #[test]
+    fn sampling_agrees_with_evaluating() {
+        let normal = Vector::new(0., 0., 1.);
+        let ω_o = Vector::new(0.5, -0.2, 0.8);
+        let mut rng = Pcg32::new(4, 4);
+        let materials: [Box<dyn BRDF>; 3] = [
+            Box::new(Glossy {
+                roughness: 0.3,
+                ..Glossy::default()
+            }),
+            Box::new(Glossy {
+                metallic: 1.,
+                albedo: const_spectra(0.9),
+                roughness: 0.5,
+                ..Glossy::default()
+            }),
+            Box::new(RoughConductor::default()),
+        ];
+        for material in &materials {
+            let expected = reflectance(material.as_ref(), &ω_o, &normal, 550.);
+            let estimate = sampled_reflectance(material.as_ref(), &ω_o, &normal, 550., &mut rng);
+            assert_abs_diff_eq!(estimate, expected, epsilon = 0.03 * expected);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! surfaces made of countless tiny mirrors, each facing a slightly different way
+//! the GGX (Trowbridge-Reitz) distribution says how many face each way,
+//! and Smith's function how many of those are hidden behind their neighbours
+use std::f32::consts::PI;
+
+use rand::Rng;
+
+use crate::{
+    geometry::primitives::{from_tangent_frame, Vector},
+    material::{cosθ, reflect},
+    random::Pcg32,
+};
+
+/// perceptual roughness, 0 to 1, to the alpha the distribution takes
+/// squared so roughness looks even across its range, and kept off 0 where the distribution becomes a spike
+pub fn roughness_to_alpha(roughness: f32) -> f32 {
+    (roughness * roughness).clamp(1e-3, 1.)
+}
+
+/// the density of microfacets whose normal is the half vector, per steradian of half vector
+/// `cosθ_h` is between the half vector and the surface normal
+pub fn ggx_distribution(cosθ_h: f32, alpha: f32) -> f32 {
+    if cosθ_h <= 0. {
+        return 0.;
+    }
+    let alpha2 = alpha * alpha;
+    let d = cosθ_h * cosθ_h * (alpha2 - 1.) + 1.;
+    alpha2 / (PI * d * d)
+}
+
+/// the fraction of microfacets seen from a direction `cosθ` off the normal that aren't blocked by others
+pub fn smith_g1(cosθ: f32, alpha: f32) -> f32 {
+    if cosθ <= 0. {
+        return 0.;
+    }
+    let alpha2 = alpha * alpha;
+    2. * cosθ / (cosθ + (alpha2 + (1. - alpha2) * cosθ * cosθ).sqrt())
+}
+
+/// masking toward the viewer and shadowing toward the light, taken as independent
+pub fn smith_g(cosθ_i: f32, cosθ_o: f32, alpha: f32) -> f32 {
+    smith_g1(cosθ_i, alpha) * smith_g1(cosθ_o, alpha)
+}
+
+/// a microfacet normal drawn in proportion to how much of the surface it covers, D(h) cosθ_h
+pub fn sample_ggx_half_vector(normal: &Vector, alpha: f32, rng: &mut Pcg32) -> Vector {
+    let ξ: f32 = rng.gen();
+    let φ = 2. * PI * rng.gen::<f32>();
+    // inverting the distribution's cumulative density in tan²θ
+    let tan2θ = alpha * alpha * ξ / (1. - ξ).max(1e-12);
+    let cosθ = 1. / (1. + tan2θ).sqrt();
+    let sinθ = (1. - cosθ * cosθ).max(0.).sqrt();
+    from_tangent_frame(
+        Vector::new(sinθ * φ.cos(), sinθ * φ.sin(), cosθ),
+        normal.unitized(),
+    )
+}
+
+/// the direction ω_o mirrors into about a sampled half vector, below the surface for a facet facing too far away
+pub fn sample_ggx_reflection(
+    ω_o: &Vector, normal: &Vector, alpha: f32, rng: &mut Pcg32
+) -> Vector {
+    reflect(ω_o, &sample_ggx_half_vector(normal, alpha, rng))
+}
+
+/// the density of `sample_ggx_reflection`, per steradian of ω_i
+pub fn ggx_reflection_pdf(ω_i: &Vector, ω_o: &Vector, normal: &Vector, alpha: f32) -> f32 {
+    if cosθ(ω_i, normal) <= 0. || cosθ(ω_o, normal) <= 0. {
+        return 0.;
+    }
+    let half = (ω_i.unitized() + ω_o.unitized()).unitized();
+    let cosθ_h = cosθ(&half, normal);
+    // reflecting squeezes solid angle, by 4 |ω_o · h| from half vectors to directions
+    ggx_distribution(cosθ_h, alpha) * cosθ_h / (4. * half.dot(&ω_o.unitized()).abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::{geometry::primitives::Vector, random::Pcg32};
+
+    use super::{
+        ggx_distribution, ggx_reflection_pdf, roughness_to_alpha, sample_ggx_reflection, smith_g1,
+    };
+
+    #[test]
+    fn facets_cover_the_surface_once() {
+        // the projected area of all the facets is the area of the surface
+        for roughness in [0.2, 0.5, 1.] {
+            let alpha = roughness_to_alpha(roughness);
+            let steps = 20_000;
+            let dθ = (PI / 2.) / steps as f32;
+            let projected: f32 = (0..steps)
+                .map(|i| {
+                    let θ = (i as f32 + 0.5) * dθ;
+                    ggx_distribution(θ.cos(), alpha) * θ.cos() * θ.sin() * dθ * 2. * PI
+                })
+                .sum();
+            assert_abs_diff_eq!(projected, 1., epsilon = 1e-2);
+        }
+    }
+
+    #[test]
+    fn nothing_hides_straight_on() {
+        assert_abs_diff_eq!(smith_g1(1., 0.5), 1.);
+        assert!(smith_g1(0.1, 0.5) < smith_g1(0.5, 0.5));
+        assert_eq!(smith_g1(-0.1, 0.5), 0.);
+    }
+
+    #[test]
+    fn sampled_directions_have_their_density() {
+        let normal = Vector::new(0., 0., 1.);
+        let ω_o = Vector::new(0., 0.3, 1.);
+        let alpha = roughness_to_alpha(0.6);
+
+        // integrating the density over a cap around the normal, and over the whole hemisphere
+        let steps = 400;
+        let (dθ, dφ) = ((PI / 2.) / steps as f32, (2. * PI) / steps as f32);
+        let (mut cap, mut hemisphere) = (0., 0.);
+        for i in 0..steps {
+            let θ = (i as f32 + 0.5) * dθ;
+            for j in 0..steps {
+                let φ = (j as f32 + 0.5) * dφ;
+                let ω_i = Vector::new(θ.sin() * φ.cos(), θ.sin() * φ.sin(), θ.cos());
+                let density = ggx_reflection_pdf(&ω_i, &ω_o, &normal, alpha) * θ.sin() * dθ * dφ;
+                hemisphere += density;
+                if ω_i.z > 0.8 {
+                    cap += density;
+                }
+            }
+        }
+        // some facets send light below the horizon, those samples are thrown away
+        assert!(hemisphere <= 1. && hemisphere > 0.8);
+
+        // which should be how often samples land there
+        let mut rng = Pcg32::new(9, 2);
+        let samples = 50_000;
+        let landed = (0..samples)
+            .filter(|_| sample_ggx_reflection(&ω_o, &normal, alpha, &mut rng).z > 0.8)
+            .count();
+        assert_abs_diff_eq!(landed as f32 / samples as f32, cap, epsilon = 1e-2);
+    }
+}
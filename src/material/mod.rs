@@ -0,0 +1,293 @@
+use crate::{
+    geometry::primitives::{cosine_over_hemisphere, Vector},
+    lighting::{const_spectra, Radiance, RadiantIntensity, Spectra},
+    random::Pcg32,
+    scene::scene_file::{MaterialDescription, SpectrumDescription},
+};
+use serde::{Deserialize, Serialize};
+use std::{f32::consts::PI, fmt::Debug};
+
+pub mod coated;
+pub mod conductor;
+pub mod dielectric;
+pub mod emissive;
+pub mod glossy;
+pub mod microfacet;
+
+/// white furnace checks, how much of the light arriving from everywhere a material sends toward one direction
+#[cfg(test)]
+pub(crate) mod furnace {
+    use std::f32::consts::PI;
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::{geometry::primitives::Vector, material::BRDF, random::Pcg32};
+
+    /// ∫ f cosθ_i dω_i at one wavelength, by the midpoint rule over the hemisphere
+    pub fn reflectance(material: &dyn BRDF, ω_o: &Vector, normal: &Vector, λ: f32) -> f32 {
+        let steps = 300;
+        let (dθ, dφ) = ((PI / 2.) / steps as f32, (2. * PI) / steps as f32);
+        let mut total = 0.;
+        for i in 0..steps {
+            let θ = (i as f32 + 0.5) * dθ;
+            for j in 0..steps {
+                let φ = (j as f32 + 0.5) * dφ;
+                let ω_i = Vector::new(θ.sin() * φ.cos(), θ.sin() * φ.sin(), θ.cos());
+                total +=
+                    material.evaluate(&ω_i, ω_o, normal).from_λ(λ) * θ.cos() * θ.sin() * dθ * dφ;
+            }
+        }
+        total
+    }
+
+    /// the same by the material's own importance sampling, checking its densities on the way
+    pub fn sampled_reflectance(
+        material: &dyn BRDF,
+        ω_o: &Vector,
+        normal: &Vector,
+        λ: f32,
+        rng: &mut Pcg32,
+    ) -> f32 {
+        let samples = 20_000;
+        let mut total = 0.;
+        for _ in 0..samples {
+            let (ω_i, pdf, value) = material.sample(ω_o, normal, rng);
+            if pdf > 0. {
+                assert_abs_diff_eq!(pdf, material.pdf(&ω_i, ω_o, normal), epsilon = 1e-3 * pdf);
+                total += value.from_λ(λ) * ω_i.dot(normal) / pdf;
+            }
+        }
+        total / samples as f32
+    }
+}
+
+pub trait BRDF: Debug + Sync + Send {
+    /// light from a point source, reflected toward the viewer
+    /// unless a material says otherwise, the BRDF weighted by the cosine of the light
+    fn rendering_equation(
+        &self,
+        _x: &Vector,     // position vector of equation
+        ω_i: &Vector,    // vector to light
+        ω_o: &Vector,    // light exit path
+        normal: &Vector, // surface normal
+        // incoming_radiant_intensity: Spectra, // the radiant flux of the lightsource encoded as a spectrum
+        incoming_radiant_intensity: RadiantIntensity, // the radiant flux of the lightsource encoded as a spectrum
+    ) -> Radiance {
+        let cosθ_i = cosθ(ω_i, normal).max(0.);
+        (cosθ_i * self.evaluate(ω_i, ω_o, normal) * incoming_radiant_intensity.0).into()
+    }
+
+    /// the BRDF itself, the share of light arriving along ω_i that leaves along ω_o, per steradian
+    /// both point away from the surface and needn't be unit length
+    fn evaluate(&self, ω_i: &Vector, ω_o: &Vector, normal: &Vector) -> Spectra;
+
+    /// a direction for light to arrive from, drawn roughly in proportion to how much of it is reflected toward ω_o
+    /// gives the unit direction, its probability density per steradian and the BRDF there
+    /// a density of 0 means nothing was drawn
+    /// unless a material knows better, directions are cosine weighted over the hemisphere
+    fn sample(&self, ω_o: &Vector, normal: &Vector, rng: &mut Pcg32) -> (Vector, f32, Spectra) {
+        let ω_i = cosine_over_hemisphere(normal.unitized(), rng);
+        (
+            ω_i,
+            self.pdf(&ω_i, ω_o, normal),
+            self.evaluate(&ω_i, ω_o, normal),
+        )
+    }
+
+    /// the density `sample` draws ω_i with, per steradian
+    fn pdf(&self, ω_i: &Vector, _ω_o: &Vector, normal: &Vector) -> f32 {
+        cosθ(ω_i, normal).max(0.) / PI
+    }
+
+    /// which sides of a polygon this material covers
+    fn sidedness(&self) -> Sidedness {
+        Sidedness::TwoSided
+    }
+
+    /// smooth surfaces reflect light from exactly one direction, which no point light will ever be in
+    /// so instead they name that direction, unit length, and how much of each wavelength comes back from it
+    fn specular_reflection(&self, _ω_o: &Vector, _normal: &Vector) -> Option<(Vector, Spectra)> {
+        None
+    }
+
+    /// clear surfaces pass light on from one of two directions, the reflection or the refraction
+    /// which one is picked at random, in proportion to how much light comes from each
+    /// `front_face` is false when the ray is inside, leaving the material
+    /// `wavelength` is the one the path carries, if something dispersive has already narrowed it to one
+    fn specular_scatter(
+        &self,
+        _ω_o: &Vector,
+        _normal: &Vector,
+        _front_face: bool,
+        _wavelength: &mut Option<f32>,
+        _rng: &mut Pcg32,
+    ) -> Option<(Vector, Spectra)> {
+        None
+    }
+
+    /// the radiance given off, the same in every direction, None for materials that only reflect
+    /// meshes wearing an emitting material are sampled as lights
+    fn emission(&self) -> Option<&Spectra> {
+        None
+    }
+
+    /// how a scene file writes this material down, None for ones it can't
+    fn describe(&self) -> Option<MaterialDescription> {
+        None
+    }
+}
+
+/// how a ray hitting the back of a polygon is treated
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sidedness {
+    /// the back is opaque but reflects nothing
+    OneSided,
+    /// both sides shade the same, the normal is flipped toward the ray
+    #[default]
+    TwoSided,
+    /// the back does not exist, rays and shadows pass straight through it
+    BackfaceCulled,
+}
+
+/// Lambert's law of cosines
+pub fn cosθ(ω: &Vector, normal: &Vector) -> f32 {
+    let divisor: f32 = ω.magnitude() * normal.magnitude();
+
+    ω.dot(normal) / divisor
+}
+
+/// ω mirrored about the normal, both pointing away from the surface
+pub fn reflect(ω: &Vector, normal: &Vector) -> Vector {
+    let ω = ω.unitized();
+    let normal = normal.unitized();
+    normal.times(2. * ω.dot(&normal)) - ω
+}
+
+/// physical object in space with associated data
+// I want shaders to simply be a trait
+// any function that takes in all the necessary data and returns a light value is a shader
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diffuse {
+    /// 0 is Lambertian, up to 1 the surface looks flatter and throws more light back toward the light
+    pub roughness: f32,
+    pub albedo: Spectra,
+    pub sidedness: Sidedness,
+}
+
+impl Default for Diffuse {
+    fn default() -> Self {
+        Diffuse {
+            roughness: 0.0,
+            albedo: const_spectra(0.3),
+            sidedness: Sidedness::default(),
+        }
+    }
+}
+
+impl BRDF for Diffuse {
+    /// Fujii's improvement on Oren and Nayar's qualitative model, which unlike the original never reflects more than it gets
+    /// it's Lambert's at a roughness of 0
+    fn evaluate(&self, ω_i: &Vector, ω_o: &Vector, normal: &Vector) -> Spectra {
+        let lambertian = (1. / PI) * self.albedo.clone();
+        if self.roughness <= 0. {
+            return lambertian;
+        }
+        let a = 1. / (1. + (0.5 - 2. / (3. * PI)) * self.roughness);
+        let b = self.roughness * a;
+
+        let cosθ_i = cosθ(ω_i, normal).max(0.);
+        let cosθ_o = cosθ(ω_o, normal).max(0.);
+        // how much the two directions lean the same way around the normal
+        let s = ω_i.unitized().dot(&ω_o.unitized()) - cosθ_i * cosθ_o;
+        let t = if s > 0. { cosθ_i.max(cosθ_o) } else { 1. };
+        (a + b * s / t.max(1e-4)) * lambertian
+    }
+
+    fn sidedness(&self) -> Sidedness {
+        self.sidedness
+    }
+
+    fn describe(&self) -> Option<MaterialDescription> {
+        Some(MaterialDescription::Diffuse {
+            albedo: SpectrumDescription::from(&self.albedo),
+            roughness: self.roughness,
+            sidedness: self.sidedness,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::{
+        geometry::primitives::Vector,
+        lighting::const_spectra,
+        material::furnace::{reflectance, sampled_reflectance},
+        random::Pcg32,
+    };
+
+    use super::{Diffuse, BRDF};
+
+    #[test]
+    fn rough_diffuse_never_gives_back_more_than_it_gets() {
+        let normal = Vector::new(0., 0., 1.);
+        for roughness in [0., 0.3, 0.6, 1.] {
+            let white = Diffuse {
+                albedo: const_spectra(1.),
+                roughness,
+                ..Diffuse::default()
+            };
+            for ω_o in [
+                Vector::new(0., 0., 1.),
+                Vector::new(0.7, 0.2, 0.7),
+                Vector::new(0.99, 0., 0.05),
+            ] {
+                let total = reflectance(&white, &ω_o, &normal, 550.);
+                assert!(total <= 1., "{roughness} reflects {total}");
+                if roughness == 0. {
+                    assert_abs_diff_eq!(total, 1., epsilon = 1e-3);
+                }
+            }
+        }
+        // seen straight on the two directions never lean together, so Fujii's closed form gives back just A
+        let rough = Diffuse {
+            albedo: const_spectra(1.),
+            roughness: 1.,
+            ..Diffuse::default()
+        };
+        let straight_on = reflectance(&rough, &normal, &normal, 550.);
+        assert_abs_diff_eq!(straight_on, 1. / (1.5 - 2. / (3. * PI)), epsilon = 1e-3);
+    }
+
+    #[test]
+    fn rough_diffuse_is_reciprocal_and_throws_light_back() {
+        let normal = Vector::new(0., 0., 1.);
+        let clay = Diffuse {
+            albedo: const_spectra(0.8),
+            roughness: 0.5,
+            ..Diffuse::default()
+        };
+        let a = Vector::new(0.6, 0.1, 0.5);
+        let b = Vector::new(-0.2, 0.7, 0.4);
+        assert_abs_diff_eq!(
+            clay.evaluate(&a, &b, &normal).from_λ(550.),
+            clay.evaluate(&b, &a, &normal).from_λ(550.),
+            epsilon = 1e-6
+        );
+        // lit from the side, it's brighter seen from the light than from the other side
+        let light = Vector::new(0.8, 0., 0.6);
+        let back = clay.evaluate(&light, &light, &normal).from_λ(550.);
+        let across = clay
+            .evaluate(&light, &Vector::new(-0.8, 0., 0.6), &normal)
+            .from_λ(550.);
+        assert!(back > across);
+        let mut rng = Pcg32::new(2, 7);
+        let expected = reflectance(&clay, &light, &normal, 550.);
+        let estimate = sampled_reflectance(&clay, &light, &normal, 550., &mut rng);
+        assert_abs_diff_eq!(estimate, expected, epsilon = 0.02 * expected);
+    }
+}
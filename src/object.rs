@@ -22,13 +22,15 @@ pub struct Object {
     pub material: Arc<dyn BRDF>,
     pub meshes: Vec<Mesh>,
     pub parent: Option<EntityKey>,
+    pub name: Option<String>,
+    pub tags: Vec<String>,
     // & links to textures associated with it
 }
 impl Object {
     pub fn get_radius(&self) -> f32 {
         let mut furthest = 0.;
         for mesh in &self.meshes {
-            for vertex in &mesh.vertices {
+            for vertex in mesh.vertices.iter() {
                 let dist = self.scale.max() * vertex.position.magnitude();
                 if dist > furthest {
                     furthest = dist;
@@ -57,12 +59,17 @@ impl Default for Object {
             material: Arc::new(Diffuse::default()),
             meshes: Vec::new(),
             parent: None,
+            name: None,
+            tags: Vec::new(),
         }
     }
 }
 
 /// this will be modified in the future to accomodate a hierarchial parent child node system
 impl Entity for Object {
+    fn clone_entity(&self) -> Box<dyn Entity> {
+        Box::new(self.clone())
+    }
     fn get_position(&self) -> Vector {
         self.position
     }
@@ -81,6 +88,33 @@ impl Entity for Object {
     fn set_parent(&mut self, parent: EntityKey) {
         self.parent = Some(parent);
     }
+    fn get_parent(&self) -> Option<EntityKey> {
+        self.parent
+    }
+    fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+    fn set_position(&mut self, position: Vector) {
+        self.position = position;
+    }
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+    fn set_scale(&mut self, scale: Vector) {
+        self.scale = scale;
+    }
+    fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_string());
+    }
+    fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+    fn get_mut_tags(&mut self) -> &mut Vec<String> {
+        &mut self.tags
+    }
     fn as_object(&self) -> Option<&Object> {
         Some(self)
     }
@@ -0,0 +1,124 @@
+use rand::{Error, RngCore};
+
+const MULTIPLIER: u64 = 6364136223846793005;
+
+/// permuted congruential generator, the 32 bit XSH RR variant
+/// small and quick to make, so every sample of every pixel can have its own
+/// which is what keeps a render the same however its pixels are split between threads
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pcg32 {
+    state: u64,
+    /// picks one of 2^63 sequences, always odd
+    increment: u64,
+}
+
+impl Pcg32 {
+    /// the same as the reference implementation's `pcg32_srandom_r`
+    pub fn new(seed: u64, stream: u64) -> Pcg32 {
+        let mut rng = Pcg32 {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        rng.step();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.step();
+        rng
+    }
+
+    /// the numbers for one pixel of a render, whichever thread or tile gets to it
+    pub fn for_pixel(seed: u64, x: u32, y: u32) -> Pcg32 {
+        Pcg32::new(mix(seed), pixel_stream(x, y))
+    }
+
+    /// the numbers for one sample of one pixel, so samples don't depend on how many came before
+    pub fn for_sample(seed: u64, x: u32, y: u32, sample: u32) -> Pcg32 {
+        Pcg32::new(mix(seed ^ mix(sample as u64 + 1)), pixel_stream(x, y))
+    }
+
+    fn step(&mut self) {
+        self.state = self
+            .state
+            .wrapping_mul(MULTIPLIER)
+            .wrapping_add(self.increment);
+    }
+}
+
+/// every pixel is its own sequence
+fn pixel_stream(x: u32, y: u32) -> u64 {
+    ((y as u64) << 32) | x as u64
+}
+
+/// splitmix64's finalizer, so seeds that are close together start far apart
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+impl RngCore for Pcg32 {
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.step();
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rotation = (old >> 59) as u32;
+        xorshifted.rotate_right(rotation)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = self.next_u32() as u64;
+        let high = self.next_u32() as u64;
+        (high << 32) | low
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, RngCore};
+
+    use super::Pcg32;
+
+    #[test]
+    fn matches_the_reference() {
+        // the first numbers pcg32-demo prints for its fixed seed
+        let mut rng = Pcg32::new(42, 54);
+        let first: Vec<u32> = (0..6).map(|_| rng.next_u32()).collect();
+        assert_eq!(
+            first,
+            [0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e]
+        );
+    }
+
+    #[test]
+    fn streams_are_their_own() {
+        let draw = |mut rng: Pcg32| -> Vec<f32> { (0..4).map(|_| rng.gen()).collect() };
+        assert_eq!(
+            draw(Pcg32::for_sample(7, 3, 4, 0)),
+            draw(Pcg32::for_sample(7, 3, 4, 0))
+        );
+        let samples = [
+            draw(Pcg32::for_sample(7, 3, 4, 0)),
+            draw(Pcg32::for_sample(7, 3, 4, 1)),
+            draw(Pcg32::for_sample(7, 4, 3, 0)),
+            draw(Pcg32::for_sample(8, 3, 4, 0)),
+            draw(Pcg32::for_pixel(7, 3, 4)),
+        ];
+        for (i, a) in samples.iter().enumerate() {
+            for b in &samples[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+}
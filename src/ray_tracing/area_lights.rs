@@ -0,0 +1,228 @@
+//! meshes wearing an emitting material, broken into world space triangles to pick points on for direct lighting
+use rand::Rng;
+
+use crate::{
+    geometry::primitives::Vector, lighting::Spectra, material::Sidedness, random::Pcg32,
+    ray_tracing::bounding_volume_hierarchy::Instance, scene::scene::Scene,
+};
+
+/// one emitting triangle, placed in the world
+#[derive(Clone, Debug)]
+pub struct EmittingTriangle {
+    pub a: Vector,
+    pub b: Vector,
+    pub c: Vector,
+    /// unitized, toward the front, which is the side that shines when only one does
+    pub normal: Vector,
+    pub area: f32,
+    pub radiance: Spectra,
+    pub sidedness: Sidedness,
+}
+
+impl EmittingTriangle {
+    /// evenly over the triangle
+    fn sample_point(&self, rng: &mut Pcg32) -> Vector {
+        let s = rng.gen::<f32>().sqrt();
+        let t = rng.gen::<f32>();
+        self.a.times(1. - s) + self.b.times(s * (1. - t)) + self.c.times(s * t)
+    }
+}
+
+/// a point picked on a light, as seen from the point being lit
+#[derive(Clone, Debug)]
+pub struct LightSample {
+    /// unitized, toward the light
+    pub direction: Vector,
+    pub distance: f32,
+    pub radiance: Spectra,
+    /// per steradian, as seen from the point being lit
+    pub pdf: f32,
+}
+
+/// every emitting triangle in the scene
+#[derive(Clone, Debug, Default)]
+pub struct AreaLights {
+    pub triangles: Vec<EmittingTriangle>,
+    /// running total of each triangle's area times its mean radiance, for picking them in proportion to their power
+    cumulative_power: Vec<f32>,
+}
+
+impl AreaLights {
+    /// expects the instances' meshes to carry their world transforms
+    pub fn build(scene: &Scene, instances: &[Instance]) -> AreaLights {
+        let mut lights = AreaLights::default();
+        for instance in instances {
+            let material = &scene.get(instance.owner).as_object().unwrap().material;
+            let Some(radiance) = material.emission() else {
+                continue;
+            };
+            let to_world = instance.mesh.get_transform();
+            for i in 0..instance.mesh.polygons.len() {
+                let polygon = instance.mesh.polygon(i);
+                let a = to_world.apply_to_point(&polygon.a.position);
+                let b = to_world.apply_to_point(&polygon.b.position);
+                let c = to_world.apply_to_point(&polygon.c.position);
+                let area = 0.5 * (b - a).cross(&(c - a)).magnitude();
+                if area <= 0. {
+                    continue;
+                }
+                // the same way intersections find the front, so a mirroring transform doesn't turn a light around
+                let normal = instance
+                    .to_object
+                    .apply_transposed_to_direction(&polygon.get_normal())
+                    .unitized();
+                lights.push(EmittingTriangle {
+                    a,
+                    b,
+                    c,
+                    normal,
+                    area,
+                    radiance: radiance.clone(),
+                    sidedness: material.sidedness(),
+                });
+            }
+        }
+        lights
+    }
+
+    fn push(&mut self, triangle: EmittingTriangle) {
+        let power = triangle.area * triangle.radiance.spectra.mean().unwrap_or(0.);
+        let total = self.cumulative_power.last().copied().unwrap_or(0.);
+        self.cumulative_power.push(total + power);
+        self.triangles.push(triangle);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+
+    /// a point on one of the lights, for lighting `from`
+    /// None when there are no lights, or the point picked faces away
+    pub fn sample(&self, from: Vector, rng: &mut Pcg32) -> Option<LightSample> {
+        let total = *self.cumulative_power.last()?;
+        if total <= 0. {
+            return None;
+        }
+        let pick = rng.gen::<f32>() * total;
+        let index = self
+            .cumulative_power
+            .partition_point(|&power| power <= pick)
+            .min(self.triangles.len() - 1);
+        let triangle = &self.triangles[index];
+        let previous = if index == 0 {
+            0.
+        } else {
+            self.cumulative_power[index - 1]
+        };
+        let probability = (self.cumulative_power[index] - previous) / total;
+
+        let to_light = triangle.sample_point(rng) - from;
+        let distance = to_light.magnitude();
+        if distance <= 0. {
+            return None;
+        }
+        let direction = to_light / distance;
+        let facing = -direction.dot(&triangle.normal);
+        let cosθ_light = match triangle.sidedness {
+            Sidedness::TwoSided => facing.abs(),
+            Sidedness::OneSided | Sidedness::BackfaceCulled => facing,
+        };
+        if cosθ_light <= 0. {
+            return None;
+        }
+        // picked evenly over the triangle's area, which from here covers less solid angle the further and more tilted it is
+        let pdf = probability / triangle.area * distance * distance / cosθ_light;
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: triangle.radiance.clone(),
+            pdf,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::PI, sync::Arc};
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::{
+        geometry::primitives::{Mesh, Vector, Vertex},
+        lighting::const_spectra,
+        material::{emissive::Emissive, Diffuse, Sidedness},
+        object::Object,
+        random::Pcg32,
+        ray_tracing::bounding_volume_hierarchy::InstanceHierarchy,
+        scene::scene::Scene,
+    };
+
+    /// a 2 by 2 square at height y, facing down
+    fn panel(y: f32) -> Mesh {
+        Mesh::new(
+            vec![
+                Vertex::new(-1., y, -1.),
+                Vertex::new(1., y, -1.),
+                Vertex::new(1., y, 1.),
+                Vertex::new(-1., y, 1.),
+            ],
+            vec![vec![0, 1, 2], vec![0, 2, 3]],
+        )
+    }
+
+    fn lit_scene(sidedness: Sidedness, radiance: f32) -> Scene {
+        let mut scene = Scene::default();
+        scene.push_object(Object {
+            meshes: vec![panel(1.)],
+            material: Arc::new(Emissive {
+                radiance: const_spectra(radiance),
+                sidedness,
+            }),
+            ..Object::default()
+        });
+        // meshes that don't glow aren't lights
+        scene.push_object(Object {
+            meshes: vec![panel(-1.)],
+            material: Arc::new(Diffuse::default()),
+            ..Object::default()
+        });
+        scene
+    }
+
+    #[test]
+    fn lights_cover_the_solid_angle_they_subtend() {
+        let scene = lit_scene(Sidedness::OneSided, 2.);
+        let lights = InstanceHierarchy::build(&scene).lights;
+        assert_eq!(lights.triangles.len(), 2);
+        assert_abs_diff_eq!(lights.triangles.iter().map(|t| t.area).sum::<f32>(), 4.);
+        // the estimate of ∫ dω over the panel is its solid angle, 4 asin(1/2) for a square twice as wide as it is far
+        let mut rng = Pcg32::new(5, 2);
+        let samples = 20000;
+        let mut solid_angle = 0.;
+        for _ in 0..samples {
+            let sample = lights.sample(Vector::new(0., 0., 0.), &mut rng).unwrap();
+            assert_eq!(sample.radiance, const_spectra(2.));
+            assert!(sample.direction.y > 0.);
+            assert!(sample.distance >= 1.);
+            solid_angle += 1. / sample.pdf;
+        }
+        solid_angle /= samples as f32;
+        assert_abs_diff_eq!(solid_angle, 2. * PI / 3., epsilon = 0.02);
+    }
+
+    #[test]
+    fn one_sided_lights_only_shine_from_the_front() {
+        let mut rng = Pcg32::new(1, 1);
+        let above = Vector::new(0.3, 2., 0.);
+        let one_sided = InstanceHierarchy::build(&lit_scene(Sidedness::OneSided, 1.)).lights;
+        assert!((0..100).all(|_| one_sided.sample(above, &mut rng).is_none()));
+        let two_sided = InstanceHierarchy::build(&lit_scene(Sidedness::TwoSided, 1.)).lights;
+        assert!((0..100).all(|_| two_sided.sample(above, &mut rng).is_some()));
+        // nothing to pick from when nothing glows
+        let dark = InstanceHierarchy::build(&lit_scene(Sidedness::OneSided, 0.)).lights;
+        assert!(dark.sample(Vector::new(0., 0., 0.), &mut rng).is_none());
+        let unlit = InstanceHierarchy::build(&Scene::default()).lights;
+        assert!(unlit.is_empty());
+        assert!(unlit.sample(Vector::new(0., 0., 0.), &mut rng).is_none());
+    }
+}
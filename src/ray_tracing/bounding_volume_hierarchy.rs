@@ -0,0 +1,722 @@
+use crate::geometry::primitives::{BoundingBox, Mesh, Ray, Vector};
+use crate::geometry::transformations::Transform;
+use crate::material::Sidedness;
+use crate::ray_tracing::area_lights::AreaLights;
+use crate::ray_tracing::ray_polygon_intersection::{ray_triangle_intersection, TriangleHit};
+use crate::scene::scene::{EntityKey, Scene};
+
+/// number of buckets centroids are sorted into when looking for a split
+const SAH_BINS: usize = 12;
+/// leaves at or below this size are never split
+const MAX_LEAF_SIZE: usize = 2;
+/// cost of stepping through a node relative to testing a primitive
+const TRAVERSAL_COST: f32 = 1.;
+
+/// a node of the flattened tree
+/// leaves hold a run of `count` primitives starting at `first` in `indices`,
+/// interior nodes have a count of zero and their children sit at `first` and `first + 1`
+#[derive(Clone, Debug)]
+pub struct BvhNode {
+    pub bounds: BoundingBox,
+    pub first: usize,
+    pub count: usize,
+}
+
+impl BvhNode {
+    pub fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+/// bounding volume hierarchy built with the surface area heuristic
+/// it only knows about the bounds of whatever it was built over,
+/// callers test the primitives themselves through a closure
+#[derive(Clone, Debug)]
+pub struct BoundingVolumeHierarchy {
+    pub nodes: Vec<BvhNode>,
+    /// primitive indices, reordered so every leaf references a contiguous run
+    pub indices: Vec<usize>,
+}
+
+#[derive(Clone, Copy)]
+struct Bin {
+    bounds: BoundingBox,
+    count: usize,
+}
+
+impl BoundingVolumeHierarchy {
+    /// builds a hierarchy over primitives with the given bounds
+    pub fn build(bounds: &[BoundingBox]) -> BoundingVolumeHierarchy {
+        let centroids: Vec<Vector> = bounds.iter().map(|b| b.centroid()).collect();
+        let mut bvh = BoundingVolumeHierarchy {
+            nodes: Vec::with_capacity(2 * bounds.len()),
+            indices: (0..bounds.len()).collect(),
+        };
+        bvh.nodes.push(BvhNode {
+            bounds: BoundingBox::empty(),
+            first: 0,
+            count: bounds.len(),
+        });
+        if !bounds.is_empty() {
+            bvh.subdivide(0, bounds, &centroids);
+        }
+        bvh
+    }
+
+    fn subdivide(&mut self, node_index: usize, bounds: &[BoundingBox], centroids: &[Vector]) {
+        let first = self.nodes[node_index].first;
+        let count = self.nodes[node_index].count;
+
+        let mut node_bounds = BoundingBox::empty();
+        let mut centroid_bounds = BoundingBox::empty();
+        for &i in &self.indices[first..first + count] {
+            node_bounds = node_bounds.union(&bounds[i]);
+            centroid_bounds.grow(&centroids[i]);
+        }
+        self.nodes[node_index].bounds = node_bounds;
+
+        if count <= MAX_LEAF_SIZE {
+            return;
+        }
+        let Some((axis, split)) = self.find_split(
+            first,
+            count,
+            &node_bounds,
+            &centroid_bounds,
+            bounds,
+            centroids,
+        ) else {
+            return;
+        };
+
+        // partition the primitives in place around the chosen plane
+        let mut left = first;
+        let mut right = first + count;
+        while left < right {
+            if centroids[self.indices[left]].axis(axis) < split {
+                left += 1;
+            } else {
+                right -= 1;
+                self.indices.swap(left, right);
+            }
+        }
+        let left_count = left - first;
+        if left_count == 0 || left_count == count {
+            return;
+        }
+
+        let left_child = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bounds: BoundingBox::empty(),
+            first,
+            count: left_count,
+        });
+        self.nodes.push(BvhNode {
+            bounds: BoundingBox::empty(),
+            first: left,
+            count: count - left_count,
+        });
+        self.nodes[node_index].first = left_child;
+        self.nodes[node_index].count = 0;
+
+        self.subdivide(left_child, bounds, centroids);
+        self.subdivide(left_child + 1, bounds, centroids);
+    }
+
+    /// binned surface area heuristic
+    /// returns the axis and position of the cheapest split, or None if a leaf is cheaper
+    fn find_split(
+        &self,
+        first: usize,
+        count: usize,
+        node_bounds: &BoundingBox,
+        centroid_bounds: &BoundingBox,
+        bounds: &[BoundingBox],
+        centroids: &[Vector],
+    ) -> Option<(usize, f32)> {
+        let leaf_cost = count as f32;
+        let parent_area = node_bounds.surface_area();
+        let mut best: Option<(usize, f32)> = None;
+        let mut best_cost = leaf_cost;
+
+        for axis in 0..3 {
+            let low = centroid_bounds.min.axis(axis);
+            let high = centroid_bounds.max.axis(axis);
+            if high <= low {
+                continue; // every centroid lies on one plane
+            }
+            let scale = SAH_BINS as f32 / (high - low);
+            let mut bins = [Bin {
+                bounds: BoundingBox::empty(),
+                count: 0,
+            }; SAH_BINS];
+            for &i in &self.indices[first..first + count] {
+                let bin = (((centroids[i].axis(axis) - low) * scale) as usize).min(SAH_BINS - 1);
+                bins[bin].count += 1;
+                bins[bin].bounds = bins[bin].bounds.union(&bounds[i]);
+            }
+
+            // sweep from both sides so every plane between bins costs O(1)
+            let mut right_area = [0.; SAH_BINS];
+            let mut right_count = [0; SAH_BINS];
+            let mut accumulated = BoundingBox::empty();
+            let mut total = 0;
+            for bin in (1..SAH_BINS).rev() {
+                accumulated = accumulated.union(&bins[bin].bounds);
+                total += bins[bin].count;
+                right_area[bin] = accumulated.surface_area();
+                right_count[bin] = total;
+            }
+            let mut accumulated = BoundingBox::empty();
+            let mut total = 0;
+            for plane in 1..SAH_BINS {
+                accumulated = accumulated.union(&bins[plane - 1].bounds);
+                total += bins[plane - 1].count;
+                if total == 0 || right_count[plane] == 0 {
+                    continue;
+                }
+                let cost = TRAVERSAL_COST
+                    + (total as f32 * accumulated.surface_area()
+                        + right_count[plane] as f32 * right_area[plane])
+                        / parent_area;
+                if cost < best_cost {
+                    best_cost = cost;
+                    best = Some((axis, low + plane as f32 / scale));
+                }
+            }
+        }
+        best
+    }
+
+    /// walks the tree front to back, calling `intersect` on every primitive in a leaf the ray reaches
+    /// `intersect` returns the distance to a hit closer than the one it is given
+    /// yields the closest primitive index and its distance
+    pub fn closest_hit<F: FnMut(usize, f32) -> Option<f32>>(
+        &self,
+        ray: &Ray,
+        max_dist: f32,
+        mut intersect: F,
+    ) -> Option<(usize, f32)> {
+        let mut closest: Option<(usize, f32)> = None;
+        let mut closest_dist = max_dist;
+        self.traverse(
+            ray,
+            |primitive, limit| {
+                if let Some(dist) = intersect(primitive, limit) {
+                    if dist < closest_dist {
+                        closest_dist = dist;
+                        closest = Some((primitive, dist));
+                    }
+                }
+                (closest_dist, false)
+            },
+            max_dist,
+        );
+        closest
+    }
+
+    /// stops at the first primitive `intersect` reports a hit for
+    pub fn any_hit<F: FnMut(usize, f32) -> bool>(
+        &self,
+        ray: &Ray,
+        max_dist: f32,
+        mut intersect: F,
+    ) -> bool {
+        let mut hit = false;
+        self.traverse(
+            ray,
+            |primitive, limit| {
+                hit = intersect(primitive, limit);
+                (limit, hit)
+            },
+            max_dist,
+        );
+        hit
+    }
+
+    /// the visitor returns the new distance limit and whether to stop
+    fn traverse<F: FnMut(usize, f32) -> (f32, bool)>(
+        &self,
+        ray: &Ray,
+        mut visit: F,
+        max_dist: f32,
+    ) {
+        if self.indices.is_empty() {
+            return;
+        }
+        let inverse_direction = Vector::new(
+            1. / ray.direction.x,
+            1. / ray.direction.y,
+            1. / ray.direction.z,
+        );
+        let mut limit = max_dist;
+        if self.nodes[0]
+            .bounds
+            .ray_intersection(ray, &inverse_direction, limit)
+            .is_none()
+        {
+            return;
+        }
+        let mut stack: Vec<usize> = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if node.is_leaf() {
+                for &primitive in &self.indices[node.first..node.first + node.count] {
+                    let (new_limit, stop) = visit(primitive, limit);
+                    if stop {
+                        return;
+                    }
+                    limit = new_limit;
+                }
+                continue;
+            }
+            let left = node.first;
+            let right = node.first + 1;
+            let left_dist =
+                self.nodes[left]
+                    .bounds
+                    .ray_intersection(ray, &inverse_direction, limit);
+            let right_dist =
+                self.nodes[right]
+                    .bounds
+                    .ray_intersection(ray, &inverse_direction, limit);
+            // the nearer child goes on the stack last so it is visited first
+            match (left_dist, right_dist) {
+                (Some(l), Some(r)) => {
+                    if l <= r {
+                        stack.push(right);
+                        stack.push(left);
+                    } else {
+                        stack.push(left);
+                        stack.push(right);
+                    }
+                }
+                (Some(_), None) => stack.push(left),
+                (None, Some(_)) => stack.push(right),
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+/// one placement of a mesh in the world
+/// the mesh is a clone, so it shares its geometry and bottom level hierarchy with every other instance
+#[derive(Clone, Debug)]
+pub struct Instance {
+    pub owner: EntityKey,
+    pub mesh: Mesh,
+    pub to_object: Transform,
+    /// world space bounds
+    pub bounds: BoundingBox,
+    /// taken from the owner's material
+    pub sidedness: Sidedness,
+}
+
+impl Instance {
+    /// None when the transform flattens the mesh, like a zero scale does, there's nothing left of it to hit
+    pub fn new(
+        owner: EntityKey,
+        mesh: &Mesh,
+        to_world: &Transform,
+        sidedness: Sidedness,
+    ) -> Option<Instance> {
+        let to_object = to_world.inverse()?;
+        let bounds = mesh.bounds().transformed(to_world);
+        Some(Instance {
+            owner,
+            mesh: mesh.clone(),
+            to_object,
+            bounds,
+            sidedness,
+        })
+    }
+
+    /// the same ray expressed in object space
+    /// the direction is deliberately left at whatever length the transform gives it,
+    /// that way distances along the ray are the same in both spaces
+    pub fn to_object_space(&self, ray: &Ray) -> Ray {
+        Ray {
+            position: self.to_object.apply_to_point(&ray.position),
+            direction: self.to_object.apply_to_direction(&ray.direction),
+        }
+    }
+
+    /// back faces only count when the material has them
+    fn probe(&self, local: &Ray, polygon: usize, limit: f32) -> Option<TriangleHit> {
+        let hit = ray_triangle_intersection(local, &self.mesh.polygon(polygon))?;
+        let culled = !hit.front_face && self.sidedness == Sidedness::BackfaceCulled;
+        if !culled && hit.dist < limit {
+            Some(hit)
+        } else {
+            None
+        }
+    }
+
+    /// the closest polygon of this instance along an object space ray
+    fn closest_hit(
+        &self,
+        local: &Ray,
+        limit: f32,
+        accelerated: bool,
+    ) -> Option<(usize, TriangleHit)> {
+        let mut closest = None;
+        let mut probe = |i: usize, limit: f32| {
+            let hit = self.probe(local, i, limit)?;
+            closest = Some((i, hit));
+            Some(hit.dist)
+        };
+        if accelerated {
+            self.mesh.hierarchy().closest_hit(local, limit, probe);
+        } else {
+            let mut closest_dist = limit;
+            for i in 0..self.mesh.polygons.len() {
+                if let Some(dist) = probe(i, closest_dist) {
+                    closest_dist = dist;
+                }
+            }
+        }
+        closest
+    }
+
+    fn any_hit(&self, local: &Ray, limit: f32, accelerated: bool) -> bool {
+        if accelerated {
+            return self.mesh.hierarchy().any_hit(local, limit, |i, limit| {
+                self.probe(local, i, limit).is_some()
+            });
+        }
+        (0..self.mesh.polygons.len()).any(|i| self.probe(local, i, limit).is_some())
+    }
+}
+
+/// what a ray struck
+#[derive(Clone, Debug)]
+pub struct Intersection {
+    /// the object the polygon belongs to
+    pub owner: EntityKey,
+    /// distance along the ray
+    pub dist: f32,
+    /// world space, unitized, always facing back toward the ray
+    /// blended from the vertex normals when the mesh has them
+    pub normal: Vector,
+    /// false when the ray struck the back of the polygon
+    pub front_face: bool,
+}
+
+/// two level acceleration structure
+/// the top level is a hierarchy over object instances, the bottom level is the hierarchy each mesh carries
+/// rebuilt every frame, but only the top level, the meshes keep theirs
+#[derive(Clone, Debug)]
+pub struct InstanceHierarchy {
+    pub instances: Vec<Instance>,
+    pub top: BoundingVolumeHierarchy,
+    /// when false every polygon of every instance is tested, which is only useful for comparison
+    pub accelerated: bool,
+    /// the polygons of the instances that glow, for direct lighting to pick points on
+    pub lights: AreaLights,
+}
+
+impl InstanceHierarchy {
+    /// expects `apply_transforms` to have already accumulated each mesh's world transform
+    pub fn build(scene: &Scene) -> InstanceHierarchy {
+        let mut instances = Vec::new();
+        for key in scene.get_object_keys() {
+            let object = scene.get(key).as_object().unwrap();
+            // flattened meshes are left out, and so are any lights they would have been
+            instances.extend(object.meshes.iter().filter_map(|mesh| {
+                Instance::new(key, mesh, mesh.get_transform(), object.material.sidedness())
+            }));
+        }
+        let bounds: Vec<BoundingBox> = instances.iter().map(|i| i.bounds).collect();
+        InstanceHierarchy {
+            top: BoundingVolumeHierarchy::build(&bounds),
+            lights: AreaLights::build(scene, &instances),
+            instances,
+            accelerated: scene.spatial_acceleration_structures,
+        }
+    }
+
+    /// the closest polygon the ray hits in any instance
+    pub fn closest_hit(&self, ray: &Ray, max_dist: f32) -> Option<Intersection> {
+        let mut polygon_hit = None;
+        let mut test = |instance: usize, limit: f32| {
+            let instance = &self.instances[instance];
+            let local = instance.to_object_space(ray);
+            let (polygon, hit) = instance.closest_hit(&local, limit, self.accelerated)?;
+            polygon_hit = Some((polygon, hit));
+            Some(hit.dist)
+        };
+        let (instance, dist) = if self.accelerated {
+            self.top.closest_hit(ray, max_dist, &mut test)?
+        } else {
+            let mut closest = None;
+            let mut closest_dist = max_dist;
+            for i in 0..self.instances.len() {
+                if let Some(dist) = test(i, closest_dist) {
+                    closest_dist = dist;
+                    closest = Some((i, dist));
+                }
+            }
+            closest?
+        };
+        let (polygon, hit) = polygon_hit?;
+        let instance = &self.instances[instance];
+        let polygon = instance.mesh.polygon(polygon);
+        let to_world = |local: Vector| {
+            instance
+                .to_object
+                .apply_transposed_to_direction(&local)
+                .unitized()
+        };
+        let normal = to_world(polygon.get_normal());
+        // decided in world space, a mirroring transform swaps which side is the front
+        let front_face = normal.dot(&ray.direction) <= 0.;
+        let normal = if front_face { normal } else { -1. * normal };
+        // smooth shading, kept on the side of the polygon the ray arrived at
+        let shading_normal = match polygon.shading_normal(hit.barycentric) {
+            Some(local) => {
+                let shading = to_world(local);
+                if shading.dot(&normal) < 0. {
+                    -1. * shading
+                } else {
+                    shading
+                }
+            }
+            None => normal,
+        };
+        Some(Intersection {
+            owner: instance.owner,
+            dist,
+            normal: shading_normal,
+            front_face,
+        })
+    }
+
+    /// whether anything at all lies along the ray before max_dist
+    pub fn occluded(&self, ray: &Ray, max_dist: f32) -> bool {
+        let test = |instance: usize, limit: f32| {
+            let instance = &self.instances[instance];
+            instance.any_hit(&instance.to_object_space(ray), limit, self.accelerated)
+        };
+        if self.accelerated {
+            self.top.any_hit(ray, max_dist, test)
+        } else {
+            (0..self.instances.len()).any(|i| test(i, max_dist))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::{
+        geometry::{
+            primitives::{BoundingBox, Mesh, Ray, Vector},
+            transformations::{build_scale_transform, build_translation_transform},
+        },
+        lighting::const_spectra,
+        material::{emissive::Emissive, Diffuse, Sidedness},
+        object::Object,
+        scene::{scene::Scene, scenes::simple_scene},
+    };
+
+    use super::{BoundingVolumeHierarchy, InstanceHierarchy};
+
+    fn random_boxes(rng: &mut StdRng, count: usize) -> Vec<BoundingBox> {
+        (0..count)
+            .map(|_| {
+                let corner = Vector::new(
+                    rng.gen_range(-10.0..10.0),
+                    rng.gen_range(-10.0..10.0),
+                    rng.gen_range(-10.0..10.0),
+                );
+                let size = Vector::new(rng.gen(), rng.gen(), rng.gen());
+                BoundingBox {
+                    min: corner,
+                    max: corner + size,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_primitive_lands_in_one_leaf() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let boxes = random_boxes(&mut rng, 1000);
+        let bvh = BoundingVolumeHierarchy::build(&boxes);
+        let mut seen = vec![0; boxes.len()];
+        for node in bvh.nodes.iter().filter(|n| n.is_leaf()) {
+            for &i in &bvh.indices[node.first..node.first + node.count] {
+                seen[i] += 1;
+                // leaves have to contain what they reference
+                assert_eq!(node.bounds.union(&boxes[i]), node.bounds);
+            }
+        }
+        assert!(seen.iter().all(|&n| n == 1));
+        assert!(bvh.nodes.len() > 1);
+    }
+
+    #[test]
+    fn traversal_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let boxes = random_boxes(&mut rng, 500);
+        let bvh = BoundingVolumeHierarchy::build(&boxes);
+        let inverse = |ray: &Ray| {
+            Vector::new(
+                1. / ray.direction.x,
+                1. / ray.direction.y,
+                1. / ray.direction.z,
+            )
+        };
+        for _ in 0..200 {
+            let ray = Ray::new(
+                Vector::new(rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0), 30.),
+                Vector::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), -1.),
+            );
+            let inv = inverse(&ray);
+            let brute_force = boxes
+                .iter()
+                .enumerate()
+                .filter_map(|(i, b)| b.ray_intersection(&ray, &inv, 1e6).map(|d| (i, d)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            let traversed = bvh.closest_hit(&ray, 1e6, |i, limit| {
+                boxes[i].ray_intersection(&ray, &inv, limit)
+            });
+            assert_eq!(brute_force.map(|h| h.1), traversed.map(|h| h.1));
+        }
+    }
+
+    #[test]
+    fn empty_hierarchy() {
+        let bvh = BoundingVolumeHierarchy::build(&[]);
+        let ray = Ray::new(Vector::new(0., 0., 0.), Vector::new(0., 0., -1.));
+        assert!(bvh.closest_hit(&ray, 1e6, |_, _| Some(0.)).is_none());
+        assert!(!bvh.any_hit(&ray, 1e6, |_, _| true));
+    }
+
+    #[test]
+    fn instance_hierarchy_matches_every_polygon() {
+        let mut scene = simple_scene().unwrap();
+        for key in scene.get_object_keys() {
+            let object = scene.get_mut(key).as_object_mut().unwrap();
+            let to_world = build_translation_transform(object.position);
+            for mesh in &mut object.meshes {
+                mesh.add_transform(to_world.clone());
+            }
+        }
+        let accelerated = InstanceHierarchy::build(&scene);
+        scene.spatial_acceleration_structures = false;
+        let brute_force = InstanceHierarchy::build(&scene);
+
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..500 {
+            let ray = Ray::new(
+                Vector::new(0., 1., 7.),
+                Vector::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..0.0),
+                ),
+            );
+            let expected = brute_force.closest_hit(&ray, 1e6);
+            let traversed = accelerated.closest_hit(&ray, 1e6);
+            assert_eq!(expected.map(|h| h.dist), traversed.map(|h| h.dist));
+            assert_eq!(
+                brute_force.occluded(&ray, 1e6),
+                accelerated.occluded(&ray, 1e6)
+            );
+        }
+    }
+
+    #[test]
+    fn instances_share_one_mesh() {
+        let cube = Mesh::_unit_cube();
+        let mut scene = Scene::default();
+        for i in 0..1000 {
+            let object = Object {
+                position: Vector::new(i as f32 * 3., 0., 0.),
+                meshes: vec![cube.clone()],
+                ..Object::default()
+            };
+            scene.push_object(object);
+        }
+        for key in scene.get_object_keys() {
+            let object = scene.get_mut(key).as_object_mut().unwrap();
+            let to_world = build_translation_transform(object.position);
+            object.meshes[0].add_transform(to_world);
+        }
+        let hierarchy = InstanceHierarchy::build(&scene);
+        assert_eq!(hierarchy.instances.len(), 1000);
+        for instance in &hierarchy.instances {
+            assert!(instance.mesh.shares_geometry(&cube));
+            // every instance uses the one bottom level hierarchy the original mesh built
+            assert!(std::ptr::eq(instance.mesh.hierarchy(), cube.hierarchy()));
+        }
+
+        // the last cube sits at x = 2997
+        let ray = Ray::new(Vector::new(2997., 0., 10.), Vector::new(0., 0., -1.));
+        let hit = hierarchy.closest_hit(&ray, 1e6).unwrap();
+        assert_abs_diff_eq!(hit.dist, 9., epsilon = 1e-4);
+        assert_abs_diff_eq!(hit.normal.z, 1., epsilon = 1e-4);
+        assert_eq!(hit.owner, *scene.get_object_keys().last().unwrap());
+    }
+
+    #[test]
+    fn flattened_meshes_are_left_out() {
+        let mut scene = Scene::default();
+        for scale in [Vector::new(1., 1., 1.), Vector::new(1., 0., 1.)] {
+            let mut cube = Mesh::_unit_cube();
+            cube.add_transform(build_scale_transform(scale));
+            scene.push_object(Object {
+                meshes: vec![cube],
+                material: Arc::new(Emissive {
+                    radiance: const_spectra(1.),
+                    sidedness: Sidedness::OneSided,
+                }),
+                ..Object::default()
+            });
+        }
+        let hierarchy = InstanceHierarchy::build(&scene);
+        assert_eq!(hierarchy.instances.len(), 1);
+        assert_eq!(hierarchy.instances[0].owner, scene.get_object_keys()[0]);
+        assert_eq!(hierarchy.lights.triangles.len(), 12);
+    }
+
+    #[test]
+    fn back_faces_follow_the_material() {
+        // looking out from the middle of a cube only ever sees back faces
+        let ray = Ray::new(Vector::new(0., 0., 0.), Vector::new(0., 0., 1.));
+        for sidedness in [
+            Sidedness::OneSided,
+            Sidedness::TwoSided,
+            Sidedness::BackfaceCulled,
+        ] {
+            let mut scene = Scene::default();
+            scene.push_object(Object {
+                meshes: vec![Mesh::_unit_cube()],
+                material: Arc::new(Diffuse {
+                    sidedness,
+                    ..Diffuse::default()
+                }),
+                ..Object::default()
+            });
+            let hierarchy = InstanceHierarchy::build(&scene);
+            let hit = hierarchy.closest_hit(&ray, 1e6);
+            if sidedness == Sidedness::BackfaceCulled {
+                assert!(hit.is_none());
+                assert!(!hierarchy.occluded(&ray, 1e6));
+            } else {
+                let hit = hit.unwrap();
+                assert!(!hit.front_face);
+                // flipped to face back into the cube
+                assert_abs_diff_eq!(hit.normal.z, -1., epsilon = 1e-4);
+                assert!(hierarchy.occluded(&ray, 1e6));
+            }
+        }
+    }
+}
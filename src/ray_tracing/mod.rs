@@ -1,3 +1,5 @@
+pub mod area_lights;
+pub mod bounding_volume_hierarchy;
 pub mod pixel_shader;
 pub mod ray_polygon_intersection;
 pub mod ray_sphere_intersection;
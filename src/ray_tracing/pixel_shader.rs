@@ -1,20 +1,20 @@
 use std::f32::consts::PI;
 
-use crate::camera::Camera;
 use crate::color::colorspace_conversion::{sRGB_to_display, spectra_to_display};
-use crate::geometry::primitives::{even_over_hemisphere, Polygon, Ray, Vector};
+use crate::geometry::primitives::{Ray, Vector};
 use crate::lighting::{black_spectra, void_spectra, Radiance};
+use crate::material::Sidedness;
 use crate::object::Object;
-use crate::ray_tracing::ray_polygon_intersection::probe_ray_polygon_intersection;
+use crate::random::Pcg32;
+use crate::ray_tracing::bounding_volume_hierarchy::{InstanceHierarchy, Intersection};
 
 use crate::geometry_pipeline::Tile;
 use crate::scene::scene::Scene;
 use image::Rgb;
-use rand::{prelude::ThreadRng, thread_rng};
 use stopwatch::Stopwatch;
 
 /// applies rendering mode to scene and yields a canvas
-pub fn shade_pixels<F: Fn(u32, u32, &Scene, &mut ThreadRng) -> Rgb<u8>>(
+pub fn shade_pixels<F: Fn(u32, u32, &Scene, &mut Pcg32) -> Rgb<u8>>(
     tile: &mut Tile,
     scene: &Scene,
     closure: F,
@@ -25,14 +25,13 @@ pub fn shade_pixels<F: Fn(u32, u32, &Scene, &mut ThreadRng) -> Rgb<u8>>(
     let y_start = tile.y_start;
     let y_end = tile.y_start + tile.height;
 
-    let mut rng: rand::prelude::ThreadRng = thread_rng();
     let mut shading = Stopwatch::start_new();
     // let (width, height) = canvas.dimensions();
     let _width = y_end - y_start;
     let _height = x_end - x_start;
     for y in y_start..y_end {
         for x in x_start..x_end {
-            let color = closure(x, y, scene, &mut rng);
+            let color = closure(x, y, scene, &mut Pcg32::for_pixel(scene.seed, x, y));
             mini_canvas.put_pixel(x - x_start, y - y_start, color);
         }
     }
@@ -41,7 +40,7 @@ pub fn shade_pixels<F: Fn(u32, u32, &Scene, &mut ThreadRng) -> Rgb<u8>>(
     if scene.logging > 1 {}
 }
 
-pub fn _color_shader(x: u32, y: u32, scene: &Scene, _rng: &mut ThreadRng) -> Rgb<u8> {
+pub fn _color_shader(x: u32, y: u32, scene: &Scene, _rng: &mut Pcg32) -> Rgb<u8> {
     let (hres, vres) = scene.active_camera().sensor.res();
     let x = x as f32 / (hres as f32);
     let y = y as f32 / (vres as f32);
@@ -69,31 +68,9 @@ pub fn _color_shader(x: u32, y: u32, scene: &Scene, _rng: &mut ThreadRng) -> Rgb
 }
 
 /// shades all objects as solid
-pub fn _solid_shader(x: u32, y: u32, scene: &Scene, rng: &mut ThreadRng) -> Rgb<u8> {
-    let ray = Camera::jittered_pixel_to_ray(scene.active_camera(), x, y, rng);
-    let mut hit = false;
-    // here we're at once per pixel
-    for object in scene.objects() {
-        // this is once per object
-        if scene.spatial_acceleration_structures && !object.ray_intercept(&ray) {
-            continue;
-        }
-        for mesh in &object.meshes {
-            // once per mesh
-            for poly in mesh.polygons.clone() {
-                let a = mesh.output_vertices[poly[0]].clone();
-                let b = mesh.output_vertices[poly[1]].clone();
-                let c = mesh.output_vertices[poly[2]].clone();
-                let polygon = Polygon::new(a, b, c);
-                let (b, _i, _dist) = probe_ray_polygon_intersection(&ray, &polygon);
-                if b {
-                    hit = true;
-                    break;
-                }
-            }
-        }
-    }
-    if hit {
+pub fn _solid_shader(x: u32, y: u32, scene: &Scene, rng: &mut Pcg32) -> Rgb<u8> {
+    let ray = scene.camera_ray(x, y, rng);
+    if occluded(&ray, scene, scene.max_render_dist) {
         Rgb([255, 255, 255])
     } else {
         spectra_to_display(&scene.background)
@@ -101,8 +78,8 @@ pub fn _solid_shader(x: u32, y: u32, scene: &Scene, rng: &mut ThreadRng) -> Rgb<
 }
 
 /// shows where bounding volume hierarchies are
-pub fn bvh_shader(x: u32, y: u32, scene: &Scene, rng: &mut ThreadRng) -> Rgb<u8> {
-    let ray = Camera::jittered_pixel_to_ray(scene.active_camera(), x, y, rng);
+pub fn bvh_shader(x: u32, y: u32, scene: &Scene, rng: &mut Pcg32) -> Rgb<u8> {
+    let ray = scene.camera_ray(x, y, rng);
     let mut hit = false;
     // here we're at once per pixel
     for object in scene.objects() {
@@ -120,13 +97,13 @@ pub fn bvh_shader(x: u32, y: u32, scene: &Scene, rng: &mut ThreadRng) -> Rgb<u8>
 }
 
 /// render depth
-pub fn z_shader(x: u32, y: u32, scene: &Scene, rng: &mut ThreadRng) -> Rgb<u8> {
-    let ray = Camera::jittered_pixel_to_ray(scene.active_camera(), x, y, rng);
+pub fn z_shader(x: u32, y: u32, scene: &Scene, rng: &mut Pcg32) -> Rgb<u8> {
+    let ray = scene.camera_ray(x, y, rng);
     let intersection = shoot_ray(ray, scene, scene.max_trace_depth);
     if intersection.is_none() {
         Rgb([0, 0, 0])
     } else {
-        let (_, _, ω_o, _) = intersection.unwrap();
+        let (_, _, ω_o, _, _) = intersection.unwrap();
         let dist = ω_o.magnitude();
         let fractional_z = 1. - (dist / scene.max_render_dist);
         sRGB_to_display((fractional_z, fractional_z, fractional_z))
@@ -135,8 +112,8 @@ pub fn z_shader(x: u32, y: u32, scene: &Scene, rng: &mut ThreadRng) -> Rgb<u8> {
 
 /// fully lit shading mode
 /// this lambda is executed once per pixel
-pub fn lit_shader(x: u32, y: u32, scene: &Scene, rng: &mut ThreadRng) -> Rgb<u8> {
-    let sample_average = integrate_pixel_radiance(x, y, scene, rng);
+pub fn lit_shader(x: u32, y: u32, scene: &Scene, _rng: &mut Pcg32) -> Rgb<u8> {
+    let sample_average = integrate_pixel_radiance(x, y, scene);
     // we should take a value parameterized in radiance, not radiant exitance!
     let joules = scene.active_camera().exposure_time
         * scene.active_camera().sensor._pixel_area()
@@ -145,44 +122,95 @@ pub fn lit_shader(x: u32, y: u32, scene: &Scene, rng: &mut ThreadRng) -> Rgb<u8>
 
     spectra_to_display(&joules)
 }
-pub fn integrate_pixel_radiance(x: u32, y: u32, scene: &Scene, rng: &mut ThreadRng) -> Radiance {
+/// each sample draws from its own stream, so the result is the same however the pixels are shared out
+pub fn integrate_pixel_radiance(x: u32, y: u32, scene: &Scene) -> Radiance {
     let mut radiance: Radiance = black_spectra().into();
-    for _ in 0..scene.samples {
-        let ray = Camera::jittered_pixel_to_ray(scene.active_camera(), x, y, rng);
-        radiance.0 = radiance.0 + dispatch_light_ray(ray, scene, scene.max_trace_depth, rng).0;
+    for sample in 0..scene.samples {
+        let mut rng = Pcg32::for_sample(scene.seed, x, y, sample);
+        let ray = scene.camera_ray(x, y, &mut rng);
+        radiance.0 = radiance.0
+            + dispatch_light_ray(ray, scene, scene.max_trace_depth, None, true, &mut rng).0;
     }
 
     (radiance.0 / scene.samples as f32).into()
 }
+/// light arriving from everywhere but the lights, estimated from one direction the material draws
+/// weighted by the BRDF and cosine there, over how likely that direction was to be drawn
+/// an emitter the direction happens to find was already counted by direct lighting, so its glow is left out
+#[allow(clippy::too_many_arguments)]
 pub fn integrate_indirect_surface_radiance(
+    object: &Object,
     intersection_point: Vector,
+    ω_o: Vector,
     normal: Vector,
     scene: &Scene,
     trace_depth: u32,
-    rng: &mut ThreadRng,
+    wavelength: Option<f32>,
+    rng: &mut Pcg32,
 ) -> Radiance {
-    let mut radiance: Radiance = black_spectra().into();
-    for _ in 0..1 {
-        let ray = Ray::new(intersection_point, even_over_hemisphere(normal, rng));
-        radiance.0 = radiance.0 + dispatch_light_ray(ray, scene, trace_depth, rng).0;
+    let (ω_i, pdf, value) = object.material.sample(&ω_o, &normal, rng);
+    let cosθ_i = ω_i.dot(&normal.unitized());
+    if pdf <= 0. || cosθ_i <= 0. {
+        return black_spectra().into();
     }
-    radiance
+    let ray = Ray::new(intersection_point, ω_i);
+    let incoming = dispatch_light_ray(ray, scene, trace_depth, wavelength, false, rng);
+    ((cosθ_i / pdf) * value * incoming.0).into()
 }
 
 /// this is what is recursed
-/// the ray is given
+/// the ray is given, along with the one wavelength it carries once it has passed through something dispersive
+/// `emission` is whether the glow of an emitter the ray hits counts, which it doesn't when the lights were sampled for already
 pub fn dispatch_light_ray(
     ray: Ray,
     scene: &Scene,
     trace_depth: u32,
-    rng: &mut ThreadRng,
+    mut wavelength: Option<f32>,
+    emission: bool,
+    rng: &mut Pcg32,
 ) -> Radiance {
     let intersection = shoot_ray(ray, scene, scene.max_trace_depth);
     if intersection.is_none() {
         return void_spectra().into();
         // return black_spectra().into();
     }
-    let (object, intersection_point, ω_o, normal) = intersection.unwrap();
+    let (object, intersection_point, ω_o, normal, front_face) = intersection.unwrap();
+    // the back of a one sided surface is just a dark blocker
+    if !front_face && object.material.sidedness() == Sidedness::OneSided {
+        return black_spectra().into();
+    }
+    let emitted = match object.material.emission() {
+        Some(radiance) if emission => radiance.clone(),
+        _ => black_spectra(),
+    };
+
+    // smooth surfaces only pass on what's in the one direction they reflect or refract
+    let specular = match object.material.specular_reflection(&ω_o, &normal) {
+        Some(reflection) => Some(reflection),
+        None => object
+            .material
+            .specular_scatter(&ω_o, &normal, front_face, &mut wavelength, rng),
+    };
+    if let Some((ω_i, weight)) = specular {
+        if !scene.recursive_raycasting || trace_depth == 0 {
+            return emitted.into();
+        }
+        // the point is already nudged off the side the ray came from, refracted rays start from the other
+        let origin = if ω_i.dot(&normal) < 0. {
+            intersection_point - normal.unitized().times(2e-5)
+        } else {
+            intersection_point
+        };
+        let passed_on = dispatch_light_ray(
+            Ray::new(origin, ω_i),
+            scene,
+            trace_depth - 1,
+            wavelength,
+            true,
+            rng,
+        );
+        return (emitted + weight * passed_on.0).into();
+    }
 
     // direct illumination
     // this is basically integrating incoming light to our point
@@ -194,6 +222,7 @@ pub fn dispatch_light_ray(
         ω_o,
         normal,
         trace_depth,
+        rng,
     );
     // direct_illumination
 
@@ -206,39 +235,44 @@ pub fn dispatch_light_ray(
         // ));
 
         let indirect_illumination = integrate_indirect_surface_radiance(
+            object,
             intersection_point,
+            ω_o,
             normal,
             scene,
             trace_depth - 1,
+            wavelength,
             rng,
         );
-        (direct_illumination.0 + indirect_illumination.0).into()
+        (emitted + direct_illumination.0 + indirect_illumination.0).into()
     } else {
-        direct_illumination.0.into()
+        (emitted + direct_illumination.0).into()
     }
 }
 
 /// shoots a ray to every light from our point to compute illumination
 /// the reason this returns radiant exitance is because we know the size of the light sources
 /// not proper recursive ray tracing
+/// emitting meshes are lit from one point picked on them, weighted by how likely that point was to be picked
 pub fn integrate_direct_surface_radiance(
     scene: &Scene,
-    object: Object,
+    object: &Object,
     intersection_point: Vector,
     direction: Vector,
     normal: Vector,
     _trace_depth: u32,
+    rng: &mut Pcg32,
 ) -> Radiance {
     let mut output: Radiance = void_spectra().into();
     // let mut output: RadiantExitance = black_spectra().into();
-    'lights: for light in scene.simple_lights() {
+    'lights: for (light_position, light) in scene.simple_lights() {
         // our job here is to find the amount of energy transmitted to the pixel from the light
-        let to_light = &intersection_point.clone().to(light.get_position());
+        let to_light = &intersection_point.clone().to(light_position);
 
         let occlusion_ray = Ray::new(intersection_point, *to_light);
 
-        let occlusion = shoot_ray(occlusion_ray.clone(), scene, _trace_depth);
-        if occlusion.is_some() {
+        // only things between the point and the light can shadow it
+        if occluded(&occlusion_ray, scene, to_light.magnitude()) {
             // this is where a recursive ray would begin
             continue 'lights;
         }
@@ -270,86 +304,171 @@ pub fn integrate_direct_surface_radiance(
 
         output.0 = output.0 + radiance.0;
     }
+
+    let Some(light) = acceleration_structure(scene)
+        .lights
+        .sample(intersection_point, rng)
+    else {
+        return output;
+    };
+    let cosθ_i = light.direction.dot(&normal.unitized());
+    // stopping just short, so the light doesn't shadow itself
+    let occlusion_ray = Ray::new(intersection_point, light.direction);
+    if cosθ_i <= 0. || occluded(&occlusion_ray, scene, light.distance * (1. - 1e-4)) {
+        return output;
+    }
+    let reflected = object
+        .material
+        .evaluate(&light.direction, &direction, &normal);
+    output.0 = output.0 + (cosθ_i / light.pdf) * reflected * light.radiance;
     output
 }
 
-/// given a ray in the scene, see what it hits if anything
-pub fn shoot_ray(ray: Ray, scene: &Scene, _depth: u32) -> Option<(Object, Vector, Vector, Vector)> {
-    let mut hit = false;
-    let mut closest_dist: f32 = 1e6;
-    let mut closest_object: &Object = &Object::default();
-    let mut surface_normal: Vector;
-    surface_normal = Vector::new(1., 1., 1.);
-    // here we're at once per pixel
-    for object in scene.objects() {
-        // this is once per object
-        if scene.spatial_acceleration_structures && !object.ray_intercept(&ray) {
-            continue;
-        }
-        for mesh in &object.meshes {
-            // once per mesh
-            for poly in mesh.polygons.clone() {
-                let a = mesh.output_vertices[poly[0]].clone();
-                let b = mesh.output_vertices[poly[1]].clone();
-                let c = mesh.output_vertices[poly[2]].clone();
-                let polygon = Polygon::new(a, b, c);
-                let (b, _i, dist) = probe_ray_polygon_intersection(&ray, &polygon);
-                if b && dist < closest_dist {
-                    closest_object = object;
-                    closest_dist = dist;
-                    hit = true;
-                    surface_normal = polygon.get_normal();
-                    if surface_normal.dot(&ray.direction) > 0. {
-                        println!(
-                            "polygon is facing away! This shouldn't register as an intersection!"
-                        );
-                        panic!();
-                    }
-                }
-            }
-        }
-    }
+/// the closest polygon along the ray and the object it belongs to
+pub fn closest_intersection<'a>(
+    ray: &Ray,
+    scene: &'a Scene,
+    max_dist: f32,
+) -> Option<(&'a Object, Intersection)> {
+    let intersection = acceleration_structure(scene).closest_hit(ray, max_dist)?;
+    let object = scene.get(intersection.owner).as_object().unwrap();
+    Some((object, intersection))
+}
 
-    if hit {
-        let mut direction: Vector = ray.direction;
-        direction.unitize();
-        direction = closest_dist * direction; // explicitly not a unit vector
-        let mut intersection_point: Vector = direction + ray.position;
-        let to_camera = -1. * direction;
-        // to prevent shader acne
-        let mut offset = surface_normal;
-        offset.unitize();
-        offset = 1e-5 * offset;
-        intersection_point = intersection_point + offset;
-
-        Some((
-            closest_object.clone(),
-            intersection_point,
-            to_camera,
-            surface_normal,
-        ))
-    } else {
-        None
-    }
+/// whether anything lies along the ray before max_dist, stops at the first hit
+pub fn occluded(ray: &Ray, scene: &Scene, max_dist: f32) -> bool {
+    acceleration_structure(scene).occluded(ray, max_dist)
+}
+
+fn acceleration_structure(scene: &Scene) -> &InstanceHierarchy {
+    scene
+        .acceleration_structure
+        .as_ref()
+        .expect("the pipeline builds the acceleration structure before casting rays")
+}
+
+/// given a ray in the scene, see what it hits if anything
+/// back face hits come with the normal already flipped toward the ray, and false as the last element
+pub fn shoot_ray(
+    ray: Ray,
+    scene: &Scene,
+    _depth: u32,
+) -> Option<(&Object, Vector, Vector, Vector, bool)> {
+    let (closest_object, intersection) = closest_intersection(&ray, scene, 1e6)?;
+    let closest_dist = intersection.dist;
+    let surface_normal = intersection.normal;
+    let mut direction: Vector = ray.direction;
+    direction.unitize();
+    direction = closest_dist * direction; // explicitly not a unit vector
+    let mut intersection_point: Vector = direction + ray.position;
+    let to_camera = -1. * direction;
+    // to prevent shader acne
+    let mut offset = surface_normal;
+    offset.unitize();
+    offset = 1e-5 * offset;
+    intersection_point = intersection_point + offset;
+
+    Some((
+        closest_object,
+        intersection_point,
+        to_camera,
+        surface_normal,
+        intersection.front_face,
+    ))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use approx::assert_abs_diff_eq;
 
     use crate::{
         camera::{Camera, Lens, Sensor},
+        geometry::primitives::{Mesh, Vertex},
+        lighting::const_spectra,
+        material::{emissive::Emissive, Diffuse},
         scene::scenes::cornell_scene,
     };
 
     use super::*;
 
+    /// a white floor under a glowing 2 by 2 panel, one up
+    fn panel_scene() -> Scene {
+        let plane = |y: f32, size: f32| {
+            Mesh::new(
+                vec![
+                    Vertex::new(-size, y, -size),
+                    Vertex::new(size, y, -size),
+                    Vertex::new(size, y, size),
+                    Vertex::new(-size, y, size),
+                ],
+                vec![vec![0, 2, 1], vec![0, 3, 2]],
+            )
+        };
+        let mut scene = Scene::default();
+        scene.push_object(Object {
+            meshes: vec![plane(0., 10.)],
+            material: Arc::new(Diffuse {
+                albedo: const_spectra(1.),
+                ..Diffuse::default()
+            }),
+            ..Object::default()
+        });
+        scene.push_object(Object {
+            meshes: vec![plane(1., 1.)],
+            material: Arc::new(Emissive {
+                radiance: const_spectra(3.),
+                ..Emissive::default()
+            }),
+            ..Object::default()
+        });
+        scene.acceleration_structure = Some(InstanceHierarchy::build(&scene));
+        scene
+    }
+
+    #[test]
+    fn white_surfaces_give_back_what_a_panel_shines_on_them() {
+        let scene = panel_scene();
+        let floor = scene.objects().next().unwrap();
+        let up = Vector::new(0., 1., 0.);
+        let mut rng = Pcg32::new(9, 3);
+        let samples = 4000;
+        let mut total = 0.;
+        for _ in 0..samples {
+            let lit = integrate_direct_surface_radiance(
+                &scene,
+                floor,
+                Vector::new(0., 1e-5, 0.),
+                Vector::new(0.5, 1., 0.2),
+                up,
+                0,
+                &mut rng,
+            );
+            total += lit.0.from_λ(550.);
+        }
+        // the panel's form factor from right under it, four corner rectangles as wide as they are high
+        // each (1/2π) 2 (1/√2) atan(1/√2)
+        let form_factor = 4. / PI * (0.5f32).sqrt() * (0.5f32).sqrt().atan();
+        assert_abs_diff_eq!(total / samples as f32, 3. * form_factor, epsilon = 0.03);
+    }
+
+    #[test]
+    fn emitters_glow_only_where_they_were_not_sampled_already() {
+        let scene = panel_scene();
+        let mut rng = Pcg32::new(2, 2);
+        let up = Ray::new(Vector::new(0., 0.5, 0.), Vector::new(0.1, 1., 0.));
+        let seen = dispatch_light_ray(up.clone(), &scene, 0, None, true, &mut rng);
+        assert_eq!(seen.0, const_spectra(3.));
+        let bounced = dispatch_light_ray(up, &scene, 0, None, false, &mut rng);
+        assert_eq!(bounced.0, black_spectra());
+    }
+
     /// useful table: https://www.nikonians.org/reviews/fov-tables
     #[test]
     fn pixel_rays() {
-        let mut scene = cornell_scene();
+        let mut scene = cornell_scene().unwrap();
         let mut ray: Ray;
-        let mut rng = thread_rng();
 
         let lens = Lens {
             focal_length: 18.,
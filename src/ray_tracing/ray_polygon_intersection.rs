@@ -9,140 +9,132 @@ pub fn _ray_polygon_intersection_test(ray: &Ray, polygon: &Polygon) -> bool {
     b
 }
 
-/// returns whether it intersects, the ray, and the distance
+/// where a ray crosses a triangle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TriangleHit {
+    /// distance along the ray, in multiples of the ray direction
+    pub dist: f32,
+    /// weights of the a, b and c corners, they sum to one
+    pub barycentric: (f32, f32, f32),
+    /// true when the ray arrives against the winding order normal
+    pub front_face: bool,
+}
+
+/// returns whether it intersects, the intersection point, and the distance
+/// back faces are treated as misses, use `ray_triangle_intersection` to see them
 #[allow(non_snake_case)]
 pub fn probe_ray_polygon_intersection(ray: &Ray, polygon: &Polygon) -> (bool, Vector, f32) {
-    // first, if the ray is parallel to the plane the polygon lies in, they do not intersect
-    // you can also discard backfacing normals
-
-    // then, find the intersection point
-    // convert it from cartesian coordinates to barycentric coordinates
-
-    // simple culling from there
-
-    // ''' this function determines intersection and returns the barycentric
-    // coordinates of the intersection point if there is a hit
-    let result = ray_plane_intersection(ray, polygon);
-    if result.is_none() {
-        return (false, Vector::new(0., 0., 0.), 0.);
-    }
-
-    let (I, dist) = result.unwrap();
-
-    if I.is_origin() {
-        return (false, I, dist);
+    match ray_triangle_intersection(ray, polygon) {
+        Some(hit) if hit.front_face => {
+            let I = ray.position + hit.dist * ray.direction;
+            (true, I, hit.dist)
+        }
+        _ => (false, Vector::new(0., 0., 0.), 0.),
     }
-    // A, B, C = points
-    let A = polygon.a.as_vector();
-
-    let B = polygon.b.as_vector();
-
-    let C = polygon.c.as_vector();
-
-    // AB = B - A
-    let AB = B.minus(&A);
-    // CB = B - C
-    let CB = B.minus(&C);
-    // v = AB - project_vector(CB, AB)
-    let v = AB.minus(&project_vector(&CB, &AB));
-    // AI = I - A
-    let AI = I.minus(&A);
-    // a is the barycentric coordinate component, if 0, I is at A, if bigger than one, it's outside the triangle
-    let a = 1. - (v.dot(&AI) / v.dot(&AB));
-
-    // BC = C - B  # the mother line
-    let BC = C.minus(&B);
-    // AC = C - A
-    let AC = C.minus(&A);
-
-    // v = BC - project_vector(AC, BC)
-    let v = BC.minus(&project_vector(&AC, &BC));
-
-    // BI = I - B
-    let BI = I.minus(&B);
-
-    // barycentric coordinate for b
-    // b = 1 - (dot(v, BI) / dot(v, BC))
-    let b = 1. - (v.dot(&BI) / v.dot(&BC));
-
-    // CA = A - C  # motherline
-    let CA = A.minus(&C);
-
-    // BA = A - B
-    let BA = A.minus(&B);
-
-    // finding the CB instead of the CA at the star of the expression took an hour
-    // v = CA - project_vector(BA, CA)
-    let v = CA.minus(&project_vector(&BA, &CA));
-
-    // CI = I - C
-    let CI = I.minus(&C);
+}
 
-    // barycentric coordinate for c
-    // c = 1 - (dot(v, CI) / dot(v, CA))
-    let c = 1. - (v.dot(&CI) / v.dot(&CA));
+/// watertight ray triangle intersection
+/// Woop, Benthin and Wald 2013 <https://jcgt.org/published/0002/01/05/>
+///
+/// the triangle is sheared into a space where the ray runs down the z axis from the origin,
+/// so the test becomes three 2D edge functions with no division until the very end.
+/// edges shared by two triangles are evaluated identically from both sides,
+/// which means a ray can't slip through the crack between them.
+/// both faces are reported, degenerate triangles never are
+#[allow(non_snake_case)]
+pub fn ray_triangle_intersection(ray: &Ray, polygon: &Polygon) -> Option<TriangleHit> {
+    let direction = &ray.direction;
 
-    //     return False
-    if a < 0. || b < 0. || c < 0. {
-        return (false, I, dist);
+    // the dimension the ray travels furthest in becomes z
+    let abs = Vector::new(direction.x.abs(), direction.y.abs(), direction.z.abs());
+    let kz = if abs.x > abs.y && abs.x > abs.z {
+        0
+    } else if abs.y > abs.z {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    // keeps the winding order, and so the sign of the edge functions, intact
+    if direction.axis(kz) < 0. {
+        std::mem::swap(&mut kx, &mut ky);
     }
-    if a > 1. || b > 1. || c > 1. {
-        return (false, I, dist);
+    let dz = direction.axis(kz);
+    if dz == 0. {
+        return None; // only a zero length direction gets here
     }
+    let Sx = direction.axis(kx) / dz;
+    let Sy = direction.axis(ky) / dz;
+    let Sz = 1. / dz;
 
-    (true, I, dist)
-}
+    let A = polygon.a.position - ray.position;
+    let B = polygon.b.position - ray.position;
+    let C = polygon.c.position - ray.position;
 
-fn project_vector(a: &Vector, b: &Vector) -> Vector {
-    a.times(a.dot(b) / a.dot(a))
-}
+    let Ax = A.axis(kx) - Sx * A.axis(kz);
+    let Ay = A.axis(ky) - Sy * A.axis(kz);
+    let Bx = B.axis(kx) - Sx * B.axis(kz);
+    let By = B.axis(ky) - Sy * B.axis(kz);
+    let Cx = C.axis(kx) - Sx * C.axis(kz);
+    let Cy = C.axis(ky) - Sy * C.axis(kz);
 
-#[allow(non_snake_case)]
-fn ray_plane_intersection(ray: &Ray, polygon: &Polygon) -> Option<(Vector, f32)> {
-    let ray_origin = &ray.position;
-    let ray: &Vector = &ray.direction;
+    let mut U = Cx * By - Cy * Bx;
+    let mut V = Ax * Cy - Ay * Cx;
+    let mut W = Bx * Ay - By * Ax;
 
-    let N = polygon.get_normal();
+    // exactly on an edge, single precision can't decide which side so double precision does
+    if U == 0. || V == 0. || W == 0. {
+        let (Ax, Ay, Bx, By, Cx, Cy) = (
+            Ax as f64, Ay as f64, Bx as f64, By as f64, Cx as f64, Cy as f64,
+        );
+        U = (Cx * By - Cy * Bx) as f32;
+        V = (Ax * Cy - Ay * Cx) as f32;
+        W = (Bx * Ay - By * Ax) as f32;
+    }
 
-    if ray.dot(&N) > 0. {
+    // the signs have to agree for the ray to be inside all three edges
+    if (U < 0. || V < 0. || W < 0.) && (U > 0. || V > 0. || W > 0.) {
         return None;
     }
-    if ray.dot(&N).abs() <= 1e-4 {
-        return None;
+    let det = U + V + W;
+    if det == 0. {
+        return None; // degenerate, or seen exactly edge on
     }
-    // if abs(dot(ray, N)) <= 1e-4:
-    //     # print('the plane normal and ray are at right angles, no intersection is possible')
-
-    // C is defined as a point that lies on the plane
-    let C: Vector = polygon.a.as_vector();
-    // V = ray
-    let V = &ray;
 
-    // W = C - ray_origin
-    let W = C.minus(ray_origin);
-    let k: f32 = W.dot(&N) / V.dot(&N);
-    // k is the multiplier with the ray to reach I from the ray_origin
-    // if the ray is a unit vector it is the distance from ray_origin to the intersection
+    let Az = Sz * A.axis(kz);
+    let Bz = Sz * B.axis(kz);
+    let Cz = Sz * C.axis(kz);
+    let T = U * Az + V * Bz + W * Cz;
 
-    // I = ray_origin + k * V
-    let I = *ray_origin + V.times(k);
-    // if k < 0  ray is facing away from plane
-    if k < 0. {
+    // behind the origin if T and det disagree in sign
+    if T * det <= 0. {
         return None;
     }
-    // if k == 0 ray is on plane I think
-    if k == 0. {
-        return None;
-    }
-    Some((I, k))
-    // returns the intersection point
+
+    let inverse_det = 1. / det;
+    let dist = T * inverse_det;
+    let barycentric = (U * inverse_det, V * inverse_det, W * inverse_det);
+    let normal =
+        (polygon.b.position - polygon.a.position).cross(&(polygon.c.position - polygon.a.position));
+    let front_face = direction.dot(&normal) < 0.;
+    Some(TriangleHit {
+        dist,
+        barycentric,
+        front_face,
+    })
 }
 
 #[cfg(test)]
 mod tests {
+    use approx::assert_abs_diff_eq;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
     use crate::{
         geometry::primitives::{Polygon, Ray, Vector, Vertex},
-        ray_tracing::ray_polygon_intersection::probe_ray_polygon_intersection,
+        ray_tracing::ray_polygon_intersection::{
+            probe_ray_polygon_intersection, ray_triangle_intersection,
+        },
     };
 
     /// useful table: https://www.nikonians.org/reviews/fov-tables
@@ -188,4 +180,165 @@ mod tests {
         );
         assert!(probe_ray_polygon_intersection(&ray, &polygon).0);
     }
+
+    fn random_point(rng: &mut StdRng, range: f32) -> Vector {
+        Vector::new(
+            rng.gen_range(-range..range),
+            rng.gen_range(-range..range),
+            rng.gen_range(-range..range),
+        )
+    }
+
+    fn triangle(a: Vector, b: Vector, c: Vector) -> Polygon {
+        Polygon::new(
+            Vertex::new(a.x, a.y, a.z),
+            Vertex::new(b.x, b.y, b.z),
+            Vertex::new(c.x, c.y, c.z),
+        )
+    }
+
+    /// the old intersector treated a hit at the world origin as a miss
+    #[test]
+    fn geometry_through_the_origin() {
+        let polygon = triangle(
+            Vector::new(-1., -1., 0.),
+            Vector::new(1., -1., 0.),
+            Vector::new(0., 1., 0.),
+        );
+        let ray = Ray::new(Vector::new(0., 0., 5.), Vector::new(0., 0., -1.));
+        let hit = ray_triangle_intersection(&ray, &polygon).unwrap();
+        assert_abs_diff_eq!(hit.dist, 5.);
+        assert!(hit.front_face);
+    }
+
+    #[test]
+    fn back_faces_are_reported() {
+        let polygon = triangle(
+            Vector::new(-1., -1., 0.),
+            Vector::new(0., 1., 0.),
+            Vector::new(1., -1., 0.),
+        );
+        let ray = Ray::new(Vector::new(0., 0., 5.), Vector::new(0., 0., -1.));
+        let hit = ray_triangle_intersection(&ray, &polygon).unwrap();
+        assert!(!hit.front_face);
+        assert!(!probe_ray_polygon_intersection(&ray, &polygon).0);
+    }
+
+    /// a ray through any point of a triangle hits it where it was aimed
+    #[test]
+    fn barycentrics_reconstruct_the_hit() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..10_000 {
+            let (a, b, c) = (
+                random_point(&mut rng, 10.),
+                random_point(&mut rng, 10.),
+                random_point(&mut rng, 10.),
+            );
+            let polygon = triangle(a, b, c);
+            let (mut u, mut v) = (rng.gen_range(0.01..0.99), rng.gen_range(0.01..0.99));
+            if u + v > 1. {
+                (u, v) = (1. - u, 1. - v);
+            }
+            let target = a + u * (b - a) + v * (c - a);
+            let origin = random_point(&mut rng, 30.);
+            let ray = Ray::new(origin, target - origin);
+            let Some(hit) = ray_triangle_intersection(&ray, &polygon) else {
+                continue; // nearly edge on triangles can legitimately be missed
+            };
+            let (wa, wb, wc) = hit.barycentric;
+            assert_abs_diff_eq!(wa + wb + wc, 1., epsilon = 1e-4);
+            assert!(wa >= 0. && wb >= 0. && wc >= 0.);
+            let from_barycentrics = wa * a + wb * b + wc * c;
+            let from_distance = origin + hit.dist * ray.direction;
+            assert_abs_diff_eq!(from_barycentrics.x, from_distance.x, epsilon = 1e-2);
+            assert_abs_diff_eq!(from_barycentrics.y, from_distance.y, epsilon = 1e-2);
+            assert_abs_diff_eq!(from_barycentrics.z, from_distance.z, epsilon = 1e-2);
+        }
+    }
+
+    /// rays aimed at a shared edge must hit at least one of the two triangles
+    #[test]
+    fn shared_edges_are_watertight() {
+        let mut rng = StdRng::seed_from_u64(2);
+        for _ in 0..10_000 {
+            let (a, b, c, d) = (
+                random_point(&mut rng, 10.),
+                random_point(&mut rng, 10.),
+                random_point(&mut rng, 10.),
+                random_point(&mut rng, 10.),
+            );
+            // abc and adb share the edge ab and are wound consistently
+            let first = triangle(a, b, c);
+            let second = triangle(a, d, b);
+            let on_edge = a + rng.gen_range(0.0..1.0) * (b - a);
+            let origin = random_point(&mut rng, 30.);
+            let ray = Ray::new(origin, on_edge - origin);
+            let hits = [&first, &second]
+                .iter()
+                .filter(|t| ray_triangle_intersection(&ray, t).is_some())
+                .count();
+            // c and d on the same side of the edge from the ray's view can fold the pair over itself,
+            // only an unfolded pair is guaranteed to cover the edge
+            let folded = {
+                let normal_first = (b - a).cross(&(c - a));
+                let normal_second = (d - a).cross(&(b - a));
+                normal_first.dot(&ray.direction).signum()
+                    != normal_second.dot(&ray.direction).signum()
+            };
+            if !folded {
+                assert!(hits >= 1, "ray slipped between {first:?} and {second:?}");
+            }
+        }
+    }
+
+    /// a fan of triangles around a vertex, rays at the vertex have to hit one of them
+    #[test]
+    fn shared_vertices_are_watertight() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..2_000 {
+            let center = random_point(&mut rng, 5.);
+            let sides = 6;
+            let rim: Vec<Vector> = (0..sides)
+                .map(|i| {
+                    let θ = i as f32 / sides as f32 * std::f32::consts::TAU;
+                    center + Vector::new(θ.cos(), θ.sin(), 0.)
+                })
+                .collect();
+            let fan: Vec<Polygon> = (0..sides)
+                .map(|i| triangle(center, rim[i], rim[(i + 1) % sides]))
+                .collect();
+            let origin = center
+                + Vector::new(
+                    rng.gen_range(-3.0..3.0),
+                    rng.gen_range(-3.0..3.0),
+                    rng.gen_range(0.5..5.0),
+                );
+            let ray = Ray::new(origin, center - origin);
+            assert!(fan
+                .iter()
+                .any(|t| ray_triangle_intersection(&ray, t).is_some()));
+        }
+    }
+
+    #[test]
+    fn degenerate_triangles_never_hit() {
+        let mut rng = StdRng::seed_from_u64(4);
+        for _ in 0..10_000 {
+            let a = random_point(&mut rng, 10.);
+            let b = random_point(&mut rng, 10.);
+            let repeated = triangle(a, a, b);
+            let point = triangle(a, a, a);
+            let origin = random_point(&mut rng, 30.);
+            let ray = Ray::new(origin, a + 0.25 * (b - a) - origin);
+            for polygon in [repeated, point] {
+                assert!(ray_triangle_intersection(&ray, &polygon).is_none());
+            }
+            // a sliver may round to a hit, but never to garbage
+            let collinear = triangle(a, b, a + 0.5 * (b - a));
+            if let Some(hit) = ray_triangle_intersection(&ray, &collinear) {
+                assert!(hit.dist.is_finite() && hit.dist > 0.);
+                assert!(hit.barycentric.0.is_finite());
+            }
+        }
+    }
 }
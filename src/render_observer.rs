@@ -0,0 +1,368 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use image::{ImageError, ImageFormat, RgbImage};
+
+use crate::geometry_pipeline::Tile;
+
+/// how far through its tiles a render is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progress {
+    pub completed: usize,
+    pub total: usize,
+}
+
+impl Progress {
+    /// 0 to 1
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.
+        } else {
+            self.completed as f32 / self.total as f32
+        }
+    }
+
+    /// how much longer the remaining tiles should take if they go as quickly as the finished ones did
+    /// None until there is something to go on
+    pub fn remaining(&self, elapsed: Duration) -> Option<Duration> {
+        if self.completed == 0 {
+            return None;
+        }
+        let left = self.total.saturating_sub(self.completed) as f64;
+        Some(elapsed.mul_f64(left / self.completed as f64))
+    }
+}
+
+/// told what a render is doing while it happens
+/// every event is sent from the thread that called the pipeline, in order:
+/// `started`, then `tile_completed` and `progress` for each tile, then `finished`
+/// rasterized renders have no tiles, so they only start and finish
+pub trait RenderObserver {
+    fn started(&mut self, _width: u32, _height: u32) {}
+    /// `canvas` is the whole image so far, with the tile already copied into it
+    fn tile_completed(&mut self, _tile: &Tile, _canvas: &RgbImage) {}
+    fn progress(&mut self, _progress: Progress) {}
+    fn finished(&mut self, _canvas: &RgbImage, _elapsed: Duration) {}
+}
+
+/// hears nothing
+pub struct Silent;
+
+impl RenderObserver for Silent {}
+
+impl<O: RenderObserver + ?Sized> RenderObserver for Box<O> {
+    fn started(&mut self, width: u32, height: u32) {
+        (**self).started(width, height);
+    }
+    fn tile_completed(&mut self, tile: &Tile, canvas: &RgbImage) {
+        (**self).tile_completed(tile, canvas);
+    }
+    fn progress(&mut self, progress: Progress) {
+        (**self).progress(progress);
+    }
+    fn finished(&mut self, canvas: &RgbImage, elapsed: Duration) {
+        (**self).finished(canvas, elapsed);
+    }
+}
+
+impl<O: RenderObserver + ?Sized> RenderObserver for &mut O {
+    fn started(&mut self, width: u32, height: u32) {
+        (**self).started(width, height);
+    }
+    fn tile_completed(&mut self, tile: &Tile, canvas: &RgbImage) {
+        (**self).tile_completed(tile, canvas);
+    }
+    fn progress(&mut self, progress: Progress) {
+        (**self).progress(progress);
+    }
+    fn finished(&mut self, canvas: &RgbImage, elapsed: Duration) {
+        (**self).finished(canvas, elapsed);
+    }
+}
+
+/// every observer in turn hears every event
+impl<O: RenderObserver> RenderObserver for Vec<O> {
+    fn started(&mut self, width: u32, height: u32) {
+        for observer in self {
+            observer.started(width, height);
+        }
+    }
+    fn tile_completed(&mut self, tile: &Tile, canvas: &RgbImage) {
+        for observer in self {
+            observer.tile_completed(tile, canvas);
+        }
+    }
+    fn progress(&mut self, progress: Progress) {
+        for observer in self {
+            observer.progress(progress);
+        }
+    }
+    fn finished(&mut self, canvas: &RgbImage, elapsed: Duration) {
+        for observer in self {
+            observer.finished(canvas, elapsed);
+        }
+    }
+}
+
+/// writes the image so far to a file as tiles come in, at most once per interval, and once more at the end
+pub struct ProgressiveFile {
+    pub path: PathBuf,
+    pub format: ImageFormat,
+    pub interval: Duration,
+    last_write: Option<Instant>,
+    /// a render can't be stopped by a file that won't write, so the latest failure waits here
+    pub error: Option<ImageError>,
+}
+
+impl ProgressiveFile {
+    pub fn new(
+        path: impl Into<PathBuf>,
+        format: ImageFormat,
+        interval: Duration,
+    ) -> ProgressiveFile {
+        ProgressiveFile {
+            path: path.into(),
+            format,
+            interval,
+            last_write: None,
+            error: None,
+        }
+    }
+
+    fn write(&mut self, canvas: &RgbImage) {
+        self.last_write = Some(Instant::now());
+        if let Err(error) = canvas.save_with_format(&self.path, self.format) {
+            self.error = Some(error);
+        }
+    }
+}
+
+impl RenderObserver for ProgressiveFile {
+    fn tile_completed(&mut self, _tile: &Tile, canvas: &RgbImage) {
+        let due = self
+            .last_write
+            .is_none_or(|last| last.elapsed() >= self.interval);
+        if due {
+            self.write(canvas);
+        }
+    }
+    fn finished(&mut self, canvas: &RgbImage, _elapsed: Duration) {
+        self.write(canvas);
+    }
+}
+
+/// a bar redrawn in place on a terminal, with how long is left
+pub struct ProgressBar<W: Write = io::Stderr> {
+    out: W,
+    /// in characters, not counting the counts and times after it
+    pub width: usize,
+    started: Instant,
+    /// whether there's a bar on the line to move past at the end
+    drawn: bool,
+}
+
+impl ProgressBar {
+    /// draws to stderr, so it stays out of anything piped from stdout
+    pub fn new() -> ProgressBar {
+        ProgressBar::to(io::stderr())
+    }
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        ProgressBar::new()
+    }
+}
+
+impl<W: Write> ProgressBar<W> {
+    pub fn to(out: W) -> ProgressBar<W> {
+        ProgressBar {
+            out,
+            width: 40,
+            started: Instant::now(),
+            drawn: false,
+        }
+    }
+
+    pub fn writer(&self) -> &W {
+        &self.out
+    }
+}
+
+impl<W: Write> RenderObserver for ProgressBar<W> {
+    fn started(&mut self, _width: u32, _height: u32) {
+        self.started = Instant::now();
+        self.drawn = false;
+    }
+    fn progress(&mut self, progress: Progress) {
+        let filled = (progress.fraction() * self.width as f32).round() as usize;
+        let remaining = progress
+            .remaining(self.started.elapsed())
+            .map_or("?".to_string(), |remaining| {
+                format!("{:.1}s", remaining.as_secs_f32())
+            });
+        // a terminal that has gone away shouldn't stop the render
+        let _ = write!(
+            self.out,
+            "\r[{}{}] {}/{} eta {remaining} ",
+            "#".repeat(filled),
+            "-".repeat(self.width - filled),
+            progress.completed,
+            progress.total,
+        );
+        let _ = self.out.flush();
+        self.drawn = true;
+    }
+    fn finished(&mut self, _canvas: &RgbImage, elapsed: Duration) {
+        if self.drawn {
+            let _ = writeln!(self.out);
+        }
+        let _ = writeln!(self.out, "rendered in {:.2}s", elapsed.as_secs_f32());
+    }
+}
+
+/// the events as they're sent over a channel, for applications that show the render themselves
+#[derive(Clone, Debug)]
+pub enum RenderEvent {
+    Started { width: u32, height: u32 },
+    TileCompleted(Tile),
+    Progress(Progress),
+    Finished { image: RgbImage, elapsed: Duration },
+}
+
+/// sends every event on, a receiver that has gone away is no trouble
+pub struct ChannelSink {
+    sender: Sender<RenderEvent>,
+}
+
+impl ChannelSink {
+    pub fn new() -> (ChannelSink, Receiver<RenderEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        (ChannelSink { sender }, receiver)
+    }
+}
+
+impl RenderObserver for ChannelSink {
+    fn started(&mut self, width: u32, height: u32) {
+        let _ = self.sender.send(RenderEvent::Started { width, height });
+    }
+    fn tile_completed(&mut self, tile: &Tile, _canvas: &RgbImage) {
+        let _ = self.sender.send(RenderEvent::TileCompleted(tile.clone()));
+    }
+    fn progress(&mut self, progress: Progress) {
+        let _ = self.sender.send(RenderEvent::Progress(progress));
+    }
+    fn finished(&mut self, canvas: &RgbImage, elapsed: Duration) {
+        let _ = self.sender.send(RenderEvent::Finished {
+            image: canvas.clone(),
+            elapsed,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use image::{ImageBuffer, ImageFormat, RgbImage};
+
+    use crate::geometry_pipeline::{render_observed, Tile};
+    use crate::scene::scene::{Rendermode, Scene};
+
+    use super::{ChannelSink, Progress, ProgressBar, ProgressiveFile, RenderEvent, RenderObserver};
+
+    #[test]
+    fn progress_estimates_what_is_left() {
+        let progress = Progress {
+            completed: 1,
+            total: 4,
+        };
+        assert_eq!(progress.fraction(), 0.25);
+        assert_eq!(
+            progress.remaining(Duration::from_secs(2)),
+            Some(Duration::from_secs(6))
+        );
+        let none_yet = Progress {
+            completed: 0,
+            total: 4,
+        };
+        assert_eq!(none_yet.remaining(Duration::from_secs(2)), None);
+    }
+
+    #[test]
+    fn every_tile_is_heard_about() {
+        let mut scene = Scene::default();
+        scene.rendermode = Rendermode::ThreadedRayTrace;
+        scene.tilesize = 4;
+        scene.active_camera_mut().sensor.horizontal_res = 10;
+        scene.active_camera_mut().sensor.vertical_res = 8;
+        let (mut sink, events) = ChannelSink::new();
+        let film = render_observed(&scene, &mut sink);
+        drop(sink);
+
+        let events: Vec<RenderEvent> = events.into_iter().collect();
+        assert!(matches!(
+            events.first(),
+            Some(RenderEvent::Started {
+                width: 10,
+                height: 8
+            })
+        ));
+        let Some(RenderEvent::Finished { image, .. }) = events.last() else {
+            panic!("the render should finish last");
+        };
+        assert_eq!(image, film.image());
+        // 3 across and 2 down
+        let tiles = events
+            .iter()
+            .filter(|event| matches!(event, RenderEvent::TileCompleted(_)))
+            .count();
+        assert_eq!(tiles, 6);
+        let last_progress = events.iter().rev().find_map(|event| match event {
+            RenderEvent::Progress(progress) => Some(*progress),
+            _ => None,
+        });
+        assert_eq!(
+            last_progress,
+            Some(Progress {
+                completed: 6,
+                total: 6
+            })
+        );
+    }
+
+    #[test]
+    fn bars_and_files_keep_up() {
+        let canvas: RgbImage = ImageBuffer::new(4, 4);
+        let tile = Tile::new(0, 0, 4, 4);
+
+        let mut bar = ProgressBar::to(Vec::new());
+        bar.width = 4;
+        bar.started(4, 4);
+        bar.progress(Progress {
+            completed: 1,
+            total: 2,
+        });
+        bar.finished(&canvas, Duration::from_millis(1500));
+        let shown = String::from_utf8(bar.writer().clone()).unwrap();
+        assert!(shown.starts_with("\r[##--] 1/2 eta "));
+        assert!(shown.ends_with(" \nrendered in 1.50s\n"));
+
+        let path = std::env::temp_dir().join("graphics_v3_progressive.png");
+        let mut file = ProgressiveFile::new(&path, ImageFormat::Png, Duration::from_secs(60));
+        file.tile_completed(&tile, &canvas);
+        assert!(path.exists());
+        // too soon for another write
+        fs::remove_file(&path).unwrap();
+        file.tile_completed(&tile, &canvas);
+        assert!(!path.exists());
+        // but the end is always written
+        file.finished(&canvas, Duration::ZERO);
+        assert!(path.exists());
+        assert!(file.error.is_none());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,383 @@
+use crate::geometry::primitives::{Mesh, Polygon, Vertex};
+use crate::scene::scene::Scene;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// a mesh along with what to call it in formats that keep names
+#[derive(Clone, Debug)]
+pub struct NamedMesh {
+    pub name: String,
+    pub mesh: Mesh,
+}
+
+/// every object's meshes carried into world space, one named mesh per object
+/// normals follow along, texture coordinates and colors are kept as they are
+/// meant for scenes that haven't been through the geometry pipeline, which adds its own transforms
+pub fn bake_scene(scene: &Scene) -> Vec<NamedMesh> {
+    let mut baked = Vec::new();
+    for (i, key) in scene.get_object_keys().into_iter().enumerate() {
+        let object = scene.get(key).as_object().unwrap();
+        let to_world = scene.world_transform(key);
+        // a zero scale flattens the object, which leaves its normals meaningless
+        let to_object = to_world.inverse();
+        let mut vertices = Vec::new();
+        let mut polygons = Vec::new();
+        for mesh in &object.meshes {
+            let offset = vertices.len();
+            vertices.extend(mesh.vertices.iter().map(|vertex| {
+                Vertex {
+                    position: to_world.apply_to_point(&vertex.position),
+                    normal: vertex
+                        .normal
+                        .zip(to_object.as_ref())
+                        .map(|(normal, to_object)| {
+                            to_object.apply_transposed_to_direction(&normal).unitized()
+                        }),
+                    ..vertex.clone()
+                }
+            }));
+            for polygon in mesh.polygons.iter() {
+                polygons.push(polygon.iter().map(|i| i + offset).collect());
+            }
+        }
+        baked.push(NamedMesh {
+            name: object.name.clone().unwrap_or_else(|| format!("object_{i}")),
+            mesh: Mesh::new(vertices, polygons),
+        });
+    }
+    baked
+}
+
+/// the format is picked by the extension, `.obj`, `.ply` or `.stl`
+pub fn save_mesh(mesh: &Mesh, path: &str) -> io::Result<()> {
+    let named = NamedMesh {
+        name: "mesh".to_string(),
+        mesh: mesh.clone(),
+    };
+    save_meshes(&[named], path)
+}
+
+/// world space geometry of the whole scene, see `bake_scene`
+pub fn save_scene(scene: &Scene, path: &str) -> io::Result<()> {
+    save_meshes(&bake_scene(scene), path)
+}
+
+/// obj keeps every mesh as its own object, ply and stl get them merged into one
+pub fn save_meshes(meshes: &[NamedMesh], path: &str) -> io::Result<()> {
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let write: fn(&mut BufWriter<File>, &[NamedMesh]) -> io::Result<()> = match extension.as_deref()
+    {
+        Some("obj") => |out, meshes| write_obj(out, meshes),
+        Some("ply") => |out, meshes| write_ply(out, &merge(meshes)),
+        Some("stl") => |out, meshes| write_stl(out, &merge(meshes)),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{path}: can only save .obj, .ply or .stl"),
+            ))
+        }
+    };
+    let mut out = BufWriter::new(File::create(path)?);
+    write(&mut out, meshes)?;
+    out.flush()
+}
+
+fn merge(meshes: &[NamedMesh]) -> Mesh {
+    let mut vertices = Vec::new();
+    let mut polygons = Vec::new();
+    for named in meshes {
+        let offset = vertices.len();
+        vertices.extend(named.mesh.vertices.iter().cloned());
+        for polygon in named.mesh.polygons.iter() {
+            polygons.push(polygon.iter().map(|i| i + offset).collect());
+        }
+    }
+    Mesh::new(vertices, polygons)
+}
+
+/// Wavefront obj, an `o` per mesh
+/// normals are written for meshes whose every vertex has one, texture coordinates when any are set
+pub fn write_obj(out: &mut impl Write, meshes: &[NamedMesh]) -> io::Result<()> {
+    // obj counts from 1 across the whole file
+    let mut first_vertex = 1;
+    for NamedMesh { name, mesh } in meshes {
+        writeln!(out, "o {name}")?;
+        let has_uvs = mesh
+            .vertices
+            .iter()
+            .any(|vertex| vertex.uv_coord != (0., 0.));
+        let has_normals = mesh.vertices.iter().all(|vertex| vertex.normal.is_some());
+        for vertex in mesh.vertices.iter() {
+            let p = vertex.position;
+            writeln!(out, "v {} {} {}", p.x, p.y, p.z)?;
+        }
+        if has_uvs {
+            for vertex in mesh.vertices.iter() {
+                let (u, v) = vertex.uv_coord;
+                writeln!(out, "vt {u} {v}")?;
+            }
+        }
+        if has_normals {
+            for vertex in mesh.vertices.iter() {
+                let n = vertex.normal.unwrap();
+                writeln!(out, "vn {} {} {}", n.x, n.y, n.z)?;
+            }
+        }
+        // every list has a line per vertex, so one index serves all three
+        for polygon in mesh.polygons.iter() {
+            write!(out, "f")?;
+            for i in polygon {
+                let i = i + first_vertex;
+                match (has_uvs, has_normals) {
+                    (false, false) => write!(out, " {i}")?,
+                    (true, false) => write!(out, " {i}/{i}")?,
+                    (false, true) => write!(out, " {i}//{i}")?,
+                    (true, true) => write!(out, " {i}/{i}/{i}")?,
+                }
+            }
+            writeln!(out)?;
+        }
+        first_vertex += mesh.vertices.len();
+    }
+    Ok(())
+}
+
+/// binary little endian PLY
+/// normals and colors are written when every vertex has them, texture coordinates when any are set
+pub fn write_ply(out: &mut impl Write, mesh: &Mesh) -> io::Result<()> {
+    let has_uvs = mesh
+        .vertices
+        .iter()
+        .any(|vertex| vertex.uv_coord != (0., 0.));
+    let has_normals = mesh.vertices.iter().all(|vertex| vertex.normal.is_some());
+    let has_colors = mesh.vertices.iter().all(|vertex| vertex.color.is_some());
+
+    writeln!(out, "ply")?;
+    writeln!(out, "format binary_little_endian 1.0")?;
+    writeln!(out, "element vertex {}", mesh.vertices.len())?;
+    for name in ["x", "y", "z"] {
+        writeln!(out, "property float {name}")?;
+    }
+    if has_normals {
+        for name in ["nx", "ny", "nz"] {
+            writeln!(out, "property float {name}")?;
+        }
+    }
+    if has_uvs {
+        for name in ["s", "t"] {
+            writeln!(out, "property float {name}")?;
+        }
+    }
+    if has_colors {
+        for name in ["red", "green", "blue"] {
+            writeln!(out, "property uchar {name}")?;
+        }
+    }
+    // points and lines aren't faces, and a uchar length would wrap past 255 corners
+    let faces: Vec<&Vec<usize>> = mesh
+        .polygons
+        .iter()
+        .filter(|polygon| polygon.len() >= 3)
+        .collect();
+    writeln!(out, "element face {}", faces.len())?;
+    writeln!(out, "property list uint uint vertex_indices")?;
+    writeln!(out, "end_header")?;
+
+    for vertex in mesh.vertices.iter() {
+        let mut floats = vertex.position.as_array().to_vec();
+        if has_normals {
+            floats.extend(vertex.normal.unwrap().as_array());
+        }
+        if has_uvs {
+            floats.extend([vertex.uv_coord.0, vertex.uv_coord.1]);
+        }
+        for value in floats {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        if has_colors {
+            let (r, g, b) = vertex.color.unwrap();
+            let byte = |channel: f32| (channel.clamp(0., 1.) * 255.).round() as u8;
+            out.write_all(&[byte(r), byte(g), byte(b)])?;
+        }
+    }
+    for polygon in faces {
+        out.write_all(&(polygon.len() as u32).to_le_bytes())?;
+        for &i in polygon {
+            out.write_all(&(i as u32).to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// binary STL, polygons with more than three corners are fanned and ones with fewer are left out
+pub fn write_stl(out: &mut impl Write, mesh: &Mesh) -> io::Result<()> {
+    let triangles: Vec<[usize; 3]> = mesh
+        .polygons
+        .iter()
+        .filter(|polygon| polygon.len() >= 3)
+        .flat_map(|polygon| {
+            (1..polygon.len() - 1).map(|i| [polygon[0], polygon[i], polygon[i + 1]])
+        })
+        .collect();
+    // the header is free text, but mustn't start with `solid` or readers take it for ASCII
+    let mut header = b"binary STL".to_vec();
+    header.resize(80, 0);
+    out.write_all(&header)?;
+    out.write_all(&(triangles.len() as u32).to_le_bytes())?;
+    for [a, b, c] in triangles {
+        let [a, b, c] = [a, b, c].map(|i| mesh.vertices[i].clone());
+        let normal = Polygon::new(a.clone(), b.clone(), c.clone()).get_normal();
+        for vector in [normal, a.position, b.position, c.position] {
+            for value in vector.as_array() {
+                out.write_all(&value.to_le_bytes())?;
+            }
+        }
+        out.write_all(&[0, 0])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::{
+        geometry::{orientation::Orientation, primitives::Vector},
+        load_error::Strictness,
+        load_object_file::parse_wavefront_obj,
+        load_ply::{load_ply, parse_ply},
+        load_stl::{load_stl, parse_stl},
+        object::Object,
+        scene::scene::Scene,
+    };
+
+    use super::{bake_scene, save_mesh, save_scene, write_obj, write_ply, write_stl, NamedMesh};
+    use crate::geometry::primitives::{Mesh, Vertex};
+
+    /// a unit square in the xy plane facing +z, painted and textured
+    fn square() -> Mesh {
+        let corners = [(0., 0.), (1., 0.), (1., 1.), (0., 1.)];
+        let vertices = corners
+            .iter()
+            .map(|&(x, y)| Vertex {
+                uv_coord: (x, y),
+                normal: Some(Vector::new(0., 0., 1.)),
+                color: Some((x, y, 1.)),
+                ..Vertex::new(x, y, 0.)
+            })
+            .collect();
+        Mesh::new(vertices, vec![vec![0, 1, 2], vec![0, 2, 3]])
+    }
+
+    /// moved up by 2 and turned so it faces +x
+    fn scene() -> Scene {
+        let mut scene = Scene::default();
+        let mut object = Object {
+            name: Some("square".to_string()),
+            meshes: vec![square()],
+            ..Object::default()
+        };
+        object.position = Vector::new(0., 2., 0.);
+        object.orientation = Orientation::from_euler(0., std::f32::consts::FRAC_PI_2, 0.);
+        scene.push_object(object);
+        scene
+    }
+
+    fn assert_same_square(original: &Mesh, read: &Mesh) {
+        assert_eq!(read.polygons.len(), original.polygons.len());
+        for i in 0..original.polygons.len() {
+            let (a, b) = (original.polygon(i), read.polygon(i));
+            for (a, b) in [(a.a, b.a), (a.b, b.b), (a.c, b.c)] {
+                assert_abs_diff_eq!(a.position.x, b.position.x, epsilon = 1e-5);
+                assert_abs_diff_eq!(a.position.y, b.position.y, epsilon = 1e-5);
+                assert_abs_diff_eq!(a.position.z, b.position.z, epsilon = 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn scenes_are_baked_into_world_space() {
+        let baked = bake_scene(&scene());
+        assert_eq!(baked.len(), 1);
+        assert_eq!(baked[0].name, "square");
+        let mesh = &baked[0].mesh;
+        let normal = mesh.vertices[0].normal.unwrap();
+        let flat = mesh.polygon(0).get_normal();
+        assert_abs_diff_eq!(normal.dot(&flat), 1., epsilon = 1e-5);
+        assert_abs_diff_eq!(mesh.vertices[0].position.y, 2., epsilon = 1e-5);
+        assert_eq!(mesh.vertices[1].color, Some((1., 0., 1.)));
+    }
+
+    #[test]
+    fn round_trips() {
+        let mesh = bake_scene(&scene()).remove(0).mesh;
+
+        let mut ply = Vec::new();
+        write_ply(&mut ply, &mesh).unwrap();
+        let read = parse_ply(&ply, "square.ply").unwrap();
+        assert_same_square(&mesh, &read);
+        assert_eq!(read.vertices[2].uv_coord, (1., 1.));
+        assert_eq!(read.vertices[2].color, Some((1., 1., 1.)));
+        assert_abs_diff_eq!(read.vertices[2].normal.unwrap().x, 1., epsilon = 1e-5);
+
+        let mut stl = Vec::new();
+        write_stl(&mut stl, &mesh).unwrap();
+        assert_same_square(&mesh, &parse_stl(&stl, "square.stl").unwrap());
+
+        let mut obj = Vec::new();
+        let named = NamedMesh {
+            name: "square".to_string(),
+            mesh: mesh.clone(),
+        };
+        write_obj(&mut obj, &[named.clone(), named]).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        let read = parse_wavefront_obj(&obj, "square.obj", Strictness::Strict).unwrap();
+        assert_eq!(read.groups.len(), 2);
+        assert_same_square(&mesh, &read.groups[1].mesh);
+        assert_eq!(read.groups[1].mesh.vertices[2].uv_coord, (1., 1.));
+    }
+
+    #[test]
+    fn saves_by_extension() {
+        let directory = std::env::temp_dir();
+        let path = |name: &str| directory.join(name).to_string_lossy().to_string();
+        let mesh = bake_scene(&scene()).remove(0).mesh;
+
+        save_scene(&scene(), &path("baked_square.ply")).unwrap();
+        assert_same_square(&mesh, &load_ply(path("baked_square.ply")).unwrap());
+        save_mesh(&mesh, &path("baked_square.stl")).unwrap();
+        assert_same_square(&mesh, &load_stl(path("baked_square.stl")).unwrap());
+        assert!(save_mesh(&mesh, &path("baked_square.fbx")).is_err());
+    }
+
+    #[test]
+    fn only_faces_are_written() {
+        // a disc with more corners than a byte counts, next to an empty polygon and a lone edge
+        let corners = 300;
+        let vertices = (0..corners)
+            .map(|i| {
+                let angle = i as f32 / corners as f32 * std::f32::consts::TAU;
+                Vertex::new(angle.cos(), angle.sin(), 0.)
+            })
+            .collect();
+        let mesh = Mesh::new(vertices, vec![vec![], (0..corners).collect(), vec![0, 1]]);
+
+        let mut ply = Vec::new();
+        write_ply(&mut ply, &mesh).unwrap();
+        let read = parse_ply(&ply, "disc.ply").unwrap();
+        // read back triangulated, but every corner made it through
+        assert_eq!(read.polygons.len(), corners - 2);
+        let mut used: Vec<usize> = read.polygons.iter().flatten().copied().collect();
+        used.sort();
+        used.dedup();
+        assert_eq!(used, (0..corners).collect::<Vec<_>>());
+
+        let mut stl = Vec::new();
+        write_stl(&mut stl, &mesh).unwrap();
+        let read = parse_stl(&stl, "disc.stl").unwrap();
+        assert_eq!(read.polygons.len(), corners - 2);
+    }
+}
@@ -1,33 +1,55 @@
 use std::sync::Arc;
 
-use slotmap::new_key_type;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use slotmap::{new_key_type, SecondaryMap};
 
+use crate::animation::Animation;
 use crate::camera::Camera;
 // use crate::coordinate_space::Polar;
 use crate::empty::Empty;
 use crate::entity::Entity;
-use crate::geometry::primitives::Mesh;
+use crate::geometry::primitives::{BoundingBox, Mesh, Ray, Vector, ORIGIN};
+use crate::geometry::transformations::{build_identity_transform, compile_transforms, Transform};
 use crate::material::BRDF;
 use crate::object::Object;
+use crate::random::Pcg32;
+use crate::ray_tracing::bounding_volume_hierarchy::InstanceHierarchy;
 // use crate::primitives::Object;
 use crate::lighting::{black_spectra, Light, Spectra};
 use crate::slotmap::SlotMap;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, ValueEnum)]
 pub enum ShaderMode {
+    #[value(name = "bvh")]
     _BVH,
+    #[value(name = "solid")]
     _Solid,
     Lit,
+    #[value(name = "z_depth")]
     _ZDepth,
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
 pub enum Rendermode {
+    #[serde(rename = "ray_trace")]
+    #[value(name = "ray_trace")]
     _RayTrace,
+    #[value(name = "threaded_ray_trace")]
     ThreadedRayTrace,
     Rasterize,
 }
 
+/// what happens to the children of a removed entity
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Removal {
+    /// the children go too, all the way down
+    Recursive,
+    /// the children move up to the removed entity's parent and stay where they are in the world
+    AdoptOrphans,
+}
+
 pub type EntityType = Box<dyn Entity>;
 new_key_type! {pub struct EntityKey;}
 /// I am not sure what the responsibilities of this construction should be
@@ -41,12 +63,14 @@ new_key_type! {pub struct EntityKey;}
 /// I have settled on unified
 /// To implement a unified mesh, references need to stay valid
 ///
-// #[derive(Clone)]
+#[derive(Clone)]
 pub struct Scene {
     // pub struct Scene<T: Entity> {
     pub root: EntityKey,
     active_camera: EntityKey,
     pub entities: SlotMap<EntityKey, EntityType>,
+    /// where every entity ends up once its parents are accounted for, filled by `cascade_transforms`
+    world_transforms: SecondaryMap<EntityKey, Transform>,
     pub materials: Vec<Arc<dyn BRDF>>,
     // pub lights: Vec<&'static dyn Light>,
     pub simple_lights: Vec<EntityKey>,
@@ -54,16 +78,28 @@ pub struct Scene {
     pub _meshes: Vec<Mesh>,
     pub background: Spectra,
     pub tick: u32,
+    /// ticks per second, for turning the tick into a time to animate to
+    pub frame_rate: f32,
+    /// evaluated by the application step every frame
+    pub animations: Vec<Animation>,
     pub rendermode: Rendermode,
     pub shadermode: ShaderMode,
     pub logging: u8,
+    /// traverse a bounding volume hierarchy instead of testing every polygon
     pub spatial_acceleration_structures: bool,
+    /// the instances rays are cast against, built by the pipeline once transforms are applied
+    /// only present for ray traced renders
+    pub acceleration_structure: Option<InstanceHierarchy>,
     pub recursive_raycasting: bool,
     pub hue_timer: bool,
     pub samples: u32,
     pub max_trace_depth: u32,
     pub max_render_dist: f32,
     pub tilesize: u32,
+    /// worker threads for threaded ray tracing, 0 uses every core
+    pub threads: usize,
+    /// where anything random about a render starts from
+    pub seed: u64,
 }
 impl Default for Scene {
     fn default() -> Self {
@@ -74,6 +110,7 @@ impl Default for Scene {
             root,
             active_camera,
             entities,
+            world_transforms: SecondaryMap::new(),
             // entities: SlotMap::new<EntityKey,EntityType>(),
             materials: Vec::new(),
             simple_lights: Vec::new(),
@@ -81,24 +118,35 @@ impl Default for Scene {
             _meshes: Vec::new(),
             background: black_spectra(),
             tick: 0,
+            frame_rate: 24.,
+            animations: Vec::new(),
             rendermode: Rendermode::ThreadedRayTrace,
             shadermode: ShaderMode::Lit,
             logging: 0,
             spatial_acceleration_structures: true,
+            acceleration_structure: None,
             recursive_raycasting: true,
             hue_timer: false,
             samples: 1,
             max_trace_depth: 1,
             max_render_dist: 1e6,
             tilesize: 64,
+            threads: 0,
+            seed: 0,
         };
         // scene.build_light_vector();
         scene
     }
 }
 impl Scene {
+    /// anything handed out mutably may be moved, so the cached world transforms are dropped
     pub fn get_mut(&mut self, key: EntityKey) -> &mut EntityType {
-        self.entities.get_mut(key).unwrap()
+        self.try_get_mut(key).unwrap()
+    }
+    /// None when the entity has been removed
+    pub fn try_get_mut(&mut self, key: EntityKey) -> Option<&mut EntityType> {
+        self.world_transforms.clear();
+        self.entities.get_mut(key)
     }
     pub fn get(&self, key: EntityKey) -> &EntityType {
         self.entities.get(key).unwrap()
@@ -115,16 +163,38 @@ impl Scene {
     pub fn set_active_camera(&mut self, key: EntityKey) {
         self.active_camera = key;
     }
+    pub fn active_camera_key(&self) -> EntityKey {
+        self.active_camera
+    }
+    /// a jittered ray through a pixel of the active camera, in world space
+    pub fn camera_ray(&self, x: u32, y: u32, rng: &mut Pcg32) -> Ray {
+        self.camera_to_world(Camera::jittered_pixel_to_ray(
+            self.active_camera(),
+            x,
+            y,
+            rng,
+        ))
+    }
+    /// places a ray in the active camera's space wherever the camera sits and faces in the world
+    pub fn camera_to_world(&self, ray: Ray) -> Ray {
+        let to_world = self.world_transform(self.active_camera);
+        Ray::new(
+            to_world.apply_to_point(&ray.position),
+            to_world.apply_to_direction(&ray.direction).unitized(),
+        )
+    }
     pub fn objects(&self) -> impl Iterator<Item = &Object> {
         let objects = self.objects.clone();
         let iterable = objects.into_iter();
         let mapped = iterable.map(|key: EntityKey| self.get(key).as_object().unwrap());
         mapped
     }
-    pub fn simple_lights(&self) -> impl Iterator<Item = &dyn Light> {
+    /// every light along with its world space position
+    pub fn simple_lights(&self) -> impl Iterator<Item = (Vector, &dyn Light)> {
         let simple_lights = self.simple_lights.clone();
         let iterable = simple_lights.into_iter();
-        let mapped = iterable.map(|key: EntityKey| self.get(key).as_light().unwrap());
+        let mapped = iterable
+            .map(|key: EntityKey| (self.world_position(key), self.get(key).as_light().unwrap()));
         mapped
     }
     pub fn get_object_keys(&self) -> Vec<EntityKey> {
@@ -150,6 +220,127 @@ impl Scene {
         let child = self.get_mut(child_key);
         child.set_parent(parent_key);
     }
+    /// takes an entity out of the scene along with whatever it was registered as
+    pub fn remove(&mut self, key: EntityKey, removal: Removal) {
+        assert!(key != self.root, "the root can't be removed");
+        assert!(
+            key != self.active_camera,
+            "switch to another camera before removing the active one"
+        );
+        // checked up front, finding the camera partway down would leave the rest half removed
+        assert!(
+            removal == Removal::AdoptOrphans || !self.is_ancestor(key, self.active_camera),
+            "the active camera is beneath it, switch to another camera before removing it"
+        );
+        let children = self.get(key).get_children();
+        match removal {
+            Removal::Recursive => {
+                for child in children {
+                    self.remove(child, Removal::Recursive);
+                }
+            }
+            Removal::AdoptOrphans => {
+                let grandparent = self.get(key).get_parent();
+                for child in children {
+                    match grandparent {
+                        Some(grandparent) => self.reparent(child, grandparent),
+                        None => self.detach(child),
+                    }
+                }
+            }
+        }
+        if let Some(parent) = self.get(key).get_parent() {
+            self.get_mut(parent)
+                .get_mut_children()
+                .retain(|&k| k != key);
+        }
+        self.objects.retain(|&k| k != key);
+        self.animations.retain(|a| a.target != key);
+        self.simple_lights.retain(|&k| k != key);
+        self.world_transforms.remove(key);
+        self.entities.remove(key);
+    }
+
+    /// moves an entity under a new parent without moving it in the world
+    /// its position, orientation and scale are rewritten relative to the new parent
+    pub fn reparent(&mut self, child: EntityKey, parent: EntityKey) {
+        assert!(
+            !self.is_ancestor(child, parent),
+            "an entity can't be parented to itself or anything beneath it"
+        );
+        let world = self.world_transform(child);
+        let local = compile_transforms(&vec![world, self.inverse_world_transform(parent)]);
+        self.detach(child);
+        self.set_local_transform(child, &local);
+        self.add_child(parent, child);
+        // everything cached beneath the child is still right, the child itself didn't move
+    }
+
+    /// moves an entity under a new parent, keeping its local transform
+    /// unlike `reparent` it ends up wherever the parent takes it
+    pub fn attach(&mut self, child: EntityKey, parent: EntityKey) {
+        assert!(
+            !self.is_ancestor(child, parent),
+            "an entity can't be parented to itself or anything beneath it"
+        );
+        if let Some(old_parent) = self.get(child).get_parent() {
+            self.get_mut(old_parent)
+                .get_mut_children()
+                .retain(|&k| k != child);
+        }
+        self.add_child(parent, child);
+        // the child and everything beneath it may have moved
+        self.world_transforms.clear();
+    }
+
+    /// leaves an entity parentless, keeping its place in the world
+    fn detach(&mut self, key: EntityKey) {
+        let world = self.world_transform(key);
+        if let Some(parent) = self.get(key).get_parent() {
+            self.get_mut(parent)
+                .get_mut_children()
+                .retain(|&k| k != key);
+        }
+        self.get_mut(key).clear_parent();
+        self.set_local_transform(key, &world);
+    }
+
+    fn set_local_transform(&mut self, key: EntityKey, local: &Transform) {
+        let (position, orientation, scale) = local.decompose();
+        let entity = self.get_mut(key);
+        entity.set_position(position);
+        entity.set_orientation(orientation);
+        entity.set_scale(scale);
+    }
+
+    /// whether `ancestor` is `key` or anything above it
+    pub fn is_ancestor(&self, ancestor: EntityKey, key: EntityKey) -> bool {
+        let mut current = Some(key);
+        while let Some(k) = current {
+            if k == ancestor {
+                return true;
+            }
+            current = self.get(k).get_parent();
+        }
+        false
+    }
+
+    /// the first entity with the given name
+    pub fn find_by_name(&self, name: &str) -> Option<EntityKey> {
+        self.entities
+            .iter()
+            .find(|(_, entity)| entity.get_name() == Some(name))
+            .map(|(key, _)| key)
+    }
+
+    /// every entity carrying the tag
+    pub fn iter_tagged<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = EntityKey> + 'a {
+        self.entities
+            .iter()
+            .filter(move |(_, entity)| entity.has_tag(tag))
+            .map(|(key, _)| key)
+    }
+
     pub fn crawl_scene_graph(&self) {
         let children = self.get(self.root).get_children();
         for child in children {
@@ -157,8 +348,94 @@ impl Scene {
         }
     }
 
+    /// walks down from every parentless entity, usually just the root,
+    /// and caches the accumulated world transform of everything beneath
     pub fn cascade_transforms(&mut self) {
-        let _root = self.get(self.root);
+        self.world_transforms.clear();
+        let parentless: Vec<EntityKey> = self
+            .entities
+            .iter()
+            .filter(|(_, entity)| entity.get_parent().is_none())
+            .map(|(key, _)| key)
+            .collect();
+        for key in parentless {
+            self.cascade_from(key, &build_identity_transform());
+        }
+    }
+
+    fn cascade_from(&mut self, key: EntityKey, parent: &Transform) {
+        let entity = self.get(key);
+        let world = compile_transforms(&vec![entity.get_local_transform(), parent.clone()]);
+        for child in entity.get_children() {
+            self.cascade_from(child, &world);
+        }
+        self.world_transforms.insert(key, world);
+    }
+
+    /// object space to world space for an entity
+    /// uses the cache when `cascade_transforms` has been run since anything was last changed, otherwise walks up the parents
+    pub fn world_transform(&self, key: EntityKey) -> Transform {
+        if let Some(world) = self.world_transforms.get(key) {
+            return world.clone();
+        }
+        let entity = self.get(key);
+        match entity.get_parent() {
+            Some(parent) => compile_transforms(&vec![
+                entity.get_local_transform(),
+                self.world_transform(parent),
+            ]),
+            None => entity.get_local_transform(),
+        }
+    }
+
+    /// world space to object space for an entity
+    pub fn inverse_world_transform(&self, key: EntityKey) -> Transform {
+        self.world_transform(key)
+            .inverse()
+            .expect("entities need an invertible transform, check for a zero scale")
+    }
+
+    /// carries coordinates in the local space of `from` into the local space of `to`
+    /// a point at the origin of `from` comes out as where `from` is as seen by `to`
+    pub fn relative_transform(&self, from: EntityKey, to: EntityKey) -> Transform {
+        compile_transforms(&vec![
+            self.world_transform(from),
+            self.inverse_world_transform(to),
+        ])
+    }
+
+    /// carries coordinates in the local space of `to` back into the local space of `from`
+    pub fn inverse_relative_transform(&self, from: EntityKey, to: EntityKey) -> Transform {
+        self.relative_transform(to, from)
+    }
+
+    /// the geometry an entity carries in its own space, empty for anything without meshes
+    pub fn local_bounds(&self, key: EntityKey) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
+        if let Some(object) = self.get(key).as_object() {
+            for mesh in &object.meshes {
+                bounds = bounds.union(&mesh.bounds());
+            }
+        }
+        bounds
+    }
+
+    /// world space box around an entity and everything parented beneath it
+    pub fn world_bounds(&self, key: EntityKey) -> BoundingBox {
+        let mut bounds = self
+            .local_bounds(key)
+            .transformed(&self.world_transform(key));
+        for child in self.get(key).get_children() {
+            bounds = bounds.union(&self.world_bounds(child));
+        }
+        bounds
+    }
+
+    pub fn world_position(&self, key: EntityKey) -> Vector {
+        match self.world_transforms.get(key) {
+            Some(world) => world.apply_to_point(&ORIGIN),
+            None => self.world_transform(key).apply_to_point(&ORIGIN),
+        }
     }
 
     // pub fn get_objects(&mut self) -> impl Iterator<Item = &mut EntityType> {
@@ -168,3 +445,251 @@ impl Scene {
     //         .filter_map(move |key| {self.entities.get_mut(key)})
     // }
 }
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::{
+        geometry::{
+            orientation::{Orientation, RIGHT},
+            primitives::{Mesh, Vector, ORIGIN},
+        },
+        lighting::PointLight,
+        object::Object,
+    };
+
+    use super::{Removal, Scene};
+
+    #[test]
+    fn lights_follow_their_parent() {
+        let mut scene = Scene::default();
+        let parent = scene.push_object(Object {
+            position: Vector::new(1., 0., 0.),
+            scale: Vector::new(2., 2., 2.),
+            ..Object::default()
+        });
+        let light = scene.push_simple_light(PointLight {
+            position: Vector::new(0., 1., 0.),
+            ..PointLight::default()
+        });
+        // reparent the light from the root onto the object
+        scene
+            .get_mut(scene.root)
+            .get_mut_children()
+            .retain(|&k| k != light);
+        scene.add_child(parent, light);
+
+        scene.cascade_transforms();
+        let position = scene.world_position(light);
+        assert_abs_diff_eq!(position.x, 1., epsilon = 1e-5);
+        assert_abs_diff_eq!(position.y, 2., epsilon = 1e-5);
+
+        scene.get_mut(parent).as_object_mut().unwrap().position = Vector::new(0., 0., -3.);
+        scene.cascade_transforms();
+        let (position, _) = scene.simple_lights().next().unwrap();
+        assert_abs_diff_eq!(position.y, 2., epsilon = 1e-5);
+        assert_abs_diff_eq!(position.z, -3., epsilon = 1e-5);
+    }
+
+    #[test]
+    fn rotation_scale_and_translation_compose() {
+        let mut scene = Scene::default();
+        let parent = scene.push_object(Object {
+            position: Vector::new(0., 5., 0.),
+            orientation: RIGHT,
+            ..Object::default()
+        });
+        let child = scene.push_object(Object {
+            position: Vector::new(0., 0., 1.),
+            scale: Vector::new(3., 3., 3.),
+            ..Object::default()
+        });
+        scene
+            .get_mut(scene.root)
+            .get_mut_children()
+            .retain(|&k| k != child);
+        scene.add_child(parent, child);
+
+        // the cache and walking up the parents have to agree
+        let walked = scene.world_transform(child);
+        scene.cascade_transforms();
+        let cached = scene.world_transform(child);
+        assert_eq!(walked.matrix, cached.matrix);
+
+        let corner = cached.apply_to_point(&Vector::new(1., 0., 0.));
+        let rotate = RIGHT.to_transform();
+        let expected = rotate.apply_to_point(&Vector::new(3., 0., 1.)) + Vector::new(0., 5., 0.);
+        assert_abs_diff_eq!(corner.x, expected.x, epsilon = 1e-5);
+        assert_abs_diff_eq!(corner.y, expected.y, epsilon = 1e-5);
+        assert_abs_diff_eq!(corner.z, expected.z, epsilon = 1e-5);
+        // rotations keep lengths
+        assert_abs_diff_eq!(
+            rotate
+                .apply_to_direction(&Vector::new(1., 2., 3.))
+                .magnitude(),
+            Vector::new(1., 2., 3.).magnitude(),
+            epsilon = 1e-5
+        );
+
+        // moving the parent afterward isn't hidden by the cache
+        let before = scene.world_position(child);
+        scene.get_mut(parent).set_position(Vector::new(0., 6., 0.));
+        assert_abs_diff_eq!(scene.world_position(child).y, before.y + 1., epsilon = 1e-5);
+    }
+
+    #[test]
+    fn relative_transforms_and_bounds() {
+        let mut scene = Scene::default();
+        let a = scene.push_object(Object {
+            position: Vector::new(4., 0., 0.),
+            scale: Vector::new(2., 2., 2.),
+            meshes: vec![Mesh::_unit_cube()],
+            ..Object::default()
+        });
+        let b = scene.push_object(Object {
+            position: Vector::new(0., 0., -3.),
+            orientation: Orientation::from_axis_angle(
+                std::f32::consts::FRAC_PI_2,
+                Vector::new(0., 1., 0.),
+            ),
+            meshes: vec![Mesh::_unit_cube()],
+            ..Object::default()
+        });
+        scene.cascade_transforms();
+
+        // a sits 4 along x and 3 along z from b, which is turned a quarter about y
+        let seen_from_b = scene.relative_transform(a, b).apply_to_point(&ORIGIN);
+        assert_abs_diff_eq!(seen_from_b.x, -3., epsilon = 1e-4);
+        assert_abs_diff_eq!(seen_from_b.y, 0., epsilon = 1e-4);
+        assert_abs_diff_eq!(seen_from_b.z, 4., epsilon = 1e-4);
+        let back = scene
+            .inverse_relative_transform(a, b)
+            .apply_to_point(&seen_from_b);
+        assert_abs_diff_eq!(back.magnitude(), 0., epsilon = 1e-4);
+
+        let bounds = scene.world_bounds(a);
+        assert_abs_diff_eq!(bounds.min.x, 2., epsilon = 1e-4);
+        assert_abs_diff_eq!(bounds.max.x, 6., epsilon = 1e-4);
+        assert_abs_diff_eq!(bounds.max.y, 2., epsilon = 1e-4);
+        // the root holds everything
+        let everything = scene.world_bounds(scene.root);
+        assert_eq!(everything.union(&bounds), everything);
+        assert_abs_diff_eq!(everything.min.z, -4., epsilon = 1e-4);
+        assert!(scene.local_bounds(scene.root).is_empty());
+    }
+
+    #[test]
+    fn reparenting_keeps_world_placement() {
+        let mut scene = Scene::default();
+        let parent = scene.push_object(Object {
+            position: Vector::new(2., -1., 0.),
+            orientation: Orientation::from_euler(0.3, 1.2, -0.4),
+            scale: Vector::new(2., 2., 2.),
+            ..Object::default()
+        });
+        let child = scene.push_object(Object {
+            position: Vector::new(1., 2., 3.),
+            orientation: Orientation::from_axis_angle(0.7, Vector::new(1., 1., 0.)),
+            ..Object::default()
+        });
+        let before = scene.world_transform(child);
+        scene.reparent(child, parent);
+        assert_eq!(scene.get(child).get_parent(), Some(parent));
+        assert!(!scene.get(scene.root).get_children().contains(&child));
+
+        scene.cascade_transforms();
+        let after = scene.world_transform(child);
+        for (a, b) in before.matrix.iter().zip(after.matrix.iter()) {
+            assert_abs_diff_eq!(a, b, epsilon = 1e-4);
+        }
+        // now it moves with the parent
+        scene.get_mut(parent).set_position(Vector::new(2., 9., 0.));
+        scene.cascade_transforms();
+        let moved = scene.world_position(child);
+        assert_abs_diff_eq!(
+            moved.y - 10.,
+            before.apply_to_point(&ORIGIN).y,
+            epsilon = 1e-4
+        );
+    }
+
+    #[test]
+    fn removal() {
+        let mut scene = Scene::default();
+        let parent = scene.push_object(Object {
+            position: Vector::new(0., 3., 0.),
+            ..Object::default()
+        });
+        let child = scene.push_object(Object::default());
+        let light = scene.push_simple_light(PointLight::default());
+        scene.get_mut(child).set_position(Vector::new(0., 1., 0.));
+        scene.reparent(child, parent);
+        scene.reparent(light, child);
+        // child ends up at 3 and the light at 2
+        scene.get_mut(parent).set_position(Vector::new(0., 5., 0.));
+        scene.cascade_transforms();
+
+        scene.remove(child, Removal::AdoptOrphans);
+        assert_eq!(scene.get_object_keys(), vec![parent]);
+        assert_eq!(scene.get(light).get_parent(), Some(parent));
+        assert_eq!(scene.get(parent).get_children(), vec![light]);
+        assert_abs_diff_eq!(scene.world_position(light).y, 2., epsilon = 1e-5);
+        scene.cascade_transforms();
+        assert_abs_diff_eq!(scene.world_position(light).y, 2., epsilon = 1e-5);
+
+        scene.remove(parent, Removal::Recursive);
+        assert!(scene.get_object_keys().is_empty());
+        assert_eq!(scene.simple_lights().count(), 0);
+        assert!(scene.entities.get(light).is_none());
+        assert!(scene.get(scene.root).get_children().is_empty());
+    }
+
+    #[test]
+    fn the_active_camera_isnt_removed_along_with_its_parent() {
+        let mut scene = Scene::default();
+        let rig = scene.push_object(Object::default());
+        // comes before the camera, so it would be the first to go
+        let sibling = scene.push_object(Object::default());
+        scene.attach(sibling, rig);
+        let camera = scene.active_camera_key();
+        scene.attach(camera, rig);
+
+        let removed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            scene.remove(rig, Removal::Recursive)
+        }));
+        assert!(removed.is_err());
+        // turned down before anything was touched
+        assert_eq!(scene.get(rig).get_children(), vec![sibling, camera]);
+        assert_eq!(scene.get_object_keys(), vec![rig, sibling]);
+
+        // handing the camera on to the root is fine
+        scene.remove(rig, Removal::AdoptOrphans);
+        assert_eq!(scene.get(camera).get_parent(), Some(scene.root));
+        assert_eq!(scene.get(sibling).get_parent(), Some(scene.root));
+    }
+
+    #[test]
+    fn names_and_tags() {
+        let mut scene = Scene::default();
+        let lamp = scene.push_simple_light(PointLight {
+            name: Some("lamp".to_string()),
+            ..PointLight::default()
+        });
+        let crate_key = scene.push_object(Object::default());
+        scene.get_mut(crate_key).set_name("crate");
+        scene.get_mut(crate_key).add_tag("prop");
+        scene.get_mut(lamp).add_tag("prop");
+        scene.get_mut(lamp).add_tag("prop");
+
+        assert_eq!(scene.find_by_name("lamp"), Some(lamp));
+        assert_eq!(scene.find_by_name("crate"), Some(crate_key));
+        assert_eq!(scene.find_by_name("nothing"), None);
+        let mut tagged: Vec<_> = scene.iter_tagged("prop").collect();
+        tagged.sort();
+        let mut expected = vec![lamp, crate_key];
+        expected.sort();
+        assert_eq!(tagged, expected);
+        assert_eq!(scene.get(lamp).get_tags().len(), 1);
+    }
+}
@@ -0,0 +1,1362 @@
+// the format itself is documented in docs/scene_format.md
+
+use crate::camera::{Camera, Lens, Sensor};
+use crate::color::rgb_to_spectra::rgb_to_spectra;
+use crate::empty::Empty;
+use crate::entity::Entity;
+use crate::geometry::orientation::{Orientation, UP};
+use crate::geometry::primitives::{Mesh, MeshSource, Vector};
+use crate::lighting::{
+    const_spectra, incandescent_spectra, tabulated_spectra, PointLight, Spectra,
+};
+use crate::load_error::{LoadError, Location, Strictness};
+use crate::load_object_file::{read_wavefront_obj, WavefrontObj};
+use crate::load_ply::load_ply;
+use crate::load_stl::load_stl;
+use crate::material::coated::Coated;
+use crate::material::conductor::{ComplexIor, Metal, Mirror, RoughConductor};
+use crate::material::dielectric::{Dielectric, Dispersion, Glass};
+use crate::material::emissive::Emissive;
+use crate::material::glossy::Glossy;
+use crate::material::{Diffuse, Sidedness, BRDF};
+use crate::object::Object;
+use crate::save_mesh::save_mesh;
+use crate::scene::scene::{EntityKey, Rendermode, Scene};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::{fs, io};
+
+/// everything in a scene file, as written
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    #[serde(default)]
+    pub render: RenderSettings,
+    #[serde(default)]
+    pub camera: CameraDescription,
+    /// referred to by name from objects
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub materials: BTreeMap<String, MaterialDescription>,
+    #[serde(default, rename = "empty", skip_serializing_if = "Vec::is_empty")]
+    pub empties: Vec<EmptyDescription>,
+    #[serde(default, rename = "light", skip_serializing_if = "Vec::is_empty")]
+    pub lights: Vec<LightDescription>,
+    #[serde(default, rename = "object", skip_serializing_if = "Vec::is_empty")]
+    pub objects: Vec<ObjectDescription>,
+}
+
+/// anything left out takes the value of `Scene::default`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderSettings {
+    pub rendermode: Rendermode,
+    pub samples: u32,
+    pub max_trace_depth: u32,
+    pub max_render_dist: f32,
+    pub tilesize: u32,
+    /// renders with the same seed come out the same
+    pub seed: u64,
+    /// what rays that hit nothing see
+    pub background: SpectrumDescription,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings::from(&Scene::default())
+    }
+}
+
+impl From<&Scene> for RenderSettings {
+    fn from(scene: &Scene) -> Self {
+        RenderSettings {
+            rendermode: scene.rendermode.clone(),
+            samples: scene.samples,
+            max_trace_depth: scene.max_trace_depth,
+            max_render_dist: scene.max_render_dist,
+            tilesize: scene.tilesize,
+            seed: scene.seed,
+            background: SpectrumDescription::from(&scene.background),
+        }
+    }
+}
+
+/// where an entity sits relative to its parent, and what it's called
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Placement {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// the name of another entity in the file, otherwise the scene root
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    pub position: [f32; 3],
+    /// a quaternion, [w, x, y, z]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<[f32; 4]>,
+    /// degrees about x, then y, then z, for writing by hand. Can't be given along with an orientation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "is_unit_scale")]
+    pub scale: [f32; 3],
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// whatever keys nothing else took, flattening keeps `deny_unknown_fields` from catching them
+    #[serde(flatten, skip_serializing_if = "BTreeMap::is_empty")]
+    pub unknown: BTreeMap<String, toml::Value>,
+}
+
+fn is_unit_scale(scale: &[f32; 3]) -> bool {
+    *scale == [1., 1., 1.]
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Placement {
+            name: None,
+            parent: None,
+            position: [0., 0., 0.],
+            orientation: None,
+            rotation: None,
+            scale: [1., 1., 1.],
+            tags: Vec::new(),
+            unknown: BTreeMap::new(),
+        }
+    }
+}
+
+impl Placement {
+    pub fn of(entity: &dyn Entity, name: Option<String>, parent: Option<String>) -> Placement {
+        let orientation = entity.get_orientation();
+        Placement {
+            name,
+            parent,
+            position: entity.get_position().as_array(),
+            orientation: (orientation != UP).then_some([
+                orientation.w,
+                orientation.x,
+                orientation.y,
+                orientation.z,
+            ]),
+            rotation: None,
+            scale: entity.get_scale().as_array(),
+            tags: entity.get_tags().to_vec(),
+            unknown: BTreeMap::new(),
+        }
+    }
+
+    fn orientation(&self) -> Result<Orientation, String> {
+        match (self.orientation, self.rotation) {
+            (Some(_), Some(_)) => Err("give an orientation or a rotation, not both".to_string()),
+            (Some([w, x, y, z]), None) => {
+                let length = (w * w + x * x + y * y + z * z).sqrt();
+                if length == 0. {
+                    return Err("an orientation can't be all zeros".to_string());
+                }
+                Ok(Orientation {
+                    w: w / length,
+                    x: x / length,
+                    y: y / length,
+                    z: z / length,
+                })
+            }
+            (None, Some([x, y, z])) => Ok(Orientation::from_euler(
+                x.to_radians(),
+                y.to_radians(),
+                z.to_radians(),
+            )),
+            (None, None) => Ok(UP),
+        }
+    }
+
+    fn apply(&self, entity: &mut dyn Entity) -> Result<(), String> {
+        if let Some(key) = self.unknown.keys().next() {
+            return Err(format!("there's no setting called `{key}`"));
+        }
+        if self.scale.contains(&0.) {
+            return Err("a scale can't be zero".to_string());
+        }
+        entity.set_position(Vector::from(self.position));
+        entity.set_orientation(self.orientation()?);
+        entity.set_scale(Vector::from(self.scale));
+        if let Some(name) = &self.name {
+            entity.set_name(name);
+        }
+        for tag in &self.tags {
+            entity.add_tag(tag);
+        }
+        Ok(())
+    }
+
+    /// names the entity in errors
+    fn describe(&self, kind: &str, index: usize) -> String {
+        match &self.name {
+            Some(name) => format!("{kind} `{name}`"),
+            None => format!("{kind} {}", index + 1),
+        }
+    }
+}
+
+/// anything left out takes the value of `Camera::default`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraDescription {
+    #[serde(flatten)]
+    pub placement: Placement,
+    /// in seconds
+    pub exposure_time: f32,
+    pub lens: Lens,
+    pub sensor: Sensor,
+}
+
+impl Default for CameraDescription {
+    fn default() -> Self {
+        let camera = Camera::default();
+        CameraDescription {
+            placement: Placement::default(),
+            exposure_time: camera.exposure_time,
+            lens: camera.lens,
+            sensor: camera.sensor,
+        }
+    }
+}
+
+/// only there to hold other entities
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmptyDescription {
+    #[serde(flatten)]
+    pub placement: Placement,
+}
+
+/// a point light
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LightDescription {
+    #[serde(flatten)]
+    pub placement: Placement,
+    /// watts in every wavelength
+    pub flux: SpectrumDescription,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ObjectDescription {
+    #[serde(flatten)]
+    pub placement: Placement,
+    #[serde(default)]
+    pub meshes: Vec<MeshReference>,
+    /// a name from `materials`, otherwise the default grey
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub material: Option<String>,
+}
+
+/// a mesh file, relative to the scene file
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MeshReference {
+    /// all of it
+    File(String),
+    /// the faces of a Wavefront obj with this group name and material, either can be left out
+    Part {
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MaterialDescription {
+    Diffuse {
+        #[serde(default = "default_albedo")]
+        albedo: SpectrumDescription,
+        #[serde(default = "default_roughness")]
+        roughness: f32,
+        #[serde(default)]
+        sidedness: Sidedness,
+    },
+    /// a GGX specular layer over a diffuse base, or a metal, as glTF's metallic-roughness materials
+    Glossy {
+        #[serde(default = "default_albedo")]
+        albedo: SpectrumDescription,
+        #[serde(default)]
+        metallic: f32,
+        #[serde(default = "default_glossy_roughness")]
+        roughness: f32,
+        #[serde(default)]
+        sidedness: Sidedness,
+    },
+    /// a perfectly smooth metal
+    Mirror {
+        #[serde(default = "default_mirror_ior")]
+        ior: IorDescription,
+        #[serde(default)]
+        sidedness: Sidedness,
+    },
+    /// a rough metal
+    Conductor {
+        #[serde(default = "default_conductor_ior")]
+        ior: IorDescription,
+        #[serde(default = "default_conductor_roughness")]
+        roughness: f32,
+        #[serde(default)]
+        sidedness: Sidedness,
+    },
+    /// a clear coat over another material, which can't be a mirror or a dielectric
+    Coated {
+        base: Box<MaterialDescription>,
+        #[serde(default = "default_coat_ior")]
+        ior: f32,
+        #[serde(default = "default_coat_roughness")]
+        roughness: f32,
+        #[serde(default = "default_coat_absorption")]
+        absorption: SpectrumDescription,
+        #[serde(default)]
+        sidedness: Sidedness,
+    },
+    /// a light in the shape of whatever wears it
+    Emissive {
+        radiance: SpectrumDescription,
+        #[serde(default)]
+        sidedness: Sidedness,
+    },
+    /// glass, water and anything else clear, always two sided
+    Dielectric {
+        #[serde(default = "default_dielectric_ior")]
+        ior: DispersionDescription,
+    },
+}
+
+fn default_albedo() -> SpectrumDescription {
+    SpectrumDescription::from(&Diffuse::default().albedo)
+}
+
+fn default_roughness() -> f32 {
+    Diffuse::default().roughness
+}
+
+fn default_glossy_roughness() -> f32 {
+    Glossy::default().roughness
+}
+
+fn default_mirror_ior() -> IorDescription {
+    IorDescription::from(&Mirror::default().ior)
+}
+
+fn default_conductor_ior() -> IorDescription {
+    IorDescription::from(&RoughConductor::default().ior)
+}
+
+fn default_coat_ior() -> f32 {
+    Coated::default().ior
+}
+
+fn default_coat_roughness() -> f32 {
+    Coated::default().roughness
+}
+
+fn default_coat_absorption() -> SpectrumDescription {
+    SpectrumDescription::from(&Coated::default().absorption)
+}
+
+fn default_dielectric_ior() -> DispersionDescription {
+    DispersionDescription::from(&Dielectric::default().ior)
+}
+
+fn default_conductor_roughness() -> f32 {
+    RoughConductor::default().roughness
+}
+
+impl MaterialDescription {
+    pub fn to_brdf(&self) -> Result<Arc<dyn BRDF>, String> {
+        match self {
+            MaterialDescription::Diffuse {
+                albedo,
+                roughness,
+                sidedness,
+            } => Ok(Arc::new(Diffuse {
+                albedo: albedo.to_spectra()?,
+                roughness: *roughness,
+                sidedness: *sidedness,
+            })),
+            MaterialDescription::Glossy {
+                albedo,
+                metallic,
+                roughness,
+                sidedness,
+            } => Ok(Arc::new(Glossy {
+                albedo: albedo.to_spectra()?,
+                metallic: *metallic,
+                roughness: *roughness,
+                sidedness: *sidedness,
+            })),
+            MaterialDescription::Mirror { ior, sidedness } => Ok(Arc::new(Mirror {
+                ior: ior.to_ior()?,
+                sidedness: *sidedness,
+            })),
+            MaterialDescription::Conductor {
+                ior,
+                roughness,
+                sidedness,
+            } => Ok(Arc::new(RoughConductor {
+                ior: ior.to_ior()?,
+                roughness: *roughness,
+                sidedness: *sidedness,
+            })),
+            MaterialDescription::Coated {
+                base,
+                ior,
+                roughness,
+                absorption,
+                sidedness,
+            } => {
+                if let MaterialDescription::Mirror { .. } | MaterialDescription::Dielectric { .. } =
+                    **base
+                {
+                    return Err(
+                        "a coat can't go over a mirror or a dielectric, there'd be nothing to see of it"
+                            .to_string(),
+                    );
+                }
+                if *ior < 1. {
+                    return Err(format!("a coat with an index of {ior} is thinner than air"));
+                }
+                Ok(Arc::new(Coated {
+                    base: base.to_brdf()?,
+                    ior: *ior,
+                    roughness: *roughness,
+                    absorption: absorption.to_spectra()?,
+                    sidedness: *sidedness,
+                }))
+            }
+            MaterialDescription::Emissive {
+                radiance,
+                sidedness,
+            } => Ok(Arc::new(Emissive {
+                radiance: radiance.to_spectra()?,
+                sidedness: *sidedness,
+            })),
+            MaterialDescription::Dielectric { ior } => Ok(Arc::new(Dielectric {
+                ior: ior.to_dispersion()?,
+            })),
+        }
+    }
+}
+
+/// a metal by name, or its refractive index and extinction coefficient as two spectra
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IorDescription {
+    Preset(Metal),
+    Measured {
+        eta: SpectrumDescription,
+        k: SpectrumDescription,
+    },
+}
+
+impl IorDescription {
+    pub fn to_ior(&self) -> Result<ComplexIor, String> {
+        match self {
+            IorDescription::Preset(metal) => Ok(metal.ior()),
+            IorDescription::Measured { eta, k } => Ok(ComplexIor {
+                η: eta.to_spectra()?,
+                k: k.to_spectra()?,
+            }),
+        }
+    }
+}
+
+/// a glass by name, one index for every wavelength, or the coefficients of a dispersion formula
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DispersionDescription {
+    Preset(Glass),
+    Constant(f32),
+    Formula(Dispersion),
+}
+
+impl DispersionDescription {
+    pub fn to_dispersion(&self) -> Result<Dispersion, String> {
+        let dispersion = match self {
+            DispersionDescription::Preset(glass) => glass.dispersion(),
+            DispersionDescription::Constant(ior) => Dispersion::constant(*ior),
+            DispersionDescription::Formula(dispersion) => *dispersion,
+        };
+        let ior = dispersion.ior(380.).min(dispersion.ior(780.));
+        if ior.is_nan() || ior <= 0. {
+            return Err(
+                "the refractive index has to be above 0 across the visible range".to_string(),
+            );
+        }
+        Ok(dispersion)
+    }
+}
+
+/// presets are written by name, and an index that doesn't change as a plain number
+impl From<&Dispersion> for DispersionDescription {
+    fn from(dispersion: &Dispersion) -> Self {
+        if let Some(glass) = Glass::ALL
+            .into_iter()
+            .find(|glass| glass.dispersion() == *dispersion)
+        {
+            return DispersionDescription::Preset(glass);
+        }
+        match dispersion {
+            Dispersion::Cauchy { a, b } if *b == 0. => DispersionDescription::Constant(*a),
+            _ => DispersionDescription::Formula(*dispersion),
+        }
+    }
+}
+
+/// presets are written by name
+impl From<&ComplexIor> for IorDescription {
+    fn from(ior: &ComplexIor) -> Self {
+        match ior.metal() {
+            Some(metal) => IorDescription::Preset(metal),
+            None => IorDescription::Measured {
+                eta: SpectrumDescription::from(&ior.η),
+                k: SpectrumDescription::from(&ior.k),
+            },
+        }
+    }
+}
+
+/// the ways of writing down a spectrum, told apart by their key
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum SpectrumDescription {
+    /// the same in every wavelength
+    Constant { constant: f32 },
+    /// a blackbody at `blackbody` kelvin giving off `watts` in total, as `incandescent_spectra`
+    Blackbody { blackbody: f32, watts: f32 },
+    /// a linear RGB reflectance, upsampled
+    Rgb { rgb: [f32; 3] },
+    /// the renderer's own 40 bins, from 380nm in steps of 10nm
+    Bins { bins: Vec<f32> },
+    /// [nanometers, value] pairs in increasing order, interpolated between and held past either end
+    Tabulated { tabulated: Vec<[f32; 2]> },
+}
+
+impl SpectrumDescription {
+    pub fn to_spectra(&self) -> Result<Spectra, String> {
+        match self {
+            SpectrumDescription::Constant { constant } => Ok(const_spectra(*constant)),
+            SpectrumDescription::Blackbody { blackbody, watts } => {
+                if *blackbody <= 0. {
+                    return Err(format!("a blackbody at {blackbody}K is too cold to glow"));
+                }
+                Ok(incandescent_spectra(*blackbody, *watts).0)
+            }
+            SpectrumDescription::Rgb { rgb: [r, g, b] } => Ok(rgb_to_spectra((*r, *g, *b))),
+            SpectrumDescription::Bins { bins } => {
+                let mut spectra = const_spectra(0.);
+                if bins.len() != spectra.spectra.len() {
+                    return Err(format!(
+                        "there are {} bins, not {}",
+                        spectra.spectra.len(),
+                        bins.len()
+                    ));
+                }
+                for (bin, value) in spectra.spectra.iter_mut().zip(bins) {
+                    *bin = *value;
+                }
+                Ok(spectra)
+            }
+            SpectrumDescription::Tabulated { tabulated } => {
+                if tabulated.is_empty() {
+                    return Err("a table needs at least one entry".to_string());
+                }
+                if tabulated.windows(2).any(|pair| pair[0][0] >= pair[1][0]) {
+                    return Err("table wavelengths have to increase".to_string());
+                }
+                Ok(tabulated_spectra(tabulated))
+            }
+        }
+    }
+}
+
+/// flat spectra are written as a constant, anything else bin by bin
+/// how a spectrum was made isn't kept, so a blackbody comes back as its bins
+impl From<&Spectra> for SpectrumDescription {
+    fn from(spectra: &Spectra) -> Self {
+        let first = spectra.spectra[0];
+        if spectra.spectra.iter().all(|&value| value == first) {
+            SpectrumDescription::Constant { constant: first }
+        } else {
+            SpectrumDescription::Bins {
+                bins: spectra.spectra.to_vec(),
+            }
+        }
+    }
+}
+
+pub fn load_scene_file(path: String) -> Result<Scene, LoadError> {
+    let text = fs::read_to_string(&path).map_err(|error| LoadError::io(&path, error))?;
+    parse_scene_file(&text, &path)
+}
+
+/// meshes are found relative to the directory `file` is in
+pub fn parse_scene_file(text: &str, file: &str) -> Result<Scene, LoadError> {
+    let description: SceneDescription = toml::from_str(text).map_err(|error| {
+        // toml points at a byte range, which is turned into a line and column
+        let before = &text[..error.span().map_or(0, |span| span.start)];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        LoadError::Malformed {
+            location: Location {
+                file: file.to_string(),
+                line: before.matches('\n').count() + 1,
+                column: before[line_start..].chars().count() + 1,
+            },
+            reason: error.message().to_string(),
+        }
+    })?;
+    description.build(file)
+}
+
+/// writes the scene along with any meshes that weren't read from a file
+/// those are saved as PLY next to it, named after the scene file
+pub fn save_scene_file(scene: &Scene, path: &str) -> io::Result<()> {
+    let description = SceneDescription::of(scene, path)?;
+    let invalid = |error: toml::ser::Error| io::Error::new(io::ErrorKind::InvalidData, error);
+    let mut value = toml::Value::try_from(&description).map_err(invalid)?;
+    shorten_floats(&mut value);
+    fs::write(path, toml::to_string(&value).map_err(invalid)?)
+}
+
+/// every number started out as an f32, written as one it reads 2.74 rather than 2.740000009536743
+fn shorten_floats(value: &mut toml::Value) {
+    match value {
+        toml::Value::Float(float) => *float = (*float as f32).to_string().parse().unwrap(),
+        toml::Value::Array(array) => array.iter_mut().for_each(shorten_floats),
+        toml::Value::Table(table) => table
+            .iter_mut()
+            .for_each(|(_, value)| shorten_floats(value)),
+        _ => {}
+    }
+}
+
+impl SceneDescription {
+    /// `file` is where the description was read from, mesh paths are relative to it
+    pub fn build(&self, file: &str) -> Result<Scene, LoadError> {
+        let invalid =
+            |what: String, reason: String| LoadError::invalid(file, format!("{what}: {reason}"));
+        let mut scene = Scene::default();
+        scene.rendermode = self.render.rendermode.clone();
+        scene.samples = self.render.samples;
+        scene.max_trace_depth = self.render.max_trace_depth;
+        scene.max_render_dist = self.render.max_render_dist;
+        scene.tilesize = self.render.tilesize;
+        scene.seed = self.render.seed;
+        scene.background = self
+            .render
+            .background
+            .to_spectra()
+            .map_err(|reason| invalid("the background".to_string(), reason))?;
+
+        let mut materials = HashMap::new();
+        for (name, material) in &self.materials {
+            let brdf = material
+                .to_brdf()
+                .map_err(|reason| invalid(format!("material `{name}`"), reason))?;
+            materials.insert(name.as_str(), brdf);
+        }
+
+        // parents are attached once everything they could refer to exists
+        let mut placed: Vec<(EntityKey, &Placement, String)> = Vec::new();
+
+        let key = scene.active_camera_key();
+        let mut camera = Camera {
+            exposure_time: self.camera.exposure_time,
+            lens: self.camera.lens.clone(),
+            sensor: self.camera.sensor.clone(),
+            ..Camera::default()
+        };
+        let what = self.camera.placement.describe("camera", 0);
+        self.camera
+            .placement
+            .apply(&mut camera)
+            .map_err(|reason| invalid(what.clone(), reason))?;
+        *scene.active_camera_mut() = camera;
+        placed.push((key, &self.camera.placement, what));
+
+        for (i, description) in self.empties.iter().enumerate() {
+            let what = description.placement.describe("empty", i);
+            let mut empty = Empty::default();
+            description
+                .placement
+                .apply(&mut empty)
+                .map_err(|reason| invalid(what.clone(), reason))?;
+            let key = scene.insert(empty);
+            scene.add_child(scene.root, key);
+            placed.push((key, &description.placement, what));
+        }
+
+        for (i, description) in self.lights.iter().enumerate() {
+            let what = description.placement.describe("light", i);
+            let flux = description
+                .flux
+                .to_spectra()
+                .map_err(|reason| invalid(what.clone(), reason))?;
+            let mut light = PointLight {
+                radiant_flux: flux.into(),
+                ..PointLight::default()
+            };
+            description
+                .placement
+                .apply(&mut light)
+                .map_err(|reason| invalid(what.clone(), reason))?;
+            let key = scene.push_simple_light(light);
+            placed.push((key, &description.placement, what));
+        }
+
+        let directory = Path::new(file).parent().unwrap_or(Path::new(""));
+        let mut meshes = MeshCache {
+            directory,
+            loaded: HashMap::new(),
+            objs: HashMap::new(),
+        };
+        for (i, description) in self.objects.iter().enumerate() {
+            let what = description.placement.describe("object", i);
+            let mut object = Object::default();
+            for reference in &description.meshes {
+                object.meshes.push(meshes.get(reference)?);
+            }
+            if let Some(name) = &description.material {
+                let Some(material) = materials.get(name.as_str()) else {
+                    return Err(invalid(
+                        what,
+                        format!("there's no material called `{name}`"),
+                    ));
+                };
+                object.material = material.clone();
+            }
+            description
+                .placement
+                .apply(&mut object)
+                .map_err(|reason| invalid(what.clone(), reason))?;
+            let key = scene.push_object(object);
+            placed.push((key, &description.placement, what));
+        }
+
+        for (key, placement, what) in &placed {
+            let Some(parent) = &placement.parent else {
+                continue;
+            };
+            let mut candidates = placed
+                .iter()
+                .filter(|(_, other, _)| other.name.as_ref() == Some(parent));
+            let parent_key = match (candidates.next(), candidates.next()) {
+                (Some((parent_key, _, _)), None) => *parent_key,
+                (None, _) => {
+                    let reason = format!("there's nothing called `{parent}` to be parented to");
+                    return Err(invalid(what.clone(), reason));
+                }
+                (Some(_), Some(_)) => {
+                    let reason = format!("more than one thing is called `{parent}`");
+                    return Err(invalid(what.clone(), reason));
+                }
+            };
+            if scene.is_ancestor(*key, parent_key) {
+                let reason = format!("`{parent}` is beneath it, it can't be its parent too");
+                return Err(invalid(what.clone(), reason));
+            }
+            scene.attach(*key, parent_key);
+        }
+        Ok(scene)
+    }
+
+    /// `path` is where the description will be written, meshes are referred to relative to it
+    /// meshes that didn't come from a file are saved next to it
+    ///
+    /// everything under the root is written along with the active camera
+    /// other cameras are written as empties, so whatever hangs off them stays put
+    pub fn of(scene: &Scene, path: &str) -> io::Result<SceneDescription> {
+        let path = Path::new(path);
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let stem = path
+            .file_stem()
+            .map_or("scene".into(), |stem| stem.to_string_lossy());
+        let camera_key = scene.active_camera_key();
+
+        // parents come before their children
+        let mut order = Vec::new();
+        descendants(scene, scene.root, &mut order);
+        if !order.contains(&camera_key) {
+            order.push(camera_key);
+            descendants(scene, camera_key, &mut order);
+        }
+
+        // anything that's a parent needs a name that picks out just it
+        let mut names: HashMap<EntityKey, String> = HashMap::new();
+        let mut taken: HashMap<String, usize> = HashMap::new();
+        for &key in &order {
+            if let Some(name) = scene.get(key).get_name() {
+                names.insert(key, name.to_string());
+                *taken.entry(name.to_string()).or_default() += 1;
+            }
+        }
+        for &key in &order {
+            let entity = scene.get(key);
+            let Some(parent) = entity.get_parent().filter(|&parent| parent != scene.root) else {
+                continue;
+            };
+            let unique = names.get(&parent).is_some_and(|name| taken[name] == 1);
+            if !unique {
+                let base = names
+                    .get(&parent)
+                    .cloned()
+                    .unwrap_or_else(|| kind(scene, parent, camera_key).to_string());
+                let name = (1..)
+                    .map(|n| format!("{base}_{n}"))
+                    .find(|name| !taken.contains_key(name))
+                    .unwrap();
+                taken.insert(name.clone(), 1);
+                names.insert(parent, name);
+            }
+        }
+
+        let mut description = SceneDescription {
+            render: RenderSettings::from(scene),
+            ..SceneDescription::default()
+        };
+        let mut written: Vec<(Mesh, String)> = Vec::new();
+        let default_material = Diffuse::default().describe();
+        for &key in &order {
+            let entity = scene.get(key);
+            let parent = entity
+                .get_parent()
+                .filter(|&parent| parent != scene.root)
+                .map(|parent| names[&parent].clone());
+            let placement = Placement::of(entity.as_ref(), names.get(&key).cloned(), parent);
+
+            if key == camera_key {
+                let camera = entity.as_camera().unwrap();
+                description.camera = CameraDescription {
+                    placement,
+                    exposure_time: camera.exposure_time,
+                    lens: camera.lens.clone(),
+                    sensor: camera.sensor.clone(),
+                };
+            } else if let Some(light) = entity.as_light() {
+                description.lights.push(LightDescription {
+                    placement,
+                    flux: SpectrumDescription::from(&light.radiant_flux().0),
+                });
+            } else if let Some(object) = entity.as_object() {
+                let mut meshes = Vec::new();
+                for mesh in &object.meshes {
+                    meshes.push(mesh_reference(mesh, directory, &stem, &mut written)?);
+                }
+                let Some(material) = object.material.describe() else {
+                    let reason = format!("{:?} can't be written to a scene file", object.material);
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, reason));
+                };
+                let material = (Some(&material) != default_material.as_ref()).then(|| {
+                    // materials that describe the same are shared
+                    let existing = description
+                        .materials
+                        .iter()
+                        .find(|(_, other)| **other == material);
+                    match existing {
+                        Some((name, _)) => name.clone(),
+                        None => {
+                            let name = (1..)
+                                .map(|n| format!("material_{n}"))
+                                .find(|name| !description.materials.contains_key(name))
+                                .unwrap();
+                            description.materials.insert(name.clone(), material);
+                            name
+                        }
+                    }
+                });
+                description.objects.push(ObjectDescription {
+                    placement,
+                    meshes,
+                    material,
+                });
+            } else {
+                description.empties.push(EmptyDescription { placement });
+            }
+        }
+        Ok(description)
+    }
+}
+
+/// depth first, not including `key` itself
+fn descendants(scene: &Scene, key: EntityKey, order: &mut Vec<EntityKey>) {
+    for child in scene.get(key).get_children() {
+        order.push(child);
+        descendants(scene, child, order);
+    }
+}
+
+/// what to call an entity that has no name
+fn kind(scene: &Scene, key: EntityKey, camera: EntityKey) -> &'static str {
+    let entity = scene.get(key);
+    if key == camera {
+        "camera"
+    } else if entity.as_light().is_some() {
+        "light"
+    } else if entity.as_object().is_some() {
+        "object"
+    } else {
+        "empty"
+    }
+}
+
+/// meshes without a source are saved once, and shared from then on
+fn mesh_reference(
+    mesh: &Mesh,
+    directory: &Path,
+    stem: &str,
+    written: &mut Vec<(Mesh, String)>,
+) -> io::Result<MeshReference> {
+    if let Some(source) = &mesh.source {
+        let path = relative_path(directory, &source.path);
+        return Ok(match (&source.group, &source.material) {
+            (None, None) => MeshReference::File(path),
+            (group, material) => MeshReference::Part {
+                path,
+                group: group.clone(),
+                material: material.clone(),
+            },
+        });
+    }
+    if let Some((_, name)) = written
+        .iter()
+        .find(|(other, _)| other.shares_geometry(mesh))
+    {
+        return Ok(MeshReference::File(name.clone()));
+    }
+    let name = format!("{stem}_mesh_{}.ply", written.len() + 1);
+    save_mesh(mesh, &directory.join(&name).to_string_lossy())?;
+    written.push((mesh.clone(), name.clone()));
+    Ok(MeshReference::File(name))
+}
+
+/// `path` as seen from `directory`, both either absolute or relative to the working directory
+/// left alone when either can't be found
+fn relative_path(directory: &Path, path: &str) -> String {
+    let directory = if directory.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        directory
+    };
+    let (Ok(from), Ok(to)) = (directory.canonicalize(), Path::new(path).canonicalize()) else {
+        return path.to_string();
+    };
+    let shared = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in shared..from.components().count() {
+        relative.push("..");
+    }
+    relative.extend(to.components().skip(shared));
+    relative.to_string_lossy().to_string()
+}
+
+/// every file is only read once, and meshes referred to more than once share their geometry
+struct MeshCache<'a> {
+    directory: &'a Path,
+    loaded: HashMap<MeshSource, Mesh>,
+    objs: HashMap<String, WavefrontObj>,
+}
+
+impl MeshCache<'_> {
+    fn get(&mut self, reference: &MeshReference) -> Result<Mesh, LoadError> {
+        let (path, group, material) = match reference {
+            MeshReference::File(path) => (path, None, None),
+            MeshReference::Part {
+                path,
+                group,
+                material,
+            } => (path, group.clone(), material.clone()),
+        };
+        let path = self.directory.join(path).to_string_lossy().to_string();
+        let source = MeshSource {
+            path: path.clone(),
+            group,
+            material,
+        };
+        if let Some(mesh) = self.loaded.get(&source) {
+            return Ok(mesh.clone());
+        }
+
+        let extension = Path::new(&path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        let whole = source.group.is_none() && source.material.is_none();
+        let mut mesh = match extension.as_deref() {
+            Some("obj") => {
+                if !self.objs.contains_key(&path) {
+                    let obj = read_wavefront_obj(path.clone(), Strictness::Strict)?;
+                    self.objs.insert(path.clone(), obj);
+                }
+                let obj = &self.objs[&path];
+                obj.part(source.group.as_deref(), source.material.as_deref())
+                    .ok_or_else(|| {
+                        LoadError::invalid(&path, "no group has that name and material")
+                    })?
+            }
+            Some("ply") if whole => load_ply(path.clone())?,
+            Some("stl") if whole => load_stl(path.clone())?,
+            Some("ply" | "stl") => {
+                return Err(LoadError::invalid(&path, "only obj files have groups"));
+            }
+            _ => {
+                return Err(LoadError::invalid(
+                    &path,
+                    "meshes have to be .obj, .ply or .stl",
+                ))
+            }
+        };
+        mesh.source = Some(source.clone());
+        self.loaded.insert(source, mesh.clone());
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::{
+        lighting::incandescent_spectra,
+        load_error::LoadError,
+        material::{
+            conductor::Metal,
+            dielectric::{Dispersion, Glass},
+            Sidedness,
+        },
+        scene::{
+            scene::Scene,
+            scenes::{cornell_scene, simple_scene},
+        },
+    };
+
+    use super::{
+        load_scene_file, parse_scene_file, save_scene_file, DispersionDescription, IorDescription,
+        MaterialDescription, SceneDescription, SpectrumDescription,
+    };
+
+    /// the things a scene file sets, compared between two scenes
+    fn assert_same_scene(a: &Scene, b: &Scene) {
+        assert_eq!(a.samples, b.samples);
+        assert_eq!(a.max_trace_depth, b.max_trace_depth);
+        assert_eq!(a.tilesize, b.tilesize);
+        assert_eq!(a.seed, b.seed);
+        assert_eq!(a.active_camera().lens, b.active_camera().lens);
+        assert_eq!(a.active_camera().sensor, b.active_camera().sensor);
+        assert_eq!(
+            a.active_camera().exposure_time,
+            b.active_camera().exposure_time
+        );
+        let camera = |scene: &Scene| scene.world_position(scene.active_camera_key());
+        assert_eq!(camera(a), camera(b));
+
+        let a_lights: Vec<_> = a.simple_lights().collect();
+        let b_lights: Vec<_> = b.simple_lights().collect();
+        assert_eq!(a_lights.len(), b_lights.len());
+        for ((a_position, a_light), (b_position, b_light)) in a_lights.iter().zip(&b_lights) {
+            assert_eq!(a_position, b_position);
+            let (a_flux, b_flux) = (&a_light.radiant_flux().0, &b_light.radiant_flux().0);
+            for (a, b) in a_flux.spectra.iter().zip(b_flux.spectra.iter()) {
+                assert_abs_diff_eq!(a, b, epsilon = 1e-4 * a.abs().max(1e-6));
+            }
+        }
+
+        let a_objects: Vec<_> = a.objects().collect();
+        let b_objects: Vec<_> = b.objects().collect();
+        assert_eq!(a_objects.len(), b_objects.len());
+        for (a, b) in a_objects.iter().zip(&b_objects) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.material.describe(), b.material.describe());
+            assert_eq!(a.meshes.len(), b.meshes.len());
+            for (a, b) in a.meshes.iter().zip(&b.meshes) {
+                assert_eq!(a.vertices, b.vertices);
+                assert_eq!(a.polygons, b.polygons);
+            }
+        }
+    }
+
+    #[test]
+    fn examples_match_the_built_in_scenes() {
+        let cornell = load_scene_file("scenes/cornell.toml".to_string()).unwrap();
+        assert_same_scene(&cornell, &cornell_scene().unwrap());
+        let simple = load_scene_file("scenes/simple.toml".to_string()).unwrap();
+        assert_same_scene(&simple, &simple_scene().unwrap());
+        // the cube is only read once
+        let objects: Vec<_> = simple.objects().collect();
+        assert!(objects[0].meshes[0].shares_geometry(&objects[1].meshes[0]));
+    }
+
+    #[test]
+    fn saved_scenes_load_back() {
+        let directory = std::env::temp_dir().join("scene_file_round_trip");
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("cornell.toml").to_string_lossy().to_string();
+
+        let mut cornell = cornell_scene().unwrap();
+        // a mesh that isn't from a file gets saved beside the scene
+        let object = cornell.objects().next().unwrap().clone();
+        let mut copy = object.clone();
+        copy.name = Some("copy".to_string());
+        for mesh in &mut copy.meshes {
+            mesh.source = None;
+        }
+        cornell.push_object(copy);
+
+        save_scene_file(&cornell, &path).unwrap();
+        let loaded = load_scene_file(path.clone()).unwrap();
+        assert_same_scene(&loaded, &cornell);
+        assert!(directory.join("cornell_mesh_1.ply").exists());
+
+        // a second trip writes exactly the same thing
+        let first = std::fs::read_to_string(&path).unwrap();
+        save_scene_file(&loaded, &path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), first);
+    }
+
+    #[test]
+    fn hierarchies_survive() {
+        let scene = parse_scene_file(
+            "[[empty]]\n\
+             name = \"rig\"\n\
+             position = [0, 1, 0]\n\
+             rotation = [0, 90, 0]\n\
+             \n\
+             [[light]]\n\
+             parent = \"rig\"\n\
+             position = [0, 0, -2]\n\
+             flux = { blackbody = 3000, watts = 100 }\n\
+             \n\
+             [camera]\n\
+             parent = \"rig\"\n",
+            "rig.toml",
+        )
+        .unwrap();
+        let (position, light) = scene.simple_lights().next().unwrap();
+        assert_abs_diff_eq!(position.x, -2., epsilon = 1e-5);
+        assert_abs_diff_eq!(position.y, 1., epsilon = 1e-5);
+        assert_eq!(light.radiant_flux().0, incandescent_spectra(3000., 100.).0);
+        let rig = scene.find_by_name("rig").unwrap();
+        assert_eq!(scene.get(scene.active_camera_key()).get_parent(), Some(rig));
+
+        let description = SceneDescription::of(&scene, "rig.toml").unwrap();
+        assert_eq!(
+            description.empties[0].placement.name.as_deref(),
+            Some("rig")
+        );
+        assert_eq!(
+            description.lights[0].placement.parent.as_deref(),
+            Some("rig")
+        );
+        assert_eq!(description.camera.placement.parent.as_deref(), Some("rig"));
+        let rebuilt = description.build("rig.toml").unwrap();
+        let (position, _) = rebuilt.simple_lights().next().unwrap();
+        assert_abs_diff_eq!(position.x, -2., epsilon = 1e-5);
+    }
+
+    #[test]
+    fn spectra_can_be_tabulated() {
+        let table = SpectrumDescription::Tabulated {
+            tabulated: vec![[400., 0.], [500., 1.]],
+        };
+        let spectra = table.to_spectra().unwrap();
+        assert_eq!(spectra.from_λ(380.), 0.);
+        assert_abs_diff_eq!(spectra.from_λ(450.), 0.5);
+        assert_eq!(spectra.from_λ(700.), 1.);
+        assert!(SpectrumDescription::Bins { bins: vec![1.; 3] }
+            .to_spectra()
+            .is_err());
+    }
+
+    #[test]
+    fn metals_are_written_by_name() {
+        let scene = parse_scene_file(
+            "[materials.gold]\n\
+             type = \"mirror\"\n\
+             ior = \"gold\"\n\
+             \n\
+             [materials.made_up]\n\
+             type = \"conductor\"\n\
+             ior = { eta = { constant = 0.2 }, k = { constant = 3.5 } }\n\
+             roughness = 0.5\n\
+             \n\
+             [[object]]\n\
+             material = \"gold\"\n\
+             \n\
+             [[object]]\n\
+             material = \"made_up\"\n",
+            "metals.toml",
+        )
+        .unwrap();
+        let materials: Vec<_> = scene
+            .objects()
+            .map(|object| object.material.describe().unwrap())
+            .collect();
+        assert_eq!(
+            materials[0],
+            MaterialDescription::Mirror {
+                ior: IorDescription::Preset(Metal::Gold),
+                sidedness: Sidedness::TwoSided,
+            }
+        );
+        assert_eq!(
+            materials[1],
+            MaterialDescription::Conductor {
+                ior: IorDescription::Measured {
+                    eta: SpectrumDescription::Constant { constant: 0.2 },
+                    k: SpectrumDescription::Constant { constant: 3.5 },
+                },
+                roughness: 0.5,
+                sidedness: Sidedness::TwoSided,
+            }
+        );
+    }
+
+    #[test]
+    fn glass_is_written_by_name() {
+        let scene = parse_scene_file(
+            "[materials.prism]\n\
+             type = \"dielectric\"\n\
+             ior = \"diamond\"\n\
+             \n\
+             [materials.window]\n\
+             type = \"dielectric\"\n\
+             ior = 1.5\n\
+             \n\
+             [materials.flint]\n\
+             type = \"dielectric\"\n\
+             ior = { cauchy = { a = 1.67, b = 0.0074 } }\n\
+             \n\
+             [[object]]\n\
+             material = \"prism\"\n\
+             \n\
+             [[object]]\n\
+             material = \"window\"\n\
+             \n\
+             [[object]]\n\
+             material = \"flint\"\n",
+            "glass.toml",
+        )
+        .unwrap();
+        let materials: Vec<_> = scene
+            .objects()
+            .map(|object| object.material.describe().unwrap())
+            .collect();
+        assert_eq!(
+            materials,
+            [
+                MaterialDescription::Dielectric {
+                    ior: DispersionDescription::Preset(Glass::Diamond),
+                },
+                MaterialDescription::Dielectric {
+                    ior: DispersionDescription::Constant(1.5),
+                },
+                MaterialDescription::Dielectric {
+                    ior: DispersionDescription::Formula(Dispersion::Cauchy { a: 1.67, b: 0.0074 }),
+                },
+            ]
+        );
+        assert!(parse_scene_file(
+            "[materials.broken]\ntype = \"dielectric\"\nior = -1\n\n[[object]]\nmaterial = \"broken\"\n",
+            "glass.toml",
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn coats_hold_their_base() {
+        let text = "[materials.varnished]\n\
+                    type = \"coated\"\n\
+                    absorption = { constant = 0.1 }\n\
+                    base = { type = \"diffuse\", albedo = { rgb = [0.5, 0.3, 0.1] }, roughness = 0.4 }\n\
+                    \n\
+                    [[object]]\n\
+                    material = \"varnished\"\n";
+        let scene = parse_scene_file(text, "coat.toml").unwrap();
+        let description = scene.objects().next().unwrap().material.describe().unwrap();
+        let MaterialDescription::Coated {
+            base,
+            ior,
+            absorption,
+            ..
+        } = &description
+        else {
+            panic!("{description:?} should be coated");
+        };
+        assert!(matches!(
+            **base,
+            MaterialDescription::Diffuse { roughness, .. } if roughness == 0.4
+        ));
+        assert_eq!(*ior, 1.5);
+        assert_eq!(*absorption, SpectrumDescription::Constant { constant: 0.1 });
+
+        let over_glass = text.replace("type = \"diffuse\"", "type = \"dielectric\"");
+        let over_glass =
+            over_glass.replace(", albedo = { rgb = [0.5, 0.3, 0.1] }, roughness = 0.4", "");
+        assert!(parse_scene_file(&over_glass, "coat.toml").is_err());
+    }
+
+    #[test]
+    fn mistakes_are_pointed_out() {
+        let error = parse_scene_file("[render]\nsamples = \"many\"\n", "bad.toml")
+            .err()
+            .unwrap();
+        assert!(matches!(error, LoadError::Malformed { .. }));
+        assert_eq!(error.location().unwrap().line, 2);
+
+        let error = parse_scene_file(
+            "[[object]]\nname = \"box\"\nmaterial = \"gold\"\n",
+            "bad.toml",
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "bad.toml: object `box`: there's no material called `gold`"
+        );
+
+        let error = parse_scene_file(
+            "[[light]]\nparent = \"rig\"\nflux = { constant = 1 }\n",
+            "bad.toml",
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "bad.toml: light 1: there's nothing called `rig` to be parented to"
+        );
+
+        let error = parse_scene_file("[[object]]\npostion = [0, 1, 0]\n", "bad.toml")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "bad.toml: object 1: there's no setting called `postion`"
+        );
+
+        let error = parse_scene_file("[camera]\nexposur_time = 0.1\n", "bad.toml")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "bad.toml: camera 1: there's no setting called `exposur_time`"
+        );
+
+        let error = parse_scene_file("[[empty]]\nscale = [1, 0, 1]\n", "bad.toml")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "bad.toml: empty 1: a scale can't be zero"
+        );
+    }
+}
@@ -1,13 +1,24 @@
 use crate::camera::{Camera, Lens, Sensor};
-use crate::geometry::orientation::RIGHT;
 // use crate::coordinate_space::Polar;
 use crate::geometry::primitives::{Mesh, Vector};
 use crate::object::Object;
 // use crate::primitives::Object;
 use crate::lighting::{incandescent_spectra, PointLight};
-use crate::load_object_file::load_wavefront_obj;
+use crate::load_error::{LoadError, Strictness};
+use crate::load_object_file::{load_wavefront_obj, load_wavefront_objects};
 use crate::scene::scene::{Rendermode, Scene};
-pub fn cornell_scene<'b>() -> Scene {
+use std::path::Path;
+
+/// one of the models shipped with the crate, found wherever the renderer is run from
+fn model(name: &str) -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("models")
+        .join(name)
+        .to_string_lossy()
+        .to_string()
+}
+
+pub fn cornell_scene() -> Result<Scene, LoadError> {
     let mut scene = Scene::default();
     let lens = Lens {
         aperture: 50.,
@@ -16,44 +27,34 @@ pub fn cornell_scene<'b>() -> Scene {
         ..Lens::default()
     };
     let sensor = Sensor {
-        width: 36.0 / 1.000,
+        width: 36.0 / 1000.,
         horizontal_res: 240 * 2,
         vertical_res: 240 * 2,
         ..Sensor::default()
     };
     let camera = Camera {
         position: Vector::new(0., 2.74, 13.),
-        exposure_time: 1e14,
+        exposure_time: 5e11,
         lens,
         sensor,
         ..Camera::default()
     };
     let key = scene.insert(camera);
     scene.set_active_camera(key);
-    let light = PointLight::new(
-        Vector::new(0.0, 5.0, -0.),
-        // Vector::new(0.0, 3.0, -0.5),
-        RIGHT,
-        incandescent_spectra(2500., 1000.),
-    );
-    scene.push_simple_light(light);
-
-    let cornell = load_wavefront_obj("models/cornell.obj".to_string());
-
-    let object = Object {
-        position: Vector::new(2.8, 0., 0.),
-        meshes: vec![cornell.clone()],
-        ..Object::default()
-    };
-    scene.push_object(object);
+    // lit by the panel in the ceiling, the mtl gives the light group an emission
+    let (objects, _) = load_wavefront_objects(model("cornell.obj"), Strictness::Strict)?;
+    for mut object in objects {
+        object.position = Vector::new(2.8, 0., 0.);
+        scene.push_object(object);
+    }
 
     scene.samples = 16;
     scene.max_trace_depth = 4;
     scene.max_render_dist = 20.;
-    scene
+    Ok(scene)
 }
 
-pub fn simple_scene<'b>() -> Scene {
+pub fn simple_scene() -> Result<Scene, LoadError> {
     let mut scene = Scene::default();
     scene.active_camera_mut().exposure_time = 1e16;
     scene.active_camera_mut().lens.aperture = 50.;
@@ -81,11 +82,9 @@ pub fn simple_scene<'b>() -> Scene {
     light.radiant_flux = incandescent_spectra(5000., 1000.);
     scene.push_simple_light(light);
 
-    let cube = load_wavefront_obj("models/cube.obj".to_string());
-    let sphere: Mesh = load_wavefront_obj("models/sphere.obj".to_string());
-    let plane: Mesh = load_wavefront_obj("models/plane.obj".to_string());
-    let _wall: Mesh = load_wavefront_obj("models/wall.obj".to_string());
-    let _cornell: Mesh = load_wavefront_obj("models/cornell.obj".to_string());
+    let cube = load_wavefront_obj(model("cube.obj"))?;
+    let sphere: Mesh = load_wavefront_obj(model("sphere.obj"))?;
+    let plane: Mesh = load_wavefront_obj(model("plane.obj"))?;
 
     let object = Object {
         position: Vector::new(3.0, 0.0, 0.),
@@ -125,7 +124,7 @@ pub fn simple_scene<'b>() -> Scene {
     // @8 samples  10: 3.2 20: 2.789 40: 2.83 80: 3.39
     // @16 10: 5.83 20: 5.91 40: 6.25 80: 6.13
     scene.hue_timer = false;
-    scene
+    Ok(scene)
 }
 
 // pub fn calibration_scene<'b>() -> Scene {
@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use graphics_v3::lighting::incandescent_spectra;
+use graphics_v3::material::Diffuse;
+use graphics_v3::scene::scene::Rendermode;
+use graphics_v3::{load_scene_file, render, Mesh, Object, PointLight, Scene, Vector, Vertex};
+
+#[test]
+fn scenes_can_be_built_and_rendered_from_outside_the_crate() {
+    let quad = Mesh::new(
+        vec![
+            Vertex::new(-1., -1., 0.),
+            Vertex::new(1., -1., 0.),
+            Vertex::new(1., 1., 0.),
+            Vertex::new(-1., 1., 0.),
+        ],
+        vec![vec![0, 1, 2], vec![0, 2, 3]],
+    );
+
+    let mut scene = Scene::default();
+    scene.rendermode = Rendermode::_RayTrace;
+    scene.samples = 8;
+    let camera = scene.active_camera_mut();
+    camera.position = Vector::new(0., 0., 6.);
+    camera.exposure_time = 1e14;
+    camera.sensor.horizontal_res = 16;
+    camera.sensor.vertical_res = 12;
+    scene.push_object(Object {
+        meshes: vec![quad],
+        material: Arc::new(Diffuse::default()),
+        ..Object::default()
+    });
+    scene.push_simple_light(PointLight {
+        position: Vector::new(0., 0., 2.),
+        radiant_flux: incandescent_spectra(3000., 100.),
+        ..PointLight::default()
+    });
+
+    let film = render(&scene);
+    assert_eq!((film.width(), film.height()), (16, 12));
+    // the quad fills the middle of the frame and the corners look past it
+    assert_ne!(film.pixel(8, 6).0, [0, 0, 0]);
+    for (x, y) in [(0, 0), (15, 0), (0, 11), (15, 11)] {
+        assert_eq!(film.pixel(x, y).0, [0, 0, 0]);
+    }
+    // the render worked on a copy
+    assert!(scene.acceleration_structure.is_none());
+}
+
+#[test]
+fn renders_repeat_exactly_however_they_are_split_up() {
+    let mut scene = Scene::default();
+    scene.samples = 4;
+    scene.max_trace_depth = 2;
+    scene.seed = 11;
+    let camera = scene.active_camera_mut();
+    camera.position = Vector::new(0., 1., 6.);
+    camera.exposure_time = 1e14;
+    camera.sensor.horizontal_res = 24;
+    camera.sensor.vertical_res = 16;
+    for (position, size) in [
+        (Vector::new(0., 0., 0.), 3.),
+        (Vector::new(0., 0.5, 1.), 0.5),
+    ] {
+        let quad = Mesh::new(
+            vec![
+                Vertex::new(-size, 0., -size),
+                Vertex::new(size, 0., -size),
+                Vertex::new(size, 0., size),
+                Vertex::new(-size, 0., size),
+            ],
+            vec![vec![0, 2, 1], vec![0, 3, 2]],
+        );
+        scene.push_object(Object {
+            position,
+            meshes: vec![quad],
+            material: Arc::new(Diffuse::default()),
+            ..Object::default()
+        });
+    }
+    scene.push_simple_light(PointLight {
+        position: Vector::new(1., 3., 1.),
+        radiant_flux: incandescent_spectra(3000., 100.),
+        ..PointLight::default()
+    });
+
+    scene.rendermode = Rendermode::_RayTrace;
+    let single = render(&scene);
+    scene.rendermode = Rendermode::ThreadedRayTrace;
+    for (threads, tilesize) in [(1, 5), (3, 8), (0, 64)] {
+        scene.threads = threads;
+        scene.tilesize = tilesize;
+        assert!(render(&scene) == single);
+    }
+    // a different seed is a different picture
+    scene.seed = 12;
+    assert!(render(&scene) != single);
+}
+
+#[test]
+fn prisms_split_white_light_into_a_rainbow() {
+    let mut scene = load_scene_file("scenes/prism.toml".to_string()).unwrap();
+    scene.samples = 16;
+    let camera = scene.active_camera_mut();
+    camera.sensor.horizontal_res = 120;
+    camera.sensor.vertical_res = 80;
+    let film = render(&scene);
+
+    // the slit seen straight on is white, but through the prism each wavelength lands somewhere else
+    let pixels: Vec<[u8; 3]> = film.image().pixels().map(|pixel| pixel.0).collect();
+    let white = |&[r, g, b]: &[u8; 3]| r.min(g).min(b) > 150;
+    let red = |&[r, g, b]: &[u8; 3]| r > 80 && r / 2 > g.max(b);
+    let blue = |&[r, g, b]: &[u8; 3]| b > 80 && b / 2 > r.max(g);
+    assert!(pixels.iter().any(white));
+    assert!(pixels.iter().any(red));
+    assert!(pixels.iter().any(blue));
+}
+
+#[test]
+fn the_cornell_box_is_lit_by_its_ceiling_panel() {
+    let mut scene = load_scene_file("scenes/cornell.toml".to_string()).unwrap();
+    assert_eq!(scene.simple_lights().count(), 0);
+    scene.samples = 8;
+    let camera = scene.active_camera_mut();
+    camera.sensor.horizontal_res = 32;
+    camera.sensor.vertical_res = 32;
+    // fifteen times fewer pixels across, each one takes in light from 15² as much sensor and 15² as much sky
+    camera.exposure_time /= 15f32.powi(4);
+    let film = render(&scene);
+    let brightness = |x: u32, y: u32| film.pixel(x, y).0.iter().map(|&c| c as u32).sum::<u32>();
+    let row = |y: u32, xs: std::ops::Range<u32>| {
+        let count = xs.len() as u32;
+        xs.map(|x| brightness(x, y)).sum::<u32>() / count
+    };
+
+    // the panel itself glows
+    assert_eq!(film.pixel(16, 3).0, [255, 255, 255]);
+    // the ceiling around it only gets what bounces back up, the back wall faces the panel
+    assert!(row(1, 6..26) < 2 * row(10, 6..26) / 3);
+    // the front of the short block faces away from the panel, the tall block beside it catches the light
+    assert!(row(25, 17..24) < row(25, 9..15) / 2);
+}